@@ -0,0 +1,311 @@
+//! Front end for demodulating the CQPSK (LSM) signal transmitted by simulcast P25 sites.
+//!
+//! LSM carries each dibit as a change in carrier phase over one symbol period, using the
+//! π/4-DQPSK constellation. The dibit mapping gives each phase change the same sign and
+//! relative size as the C4FM frequency deviation of the same dibit:
+//!
+//! | Dibit | C4FM deviation | LSM phase change |
+//! |-------|----------------|------------------|
+//! | 01    | +1800 Hz       | +3π/4            |
+//! | 00    | +600 Hz        | +π/4             |
+//! | 10    | -600 Hz        | -π/4             |
+//! | 11    | -1800 Hz       | -3π/4            |
+//!
+//! Scaling the differential phase so the outer changes are ±1 produces a signal at the
+//! same levels as the C4FM discriminator output, which is fed into `DataUnitReceiver` in
+//! place of that output. Frame sync detection, symbol timing recovery, and symbol
+//! decisions then proceed unchanged.
+//!
+//! The front end performs the following steps:
+//!
+//! 1. Channel filter the input to the bandwidth of a P25 channel
+//! 2. Resample the input to `SAMPLE_RATE`, if necessary
+//! 3. Track and remove any carrier frequency offset
+//! 4. Measure the phase change across each symbol period
+
+use std;
+
+use baseband::iq::{ChannelFilter, Complex};
+use baseband::resample::Resampler;
+use consts::{SAMPLE_RATE, SYMBOL_PERIOD};
+
+/// Gain of the carrier frequency tracking loop.
+const CARRIER_GAIN: f32 = 0.0005;
+
+/// Maximum tracked carrier frequency offset, in radians per sample (±2400Hz.)
+const MAX_CARRIER: f32 = std::f32::consts::PI / 10.0;
+
+/// Phase change (radians) of the outer symbols, which are scaled to ±1 in the output.
+const MAX_PHASE: f32 = 3.0 * std::f32::consts::PI / 4.0;
+
+/// Tracks and removes a carrier frequency offset from the input signal.
+///
+/// A frequency offset adds a constant rotation to the phase change across every symbol
+/// period, so the error is measured as the distance from that phase change to the nearest
+/// point in the π/4-DQPSK constellation. This is done without symbol timing by raising the
+/// differential sample to the fourth power, which maps every constellation point to -1.
+/// Samples between symbol instants are spread symmetrically around the constellation
+/// points, so they contribute no net error.
+pub struct CarrierTracker {
+    /// Current phase (radians) of the local oscillator.
+    phase: f32,
+    /// Current frequency (radians per sample) of the local oscillator.
+    freq: f32,
+}
+
+impl CarrierTracker {
+    /// Create a new `CarrierTracker` with zero frequency offset.
+    pub fn new() -> CarrierTracker {
+        CarrierTracker {
+            phase: 0.0,
+            freq: 0.0,
+        }
+    }
+
+    /// Remove the current frequency offset from the given sample.
+    pub fn feed(&mut self, s: Complex) -> Complex {
+        use std::f32::consts::PI;
+
+        let out = s * Complex::from_phase(-self.phase);
+
+        self.phase += self.freq;
+
+        // Keep the phase bounded so precision isn't lost over time.
+        if self.phase > PI {
+            self.phase -= 2.0 * PI;
+        } else if self.phase < -PI {
+            self.phase += 2.0 * PI;
+        }
+
+        out
+    }
+
+    /// Update the frequency offset based on the given differential sample, the product of
+    /// a sample with the conjugate of the sample one symbol period earlier.
+    pub fn track(&mut self, diff: Complex) {
+        let sqr = diff * diff;
+        let quad = sqr * sqr;
+
+        // Phase error over one symbol period, in the range [-π/4, π/4].
+        let err = (quad * -1.0).arg() / 4.0;
+
+        self.freq = (self.freq + CARRIER_GAIN * err / SYMBOL_PERIOD as f32)
+            .max(-MAX_CARRIER).min(MAX_CARRIER);
+    }
+
+    /// Current frequency offset estimate, in radians per sample at `SAMPLE_RATE`.
+    pub fn freq(&self) -> f32 { self.freq }
+}
+
+/// Demodulates complex baseband samples of a CQPSK signal at some input rate into
+/// differential phase samples at `SAMPLE_RATE`, suitable for feeding into
+/// `DataUnitReceiver`.
+pub struct CqpskFrontEnd {
+    /// Channel filter at the input rate.
+    chan: ChannelFilter,
+    /// Converts input to `SAMPLE_RATE`, if necessary.
+    resampler: Option<Resampler<Complex>>,
+    /// Carrier frequency tracking loop.
+    carrier: CarrierTracker,
+    /// Ring buffer of the samples over the previous symbol period.
+    history: [Complex; SYMBOL_PERIOD],
+    /// Index of the sample one symbol period before the next input.
+    pos: usize,
+}
+
+impl CqpskFrontEnd {
+    /// Create a new `CqpskFrontEnd` for complex samples at the given rate (Hz.)
+    pub fn new(rate: usize) -> CqpskFrontEnd {
+        CqpskFrontEnd {
+            chan: ChannelFilter::new(rate),
+            resampler: if rate == SAMPLE_RATE {
+                None
+            } else {
+                Some(Resampler::new(rate))
+            },
+            carrier: CarrierTracker::new(),
+            history: [Complex::default(); SYMBOL_PERIOD],
+            pos: 0,
+        }
+    }
+
+    /// Feed in a complex sample at the input rate, passing each resulting differential
+    /// phase sample at `SAMPLE_RATE` to the given function.
+    pub fn feed<F: FnMut(f32)>(&mut self, s: Complex, mut out: F) {
+        let s = self.chan.feed(s);
+
+        let carrier = &mut self.carrier;
+        let history = &mut self.history;
+        let pos = &mut self.pos;
+
+        let mut demod = |s: Complex| {
+            let s = carrier.feed(s);
+            let diff = s * history[*pos].conj();
+
+            history[*pos] = s;
+            *pos = (*pos + 1) % SYMBOL_PERIOD;
+
+            carrier.track(diff);
+            out(diff.arg() / MAX_PHASE);
+        };
+
+        match self.resampler {
+            Some(ref mut r) => r.feed(s, demod),
+            None => demod(s),
+        }
+    }
+
+    /// Current carrier frequency offset estimate (Hz.)
+    pub fn carrier_offset(&self) -> f32 {
+        self.carrier.freq() * SAMPLE_RATE as f32 / (2.0 * std::f32::consts::PI)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI;
+    use bits::{Dibit, Dibits};
+    use baseband::decode::{Decoder, Decider};
+    use baseband::sync::SYNC_GENERATOR;
+    use message::data_unit::{DataUnitReceiver, ReceiverEvent};
+    use message::nid::{NetworkId, NetworkAccessCode, DataUnit};
+    use message::status::{StatusInterleaver, StatusCode};
+
+    /// Generate a raised-cosine shaped π/4-DQPSK signal for the given symbols, sampled at
+    /// the given rate and shifted by the given carrier offset (Hz.)
+    fn modulate(symbols: &[Dibit], rate: usize, offset: f32) -> Vec<Complex> {
+        fn pulse(t: f32) -> f32 {
+            const ALPHA: f32 = 0.2;
+
+            let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+            let denom = 1.0 - (2.0 * ALPHA * t).powi(2);
+
+            if denom.abs() < 1e-4 {
+                sinc * PI / 4.0
+            } else {
+                sinc * (PI * ALPHA * t).cos() / denom
+            }
+        }
+
+        let mut phase = 0.0;
+        let points: Vec<Complex> = symbols.iter().map(|d| {
+            phase += match d.bits() {
+                0b01 => 3.0 * PI / 4.0,
+                0b00 => PI / 4.0,
+                0b10 => -PI / 4.0,
+                _ => -3.0 * PI / 4.0,
+            };
+
+            Complex::from_phase(phase)
+        }).collect();
+
+        let period = rate as f32 / 4800.0;
+        let samples = (symbols.len() as f32 * period) as usize;
+
+        (0..samples).map(|n| {
+            let t = n as f32 / period;
+            let center = t.round() as isize;
+
+            let s = (center - 8..center + 8)
+                .filter(|&k| k >= 0 && (k as usize) < points.len())
+                .fold(Complex::default(), |sum, k| {
+                    sum + points[k as usize] * pulse(t - k as f32)
+                });
+
+            s * Complex::from_phase(2.0 * PI * offset * n as f32 / rate as f32) * 0.5
+        }).collect()
+    }
+
+    fn random(count: usize, mut seed: u32) -> Vec<Dibit> {
+        (0..count).map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            Dibit::new((seed >> 16) as u8 & 0b11)
+        }).collect()
+    }
+
+    #[test]
+    fn test_carrier_tracking() {
+        for &offset in &[-300.0, 0.0, 500.0] {
+            let symbols = random(2000, 0x1234);
+            let mut f = CqpskFrontEnd::new(48000);
+
+            for s in modulate(&symbols[..], 48000, offset) {
+                f.feed(s, |_| {});
+            }
+
+            assert!((f.carrier_offset() - offset).abs() < 10.0);
+        }
+    }
+
+    #[test]
+    fn test_symbols() {
+        let symbols = random(3000, 0x4321);
+        let mut f = CqpskFrontEnd::new(48000);
+        let mut out = vec![];
+
+        for s in modulate(&symbols[..], 48000, 200.0) {
+            f.feed(s, |x| out.push(x));
+        }
+
+        // Skip carrier acquisition and let the symbol clock settle.
+        let mut d = Decoder::new(Decider::new(2.0 / 3.0, 0.0, -2.0 / 3.0));
+        let decoded: Vec<Dibit> = out[1000 * SYMBOL_PERIOD..].iter()
+            .filter_map(|&s| d.feed(s))
+            .map(|s| s.dibit())
+            .skip(100)
+            .collect();
+
+        // Find where the decoded symbols line up with the transmitted ones.
+        let start = (1090..1110).find(|&k| symbols[k..k + 50] == decoded[..50]).unwrap();
+
+        let errs = decoded.iter().zip(symbols[start..].iter())
+            .take(1800)
+            .filter(|&(a, b)| a != b)
+            .count();
+
+        assert_eq!(errs, 0);
+    }
+
+    #[test]
+    fn test_data_unit_receiver() {
+        let nid = NetworkId::new(NetworkAccessCode::Default, DataUnit::TrunkingSignaling);
+
+        // Idle symbols let the carrier tracker settle before the first frame, and the
+        // symbol thresholds are smoothed over several frames.
+        let mut symbols = random(500, 0x5678);
+
+        for i in 0..4 {
+            symbols.extend(StatusInterleaver::new(
+                Dibits::new(SYNC_GENERATOR.iter().cloned())
+                    .chain(Dibits::new(nid.encode().iter().cloned()))
+                    .chain(random(200, i).into_iter()),
+                StatusCode::InboundIdle
+            ));
+        }
+
+        for &rate in &[48000, 96000] {
+            let mut f = CqpskFrontEnd::new(rate);
+            let mut recv = DataUnitReceiver::new();
+            let mut count = 0;
+
+            for s in modulate(&symbols[..], rate, -250.0) {
+                f.feed(s, |x| match recv.feed(x) {
+                    Some(Ok(ReceiverEvent::NetworkId(nid))) => {
+                        if nid.access_code == NetworkAccessCode::Default &&
+                            nid.data_unit == DataUnit::TrunkingSignaling
+                        {
+                            count += 1;
+                        }
+
+                        recv.resync();
+                    },
+                    Some(Err(_)) => recv.resync(),
+                    _ => {},
+                });
+            }
+
+            assert!(count >= 3);
+        }
+    }
+}
@@ -3,13 +3,42 @@
 use bits;
 use consts;
 
+/// Proportional gain of the symbol clock tracking loop.
+const TIMING_GAIN: f32 = 0.02;
+/// Integral gain of the symbol clock tracking loop, which lets the loop follow a constant
+/// drift between the transmitter and receiver sample clocks.
+const DRIFT_GAIN: f32 = 0.0001;
+/// Maximum tracked drift, in samples per symbol.
+const MAX_DRIFT: f32 = 0.05;
+/// Confidence assigned to a bit decided at the distance between adjacent thresholds
+/// from its threshold.
+const CONFIDENCE_SCALE: f32 = 8.0;
+
 /// Decodes symbol from sample at each symbol instant.
+///
+/// The symbol clock is continuously adjusted with a Gardner timing error detector, which
+/// examines the sample halfway between each pair of symbol instants. When the clock is
+/// correct, this sample falls on the zero crossing of any transition between symbols of
+/// opposite polarity.
 #[derive(Copy, Clone)]
 pub struct Decoder {
     /// Sample index into current symbol period.
     pos: usize,
     /// Decider used for decoding symbol at each symbol instant.
     decider: Decider,
+    /// Sample at the previous symbol instant.
+    prev: f32,
+    /// Sample halfway between the previous and current symbol instants, if it hasn't
+    /// been used to update the symbol clock yet.
+    mid: Option<f32>,
+    /// Sample at the current symbol instant.
+    cur: f32,
+    /// Accumulated timing error, in samples, not yet applied to the symbol clock.
+    phase: f32,
+    /// Estimated symbol clock drift, in samples per symbol.
+    drift: f32,
+    /// Total number of samples the symbol clock has been shifted by.
+    shift: isize,
 }
 
 impl Decoder {
@@ -22,19 +51,75 @@ impl Decoder {
             // sequence), so take that sample into account.
             pos: 1,
             decider: decider,
+            prev: 0.0,
+            mid: None,
+            cur: 0.0,
+            phase: 0.0,
+            drift: 0.0,
+            shift: 0,
         }
     }
 
     /// Examine the given sample and, based on the symbol clock, decode it into a symbol
     /// or do nothing.
-    pub fn feed(&mut self, s: f32) -> Option<bits::Dibit> {
+    pub fn feed(&mut self, s: f32) -> Option<bits::SoftDibit> {
         self.pos += 1;
         self.pos %= consts::SYMBOL_PERIOD;
 
-        if self.pos == 0 {
-            Some(self.decider.decide(s))
-        } else {
-            None
+        match self.pos {
+            0 => {
+                self.prev = self.cur;
+                self.cur = s;
+
+                Some(self.decider.decide_soft(s))
+            },
+            // The symbol clock can only be adjusted after the instant has passed.
+            1 => {
+                self.track();
+                None
+            },
+            p if p == consts::SYMBOL_PERIOD / 2 => {
+                self.mid = Some(s);
+                None
+            },
+            _ => None,
+        }
+    }
+
+    /// Current offset, in samples, of the symbol clock from the one derived from the
+    /// frame sync. A positive offset means symbols are being sampled later.
+    pub fn timing_offset(&self) -> f32 { self.shift as f32 + self.phase }
+
+    /// Update the symbol clock based on the timing error around the current symbol
+    /// instant.
+    fn track(&mut self) {
+        // After a repeated sample, the symbol clock passes through here again without a
+        // new symbol instant.
+        let mid = match self.mid.take() {
+            Some(mid) => mid,
+            None => return,
+        };
+
+        let err = match self.decider.timing_error(self.prev, mid, self.cur) {
+            Some(err) => err,
+            None => return,
+        };
+
+        self.drift = (self.drift + DRIFT_GAIN * err).max(-MAX_DRIFT).min(MAX_DRIFT);
+        self.phase += TIMING_GAIN * err + self.drift;
+
+        // Only whole samples can be skipped or repeated, so apply the accumulated error
+        // once it reaches a full sample.
+        if self.phase >= 1.0 {
+            // Repeat a sample, so the next instant comes one sample later.
+            self.pos = 0;
+            self.phase -= 1.0;
+            self.shift += 1;
+        } else if self.phase <= -1.0 {
+            // Skip a sample, so the next instant comes one sample earlier.
+            self.pos = 2;
+            self.phase += 1.0;
+            self.shift -= 1;
         }
     }
 }
@@ -73,6 +158,43 @@ impl Decider {
             bits::Dibit::new(0b11)
         }
     }
+
+    /// Decide which symbol the given sample looks closest to, and measure the confidence
+    /// in each bit of the decision by the distance from the sample to the threshold that
+    /// decided it. The MSB is decided by the middle threshold and the LSB by the upper or
+    /// lower threshold.
+    pub fn decide_soft(&self, sample: f32) -> bits::SoftDibit {
+        let (outer, spacing) = if sample > self.mthresh {
+            (self.pthresh, self.pthresh - self.mthresh)
+        } else {
+            (self.nthresh, self.mthresh - self.nthresh)
+        };
+
+        let confidence = |dist: f32| {
+            (dist.abs() / spacing * CONFIDENCE_SCALE).round() as u8
+        };
+
+        bits::SoftDibit::new(self.decide(sample),
+                             confidence(sample - self.mthresh),
+                             confidence(sample - outer))
+    }
+
+    /// Calculate the timing error from the samples at the previous and current symbol
+    /// instants and the sample halfway between them. The error is positive if symbols are
+    /// being sampled too early and negative if too late. Return `None` if the thresholds
+    /// are degenerate.
+    fn timing_error(&self, prev: f32, mid: f32, cur: f32) -> Option<f32> {
+        // Distance between the middle threshold and the inner symbol levels.
+        let unit = (self.pthresh - self.mthresh) / 2.0;
+
+        if unit.is_nan() || unit <= 0.0 {
+            return None;
+        }
+
+        let norm = |s: f32| (s - self.mthresh) / unit;
+
+        Some(norm(mid) * (norm(prev) - norm(cur)))
+    }
 }
 
 #[cfg(test)]
@@ -89,6 +211,31 @@ mod test {
         assert_eq!(d.decide(-0.244).bits(), 0b11);
     }
 
+    #[test]
+    fn test_decide_soft() {
+        let d = Decider::new(0.1, 0.0, -0.1);
+
+        let s = d.decide_soft(0.15);
+        assert_eq!(s.dibit().bits(), 0b01);
+        assert_eq!(s.hi(), 12);
+        assert_eq!(s.lo(), 4);
+
+        let s = d.decide_soft(0.05);
+        assert_eq!(s.dibit().bits(), 0b00);
+        assert_eq!(s.hi(), 4);
+        assert_eq!(s.lo(), 4);
+
+        let s = d.decide_soft(-0.01);
+        assert_eq!(s.dibit().bits(), 0b10);
+        assert_eq!(s.hi(), 1);
+        assert_eq!(s.lo(), 7);
+
+        let s = d.decide_soft(-0.5);
+        assert_eq!(s.dibit().bits(), 0b11);
+        assert_eq!(s.hi(), bits::MAX_CONFIDENCE);
+        assert_eq!(s.lo(), bits::MAX_CONFIDENCE);
+    }
+
     #[test]
     fn test_decoder() {
         let mut d = Decoder::new(Decider::new(0.0, 0.0, 0.0));
@@ -114,4 +261,80 @@ mod test {
         assert!(d.feed(0.1816711425781250).is_none());
         assert!(d.feed(0.1799926757812500).is_some());
     }
+
+    /// Generate a raised-cosine shaped baseband signal for the given symbols, sampled
+    /// with a clock that runs at the given rate relative to the nominal one.
+    fn shaped(symbols: &[bits::Dibit], rate: f32) -> Vec<f32> {
+        use std::f32::consts::PI;
+
+        fn pulse(t: f32) -> f32 {
+            const ALPHA: f32 = 0.2;
+
+            let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+            let denom = 1.0 - (2.0 * ALPHA * t).powi(2);
+
+            if denom.abs() < 1e-4 {
+                sinc * PI / 4.0
+            } else {
+                sinc * (PI * ALPHA * t).cos() / denom
+            }
+        }
+
+        let period = consts::SYMBOL_PERIOD as f32;
+        let samples = (symbols.len() as f32 * period / rate) as usize;
+
+        (0..samples).map(|n| {
+            let t = n as f32 * rate / period;
+            let center = t.round() as isize;
+
+            (center - 8..center + 8)
+                .filter(|&k| k >= 0 && (k as usize) < symbols.len())
+                .map(|k| {
+                    let level = match symbols[k as usize].bits() {
+                        0b01 => 3.0,
+                        0b00 => 1.0,
+                        0b10 => -1.0,
+                        _ => -3.0,
+                    };
+
+                    0.06 * level * pulse(t - k as f32)
+                })
+                .sum()
+        }).collect()
+    }
+
+    #[test]
+    fn test_timing_recovery() {
+        let mut seed = 0x1234u32;
+        let symbols: Vec<bits::Dibit> = (0..3000).map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            bits::Dibit::new((seed >> 16) as u8 & 0b11)
+        }).collect();
+
+        // Sample clock is 500ppm fast or slow relative to the transmitter, drifting by 1.5
+        // symbols over the run.
+        for &rate in &[1.0005, 0.9995] {
+            let samples = shaped(&symbols[..], rate);
+            let mut d = Decoder::new(Decider::new(0.12, 0.0, -0.12));
+
+            // Start just after the first symbol instant, as if after a frame sync.
+            let decoded: Vec<bits::Dibit> = samples[2..].iter()
+                .filter_map(|&s| d.feed(s))
+                .map(|s| s.dibit())
+                .collect();
+
+            let errs = decoded.iter().zip(symbols[1..].iter())
+                .filter(|&(a, b)| a != b)
+                .count();
+
+            assert_eq!(errs, 0);
+            assert!(decoded.len() >= symbols.len() - 2);
+
+            if rate > 1.0 {
+                assert!(d.timing_offset() < -12.0);
+            } else {
+                assert!(d.timing_offset() > 12.0);
+            }
+        }
+    }
 }
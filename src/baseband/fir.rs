@@ -0,0 +1,115 @@
+//! FIR filters with coefficients designed at runtime, for filters that depend on the
+//! input sample rate.
+
+use std::ops::{Add, Mul};
+
+/// Calculate the number of taps needed for a windowed-sinc lowpass filter with the given
+/// transition width (normalized to the sample rate.)
+pub fn lowpass_len(transition: f32) -> usize {
+    assert!(transition > 0.0);
+
+    // Transition width of the Blackman window.
+    (5.5 / transition).ceil() as usize | 1
+}
+
+/// Design a windowed-sinc lowpass filter with the given cutoff frequency (normalized to
+/// the sample rate) and number of taps. The filter has unity gain at DC.
+pub fn lowpass(cutoff: f32, len: usize) -> Vec<f32> {
+    use std::f32::consts::PI;
+
+    assert!(cutoff > 0.0 && cutoff < 0.5);
+    assert!(len > 0);
+
+    let center = (len - 1) as f32 / 2.0;
+
+    (0..len).map(|n| {
+        let t = n as f32 - center;
+
+        let sinc = if t == 0.0 {
+            1.0
+        } else {
+            (2.0 * PI * cutoff * t).sin() / (2.0 * PI * cutoff * t)
+        };
+
+        2.0 * cutoff * sinc * blackman(n, len)
+    }).collect()
+}
+
+/// Calculate the Blackman window coefficient at the given index in a window of the given
+/// length.
+fn blackman(n: usize, len: usize) -> f32 {
+    use std::f32::consts::PI;
+
+    if len == 1 {
+        return 1.0;
+    }
+
+    let x = n as f32 / (len - 1) as f32;
+
+    0.42 - 0.5 * (2.0 * PI * x).cos() + 0.08 * (4.0 * PI * x).cos()
+}
+
+/// FIR filter over samples of type `T` with runtime coefficients.
+pub struct Fir<T> {
+    /// Filter coefficients.
+    taps: Vec<f32>,
+    /// Ring buffer of the most recent input samples.
+    history: Vec<T>,
+    /// Index of the most recent sample in `history`.
+    pos: usize,
+}
+
+impl<T> Fir<T> where T: Copy + Default + Add<Output = T> + Mul<f32, Output = T> {
+    /// Create a new `Fir` with the given coefficients and an empty history.
+    pub fn new(taps: Vec<f32>) -> Fir<T> {
+        assert!(!taps.is_empty());
+
+        Fir {
+            history: vec![T::default(); taps.len()],
+            taps: taps,
+            pos: 0,
+        }
+    }
+
+    /// Add the given sample to the history and calculate the current filter output.
+    pub fn feed(&mut self, s: T) -> T {
+        let len = self.history.len();
+
+        self.pos = (self.pos + 1) % len;
+        self.history[self.pos] = s;
+
+        self.taps.iter().enumerate().fold(T::default(), |sum, (k, &c)| {
+            sum + self.history[(self.pos + len - k) % len] * c
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lowpass() {
+        let taps = lowpass(0.1, lowpass_len(0.05));
+        assert_eq!(taps.len(), 111);
+
+        let sum: f32 = taps.iter().sum();
+        assert!((sum - 1.0).abs() < 1e-3);
+
+        for i in 0..taps.len() / 2 {
+            assert!((taps[i] - taps[taps.len() - i - 1]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_fir() {
+        let mut f = Fir::new(vec![0.5, 0.25, 0.25]);
+
+        assert_eq!(f.feed(1.0), 0.5);
+        assert_eq!(f.feed(2.0), 1.25);
+        assert_eq!(f.feed(4.0), 2.75);
+        assert_eq!(f.feed(0.0), 1.5);
+        assert_eq!(f.feed(0.0), 1.0);
+        assert_eq!(f.feed(0.0), 0.0);
+    }
+}
@@ -0,0 +1,258 @@
+//! Front end for demodulating complex (I/Q) baseband samples into the C4FM discriminator
+//! signal consumed by the receivers.
+//!
+//! The front end performs the following steps:
+//!
+//! 1. Channel filter the input to the bandwidth of a P25 channel
+//! 2. Demodulate the instantaneous frequency with an FM discriminator
+//! 3. Resample the discriminator output to `SAMPLE_RATE`, if necessary
+//! 4. Apply the C4FM receive (integrate-and-dump) filter, which undoes the inverse-sinc
+//!    shaping applied by the transmitter
+
+use std;
+use std::ops::{Add, Sub, Mul};
+
+use static_fir::FirFilter;
+
+use baseband::fir::{self, Fir};
+use baseband::resample::Resampler;
+use consts::{SAMPLE_RATE, SYMBOL_PERIOD};
+
+/// Cutoff frequency (Hz) of the channel filter.
+const CHANNEL_CUTOFF: f32 = 6250.0;
+
+/// Width (Hz) of the channel filter transition band.
+const CHANNEL_TRANSITION: f32 = 3000.0;
+
+/// Frequency deviation (Hz) of the outer C4FM symbols, which are scaled to ±1 in the
+/// discriminator output.
+const MAX_DEVIATION: f32 = 1800.0;
+
+/// Complex baseband sample.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Complex {
+    /// In-phase (real) part.
+    pub re: f32,
+    /// Quadrature (imaginary) part.
+    pub im: f32,
+}
+
+impl Complex {
+    /// Create a new `Complex` with the given real and imaginary parts.
+    pub fn new(re: f32, im: f32) -> Complex {
+        Complex {
+            re: re,
+            im: im,
+        }
+    }
+
+    /// Create a new unit-magnitude `Complex` with the given phase (radians.)
+    pub fn from_phase(phase: f32) -> Complex {
+        Complex::new(phase.cos(), phase.sin())
+    }
+
+    /// Complex conjugate.
+    pub fn conj(&self) -> Complex { Complex::new(self.re, -self.im) }
+
+    /// Squared magnitude.
+    pub fn norm_sqr(&self) -> f32 { self.re * self.re + self.im * self.im }
+
+    /// Phase angle (radians), in the range [-π, π].
+    pub fn arg(&self) -> f32 { self.im.atan2(self.re) }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im,
+                     self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Mul<f32> for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: f32) -> Complex {
+        Complex::new(self.re * other, self.im * other)
+    }
+}
+
+/// Lowpass filter that rejects signals outside the P25 channel.
+pub struct ChannelFilter(Fir<Complex>);
+
+impl ChannelFilter {
+    /// Create a new `ChannelFilter` for samples at the given rate (Hz.)
+    pub fn new(rate: usize) -> ChannelFilter {
+        let rate = rate as f32;
+
+        ChannelFilter(Fir::new(fir::lowpass(CHANNEL_CUTOFF / rate,
+            fir::lowpass_len(CHANNEL_TRANSITION / rate))))
+    }
+
+    /// Filter the given sample.
+    pub fn feed(&mut self, s: Complex) -> Complex { self.0.feed(s) }
+}
+
+/// FM discriminator that measures the frequency difference between successive samples.
+pub struct Discriminator {
+    /// Previous sample.
+    prev: Complex,
+    /// Scales the phase difference between samples to the output level.
+    scale: f32,
+}
+
+impl Discriminator {
+    /// Create a new `Discriminator` for samples at the given rate (Hz.) The output is
+    /// scaled so the outer C4FM symbol deviations are ±1.
+    pub fn new(rate: usize) -> Discriminator {
+        Discriminator {
+            prev: Complex::default(),
+            scale: rate as f32 / (2.0 * std::f32::consts::PI * MAX_DEVIATION),
+        }
+    }
+
+    /// Demodulate the given sample.
+    pub fn feed(&mut self, s: Complex) -> f32 {
+        let diff = s * self.prev.conj();
+        self.prev = s;
+
+        diff.arg() * self.scale
+    }
+}
+
+// Integrate-and-dump filter over a single symbol period, which is the receive filter
+// specified for C4FM.
+impl_fir!(C4fmReceiveFilter, f32, SYMBOL_PERIOD, [
+    0.1,
+    0.1,
+    0.1,
+    0.1,
+    0.1,
+    0.1,
+    0.1,
+    0.1,
+    0.1,
+    0.1,
+]);
+
+/// Demodulates complex baseband samples at some input rate into discriminator samples at
+/// `SAMPLE_RATE`.
+pub struct C4fmFrontEnd {
+    /// Channel filter at the input rate.
+    chan: ChannelFilter,
+    /// FM discriminator at the input rate.
+    disc: Discriminator,
+    /// Converts discriminator output to `SAMPLE_RATE`, if necessary.
+    resampler: Option<Resampler>,
+    /// C4FM receive filter at `SAMPLE_RATE`.
+    recv: FirFilter<C4fmReceiveFilter>,
+}
+
+impl C4fmFrontEnd {
+    /// Create a new `C4fmFrontEnd` for complex samples at the given rate (Hz.)
+    pub fn new(rate: usize) -> C4fmFrontEnd {
+        C4fmFrontEnd {
+            chan: ChannelFilter::new(rate),
+            disc: Discriminator::new(rate),
+            resampler: if rate == SAMPLE_RATE {
+                None
+            } else {
+                Some(Resampler::new(rate))
+            },
+            recv: FirFilter::new(),
+        }
+    }
+
+    /// Feed in a complex sample at the input rate, passing each resulting discriminator
+    /// sample at `SAMPLE_RATE` to the given function.
+    pub fn feed<F: FnMut(f32)>(&mut self, s: Complex, mut out: F) {
+        let s = self.disc.feed(self.chan.feed(s));
+        let recv = &mut self.recv;
+
+        match self.resampler {
+            Some(ref mut r) => r.feed(s, |x| out(recv.feed(x))),
+            None => out(recv.feed(s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_complex() {
+        let a = Complex::new(1.0, 2.0);
+        let b = Complex::new(3.0, -1.0);
+
+        assert_eq!(a + b, Complex::new(4.0, 1.0));
+        assert_eq!(a - b, Complex::new(-2.0, 3.0));
+        assert_eq!(a * b, Complex::new(5.0, 5.0));
+        assert_eq!(a * 2.0, Complex::new(2.0, 4.0));
+        assert_eq!(a.conj(), Complex::new(1.0, -2.0));
+        assert_eq!(a.norm_sqr(), 5.0);
+        assert!((Complex::new(0.0, 1.0).arg() - PI / 2.0).abs() < 1e-6);
+        assert!((Complex::from_phase(0.5).arg() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_discriminator() {
+        let mut d = Discriminator::new(48000);
+
+        for n in 0..100 {
+            let s = Complex::from_phase(2.0 * PI * 900.0 * n as f32 / 48000.0);
+            let x = d.feed(s);
+
+            if n > 0 {
+                assert!((x - 0.5).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_front_end() {
+        // Frequency modulate a slow tone at 2/3 of the maximum deviation.
+        const TONE: f32 = 200.0;
+        const DEV: f32 = 1200.0;
+
+        for &rate in &[48000, 96000, 240000] {
+            let mut f = C4fmFrontEnd::new(rate);
+            let mut phase = 0.0;
+            let mut out = vec![];
+
+            for n in 0..rate / 10 {
+                let t = n as f32 / rate as f32;
+                let freq = DEV * (2.0 * PI * TONE * t).sin();
+
+                phase += 2.0 * PI * freq / rate as f32;
+                f.feed(Complex::from_phase(phase), |s| out.push(s));
+            }
+
+            assert!((out.len() as isize - SAMPLE_RATE as isize / 10).abs() <= 1);
+
+            // Skip past filter startup and find the peak deviation.
+            let peak = out[SAMPLE_RATE / 50..].iter()
+                .fold(0.0f32, |m, &s| m.max(s.abs()));
+
+            assert!((peak - DEV / MAX_DEVIATION).abs() < 0.01);
+        }
+    }
+}
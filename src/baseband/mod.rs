@@ -1,5 +1,9 @@
 //! Sychronization and symbol encoding/decoding of C4FM signal used by P25.
 
+pub mod cqpsk;
 pub mod decode;
 pub mod encode;
+pub mod fir;
+pub mod iq;
+pub mod resample;
 pub mod sync;
@@ -0,0 +1,171 @@
+//! Rational resampling of baseband input to the internal sample rate.
+//!
+//! The symbol decoding and frame sync stages are designed around `SAMPLE_RATE`, so input
+//! at any other rate is first brought to that rate with a polyphase interpolating and
+//! decimating lowpass filter.
+
+use std::ops::{Add, Mul};
+
+use baseband::fir;
+use consts::SAMPLE_RATE;
+
+/// Cutoff frequency (Hz) of the anti-aliasing/anti-imaging filter. This is well above
+/// the bandwidth of a C4FM discriminator signal. It's lowered for input rates too low to
+/// hold the full transition band below their Nyquist frequency.
+const CUTOFF: f32 = 6000.0;
+
+/// Width (Hz) of the filter transition band.
+const TRANSITION: f32 = 6000.0;
+
+/// Converts baseband samples at some input rate to `SAMPLE_RATE` by interpolating by an
+/// integer factor, lowpass filtering, and decimating by another integer factor.
+pub struct Resampler<T = f32> {
+    /// Interpolation factor.
+    up: usize,
+    /// Decimation factor.
+    down: usize,
+    /// Lowpass filter coefficients at the interpolated rate.
+    taps: Vec<f32>,
+    /// Ring buffer of the most recent input samples.
+    history: Vec<T>,
+    /// Index of the most recent sample in `history`.
+    pos: usize,
+    /// Polyphase branch of the next output sample.
+    phase: usize,
+}
+
+impl<T> Resampler<T> where T: Copy + Default + Add<Output = T> + Mul<f32, Output = T> {
+    /// Create a new `Resampler` that converts samples at the given rate (Hz) to
+    /// `SAMPLE_RATE`.
+    pub fn new(rate: usize) -> Resampler<T> {
+        assert!(rate > 0);
+
+        let div = gcd(rate, SAMPLE_RATE);
+        let up = SAMPLE_RATE / div;
+        let down = rate / div;
+
+        // Keep the stopband above the input Nyquist frequency, so images of the input
+        // spectrum are rejected.
+        let cutoff = CUTOFF.min(rate as f32 / 2.0 - TRANSITION / 2.0);
+        assert!(cutoff > 0.0);
+
+        // Filter is designed at the interpolated rate.
+        let proto = (rate * up) as f32;
+
+        // Round the length up to fill every polyphase branch.
+        let branch = fir::lowpass_len(TRANSITION / proto) / up + 1;
+
+        // Make up for the zeros inserted during interpolation.
+        let taps = fir::lowpass(cutoff / proto, branch * up).into_iter()
+            .map(|c| c * up as f32)
+            .collect();
+
+        Resampler {
+            up: up,
+            down: down,
+            taps: taps,
+            history: vec![T::default(); branch],
+            pos: 0,
+            phase: 0,
+        }
+    }
+
+    /// Feed in a sample at the input rate, passing each resulting sample at
+    /// `SAMPLE_RATE` to the given function.
+    pub fn feed<F: FnMut(T)>(&mut self, s: T, mut out: F) {
+        let len = self.history.len();
+
+        self.pos = (self.pos + 1) % len;
+        self.history[self.pos] = s;
+
+        while self.phase < self.up {
+            // Convolve the branch of taps for this phase with the input history, newest
+            // sample first.
+            let sum = (0..len).fold(T::default(), |sum, k| {
+                sum + self.history[(self.pos + len - k) % len] *
+                    self.taps[self.phase + k * self.up]
+            });
+
+            out(sum);
+
+            self.phase += self.down;
+        }
+
+        self.phase -= self.up;
+    }
+}
+
+/// Calculate the greatest common divisor of the given numbers.
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(48000, 25000), 1000);
+        assert_eq!(gcd(48000, 96000), 48000);
+        assert_eq!(gcd(48000, 24000), 24000);
+        assert_eq!(gcd(48000, 48000), 48000);
+    }
+
+    #[test]
+    fn test_resample() {
+        const FREQ: f32 = 1000.0;
+
+        for &rate in &[24000, 25000, 44100, 48000, 96000] {
+            let mut r = Resampler::new(rate);
+            let mut out = vec![];
+
+            for n in 0..rate / 10 {
+                let t = n as f32 / rate as f32;
+                r.feed((2.0 * PI * FREQ * t).sin(), |s| out.push(s));
+            }
+
+            // Output should have the same duration as the input.
+            assert!((out.len() as isize - SAMPLE_RATE as isize / 10).abs() <= 1);
+
+            // Delay through the filter, in seconds.
+            let delay = (r.taps.len() - 1) as f32 / 2.0 / (rate * r.up) as f32;
+
+            // Skip past the filter startup.
+            for (n, &s) in out.iter().enumerate().skip(SAMPLE_RATE / 100) {
+                let t = n as f32 / SAMPLE_RATE as f32 - delay;
+                assert!((s - (2.0 * PI * FREQ * t).sin()).abs() < 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn test_low_rate() {
+        const RATE: usize = 8000;
+
+        // Amplitude of the given frequency in the given samples at `SAMPLE_RATE`.
+        fn amplitude(samples: &[f32], freq: f32) -> f32 {
+            let (re, im) = samples.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &s)| {
+                let w = 2.0 * PI * freq * n as f32 / SAMPLE_RATE as f32;
+                (re + s * w.cos(), im + s * w.sin())
+            });
+
+            2.0 * (re * re + im * im).sqrt() / samples.len() as f32
+        }
+
+        let mut r = Resampler::new(RATE);
+        let mut out = vec![];
+
+        for n in 0..RATE {
+            let t = n as f32 / RATE as f32;
+            r.feed((2.0 * PI * 500.0 * t).sin(), |s| out.push(s));
+        }
+
+        let out = &out[SAMPLE_RATE / 10..];
+
+        // The tone passes through, but its image around the input sample rate doesn't.
+        assert!(amplitude(out, 500.0) > 0.5);
+        assert!(amplitude(out, 7500.0) < 0.01);
+    }
+}
@@ -71,6 +71,49 @@ impl Dibit {
     pub fn lo(&self) -> u8 { self.0 & 1 }
 }
 
+/// Maximum confidence in a bit of a `SoftDibit`.
+pub const MAX_CONFIDENCE: u8 = 15;
+
+/// A dibit along with the confidence in each of its bits, measured by how far the
+/// received symbol fell from the decision threshold that determined that bit.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub struct SoftDibit {
+    /// Decided dibit.
+    dibit: Dibit,
+    /// Confidence in the MSB.
+    hi: u8,
+    /// Confidence in the LSB.
+    lo: u8,
+}
+
+impl SoftDibit {
+    /// Construct a new `SoftDibit` from the given decided dibit and confidences in its
+    /// MSB and LSB, which are clamped to `MAX_CONFIDENCE`.
+    pub fn new(dibit: Dibit, hi: u8, lo: u8) -> SoftDibit {
+        SoftDibit {
+            dibit: dibit,
+            hi: std::cmp::min(hi, MAX_CONFIDENCE),
+            lo: std::cmp::min(lo, MAX_CONFIDENCE),
+        }
+    }
+
+    /// Get the decided dibit.
+    pub fn dibit(&self) -> Dibit { self.dibit }
+    /// Get the confidence in the MSB.
+    pub fn hi(&self) -> u8 { self.hi }
+    /// Get the confidence in the LSB.
+    pub fn lo(&self) -> u8 { self.lo }
+}
+
+/// A hard-decided dibit is treated as having equal confidence in every bit.
+impl From<Dibit> for SoftDibit {
+    fn from(dibit: Dibit) -> SoftDibit { SoftDibit::new(dibit, 1, 1) }
+}
+
+impl From<SoftDibit> for Dibit {
+    fn from(soft: SoftDibit) -> Dibit { soft.dibit }
+}
+
 /// Parameters for `Dibits` iterator.
 pub struct DibitParams;
 
@@ -265,6 +308,42 @@ impl<P, T> Iterator for SubByteIter<P, T> where
     }
 }
 
+/// Iterates over the dibits of a coded word, MSB to LSB.
+pub struct WordDibits {
+    /// Word to split into dibits.
+    word: u64,
+    /// Number of dibits remaining in the word.
+    remain: usize,
+}
+
+impl WordDibits {
+    /// Construct a new `WordDibits` over the given word, made up of the given number of
+    /// dibits in the LSB position.
+    pub fn new(word: u64, dibits: usize) -> WordDibits {
+        assert!(dibits <= 32);
+        assert!(dibits == 32 || word >> (dibits * 2) == 0);
+
+        WordDibits {
+            word: word,
+            remain: dibits,
+        }
+    }
+}
+
+impl Iterator for WordDibits {
+    type Item = Dibit;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remain == 0 {
+            return None;
+        }
+
+        self.remain -= 1;
+
+        Some(Dibit::new((self.word >> (self.remain * 2)) as u8 & 0b11))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -304,6 +383,17 @@ mod test {
         assert!(d.next().is_none());
     }
 
+    #[test]
+    fn test_word_dibits() {
+        let d: Vec<u8> = WordDibits::new(0b100111, 3).map(|d| d.bits()).collect();
+        assert_eq!(d, &[0b10, 0b01, 0b11]);
+
+        let d: Vec<u8> = WordDibits::new(0b1, 4).map(|d| d.bits()).collect();
+        assert_eq!(d, &[0b00, 0b00, 0b00, 0b01]);
+
+        assert!(WordDibits::new(0, 0).next().is_none());
+    }
+
     #[test]
     fn test_dibit_bytes() {
         let dibits = [
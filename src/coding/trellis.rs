@@ -20,10 +20,12 @@ pub type DibitFSM = TrellisFSM<DibitStates>;
 /// 3/4-rate convolutional ("trellis") code state machine.
 pub type TribitFSM = TrellisFSM<TribitStates>;
 
-/// Half-rate convolution ("trellis") code decoder.
+/// Half-rate convolution ("trellis") code decoder. Decoding uses soft decisions when
+/// the source yields `SoftDibit`s.
 pub type DibitDecoder<T> = ViterbiDecoder<DibitStates, DibitHistory, DibitWalks, T>;
 
-/// 3/4-rate convolution ("trellis") code decoder.
+/// 3/4-rate convolution ("trellis") code decoder. Decoding uses soft decisions when the
+/// source yields `SoftDibit`s.
 pub type TribitDecoder<T> = ViterbiDecoder<TribitStates, TribitHistory, TribitWalks, T>;
 
 pub trait States {
@@ -71,6 +73,46 @@ pub trait States {
     }
 }
 
+/// A received symbol that can be measured against the dibits on a state transition.
+pub trait Received: Copy {
+    /// Calculate the distance between the given received symbol pair and the given
+    /// dibit pair on a state transition.
+    fn distance(input: (Self, Self), pair: (bits::Dibit, bits::Dibit)) -> usize;
+
+    /// Get the hard-decided dibit of the received symbol.
+    fn dibit(&self) -> bits::Dibit;
+
+    /// Count the bits that differ between the given received symbol pair and the given
+    /// dibit pair on a state transition.
+    fn errors(input: (Self, Self), pair: (bits::Dibit, bits::Dibit)) -> usize {
+        Edge::new((input.0.dibit(), input.1.dibit())).distance(Edge::new(pair))
+    }
+}
+
+/// Hard decisions are measured by Hamming distance.
+impl Received for bits::Dibit {
+    fn distance(input: (Self, Self), pair: (bits::Dibit, bits::Dibit)) -> usize {
+        Edge::new(input).distance(Edge::new(pair))
+    }
+
+    fn dibit(&self) -> bits::Dibit { *self }
+}
+
+/// Soft decisions are measured by the total confidence of the bits that differ, so bits
+/// decided near a threshold count for less than those decided far from one.
+impl Received for bits::SoftDibit {
+    fn distance(input: (Self, Self), pair: (bits::Dibit, bits::Dibit)) -> usize {
+        let weigh = |s: bits::SoftDibit, d: bits::Dibit| {
+            (s.dibit().hi() ^ d.hi()) as usize * s.hi() as usize +
+                (s.dibit().lo() ^ d.lo()) as usize * s.lo() as usize
+        };
+
+        weigh(input.0, pair.0) + weigh(input.1, pair.1)
+    }
+
+    fn dibit(&self) -> bits::Dibit { bits::SoftDibit::dibit(self) }
+}
+
 /// Half-rate state machine (dibit input).
 pub struct DibitStates;
 
@@ -232,7 +274,7 @@ impl_walks!(TribitWalks, TribitHistory, 8);
 /// Decodes a received convolutional code dibit stream to a nearby codeword using the
 /// truncated Viterbi algorithm.
 pub struct ViterbiDecoder<S, H, W, T> where
-    S: States, H: WalkHistory, W: Walks<H>, T: Iterator<Item = bits::Dibit>
+    S: States, H: WalkHistory, W: Walks<H>, T: Iterator, T::Item: Received
 {
     states: std::marker::PhantomData<S>,
     history: std::marker::PhantomData<H>,
@@ -247,7 +289,7 @@ pub struct ViterbiDecoder<S, H, W, T> where
 }
 
 impl<S, H, W, T> ViterbiDecoder<S, H, W, T> where
-    S: States, H: WalkHistory, W: Walks<H>, T: Iterator<Item = bits::Dibit>
+    S: States, H: WalkHistory, W: Walks<H>, T: Iterator, T::Item: Received
 {
     /// Construct a new `ViterbiDecoder` over the given dibit source.
     pub fn new(src: T) -> ViterbiDecoder<S, H, W, T> {
@@ -277,11 +319,11 @@ impl<S, H, W, T> ViterbiDecoder<S, H, W, T> where
     }
 
     fn step(&mut self) -> bool {
-        let input = Edge::new(match (self.src.next(), self.src.next()) {
+        let input = match (self.src.next(), self.src.next()) {
             (Some(hi), Some(lo)) => (hi, lo),
             (None, None) => return false,
             _ => panic!("dibits ended on boundary"),
-        });
+        };
 
         self.remain += 1;
         self.switch_walk();
@@ -295,19 +337,31 @@ impl<S, H, W, T> ViterbiDecoder<S, H, W, T> where
     }
 
     ///
-    fn search(&self, state: usize, input: Edge) -> (Walk<H>, bool) {
+    fn search(&self, state: usize, input: (T::Item, T::Item)) -> (Walk<H>, bool) {
         self.walks[self.prev].iter()
             .enumerate()
-            .map(|(i, w)| (Edge::new(S::pair(i, state)), w))
-            .fold((Walk::default(), false), |(walk, amb), (e, w)| {
-                match w.distance.checked_add(input.distance(e)) {
-                    Some(sum) if sum < walk.distance => (walk.replace(&w, sum), false),
-                    Some(sum) if sum == walk.distance => (walk.combine(&w, sum), true),
+            .map(|(i, w)| (S::pair(i, state), w))
+            .fold((Walk::default(), false), |(walk, amb), (pair, w)| {
+                let errs = w.errors + T::Item::errors(input, pair);
+
+                match w.distance.checked_add(T::Item::distance(input, pair)) {
+                    Some(sum) if sum < walk.distance =>
+                        (walk.replace(&w, sum, errs), false),
+                    Some(sum) if sum == walk.distance =>
+                        (walk.combine(&w, sum, errs), true),
                     _ => (walk, amb),
                 }
             })
     }
 
+    /// Number of received bits corrected so far, along the most likely walk through the
+    /// trellis. Once all symbols have been decoded, this covers the entire codeword.
+    pub fn errors(&self) -> usize {
+        self.walks[self.cur].iter()
+            .min_by_key(|w| w.distance)
+            .map_or(0, |w| w.errors)
+    }
+
     ///
     fn decode(&self) -> Decision {
         self.walks[self.cur].iter().fold(Ambiguous(std::usize::MAX), |s, w| {
@@ -323,7 +377,7 @@ impl<S, H, W, T> ViterbiDecoder<S, H, W, T> where
 }
 
 impl<S, H, W, T> Iterator for ViterbiDecoder<S, H, W, T> where
-    S: States, H: WalkHistory, W: Walks<H>, T: Iterator<Item = bits::Dibit>
+    S: States, H: WalkHistory, W: Walks<H>, T: Iterator, T::Item: Received
 {
     type Item = Result<S::Symbol, ()>;
 
@@ -353,6 +407,8 @@ enum Decision {
 pub struct Walk<H: WalkHistory>{
     history: H,
     pub distance: usize,
+    /// Number of received bits that differ from the codeword along the walk.
+    pub errors: usize,
 }
 
 impl<H: WalkHistory> Walk<H> {
@@ -364,6 +420,7 @@ impl<H: WalkHistory> Walk<H> {
             } else {
                 std::usize::MAX
             },
+            errors: 0,
        }.init(state)
     }
 
@@ -374,11 +431,13 @@ impl<H: WalkHistory> Walk<H> {
 
     pub fn append(&mut self, other: Self) {
         self.distance = other.distance;
+        self.errors = other.errors;
         other.iter().cloned().collect_slice(&mut self[1..]);
     }
 
-    pub fn combine(mut self, other: &Self, distance: usize) -> Self {
+    pub fn combine(mut self, other: &Self, distance: usize, errors: usize) -> Self {
         self.distance = distance;
+        self.errors = std::cmp::min(self.errors, errors);
 
         for (dest, src) in self.iter_mut().zip(other.iter()) {
             if src != dest {
@@ -389,8 +448,9 @@ impl<H: WalkHistory> Walk<H> {
         self
     }
 
-    pub fn replace(mut self, other: &Self, distance: usize) -> Self {
+    pub fn replace(mut self, other: &Self, distance: usize, errors: usize) -> Self {
         self.distance = distance;
+        self.errors = errors;
         other.iter().cloned().collect_slice_checked(&mut self[..]);
 
         self
@@ -505,6 +565,50 @@ mod test {
         assert_eq!(dec.next().unwrap().unwrap().bits(), 3);
         assert_eq!(dec.next().unwrap().unwrap().bits(), 0);
         assert_eq!(dec.next().unwrap().unwrap().bits(), 2);
+        assert!(dec.next().is_none());
+        assert_eq!(dec.errors(), 1);
+    }
+
+    #[test]
+    fn test_soft_decoder() {
+        let bits = [1, 2, 2, 2, 2, 1, 3, 3, 0, 2];
+        let stream = bits.iter().map(|&bits| Dibit::new(bits));
+
+        let mut dibits = vec![];
+        let mut fsm = DibitFSM::new();
+
+        for dibit in stream {
+            let (hi, lo) = fsm.feed(dibit);
+            dibits.push(hi);
+            dibits.push(lo);
+        }
+
+        let (hi, lo) = fsm.finish();
+        dibits.push(hi);
+        dibits.push(lo);
+
+        // Flip every bit of a run of symbols, but decide them with low confidence.
+        let soft: Vec<SoftDibit> = dibits.iter().enumerate().map(|(i, &d)| {
+            if i >= 2 && i < 4 {
+                SoftDibit::new(Dibit::new(d.bits() ^ 0b11), 1, 1)
+            } else {
+                SoftDibit::new(d, 8, 8)
+            }
+        }).collect();
+
+        let hard: Vec<Dibit> = soft.iter().map(|s| s.dibit()).collect();
+        let dec: Vec<Result<Dibit, ()>> =
+            DibitDecoder::new(hard.iter().cloned()).collect();
+        assert!(dec.iter().zip(bits.iter()).any(|(d, &b)| *d != Ok(Dibit::new(b))));
+
+        let mut dec = DibitDecoder::new(soft.iter().cloned());
+        let out: Vec<Result<Dibit, ()>> = (&mut dec).collect();
+        assert_eq!(out.len(), bits.len());
+        assert_eq!(dec.errors(), 4);
+
+        for (d, &b) in out.iter().zip(bits.iter()) {
+            assert_eq!(*d, Ok(Dibit::new(b)));
+        }
     }
 
     #[test]
@@ -550,5 +654,7 @@ mod test {
         assert_eq!(dec.next().unwrap().unwrap().bits(), 6);
         assert_eq!(dec.next().unwrap().unwrap().bits(), 7);
         assert_eq!(dec.next().unwrap().unwrap().bits(), 0);
+        assert!(dec.next().is_none());
+        assert_eq!(dec.errors(), 1);
     }
 }
@@ -2,7 +2,8 @@
 
 /// Symbols (dibits) per second.
 pub const SYMBOL_RATE: usize = 4800;
-/// Baseband samples per second
+/// Baseband samples per second used internally. Input at other rates is resampled to
+/// this rate.
 pub const SAMPLE_RATE: usize = 48000;
 /// Baseband samples per symbol.
 pub const SYMBOL_PERIOD: usize = SAMPLE_RATE / SYMBOL_RATE;
@@ -16,6 +17,14 @@ pub const CODING_DIBITS: usize = 98;
 pub const TSBK_DIBITS: usize = 48;
 /// Number of bytes in an uncoded TSBK packet.
 pub const TSBK_BYTES: usize = TSBK_DIBITS / 4;
+/// Number of dibits in an uncoded 1/2-rate data packet block.
+pub const DATA_BLOCK_DIBITS: usize = 48;
+/// Number of bytes in an uncoded 1/2-rate data packet block.
+pub const DATA_BLOCK_BYTES: usize = DATA_BLOCK_DIBITS / 4;
+/// Number of tribits in an uncoded 3/4-rate data packet block.
+pub const CONFIRMED_BLOCK_TRIBITS: usize = 48;
+/// Number of bytes in an uncoded 3/4-rate data packet block.
+pub const CONFIRMED_BLOCK_BYTES: usize = CONFIRMED_BLOCK_TRIBITS * 3 / 8;
 /// Number of dibits in a coded voice frame.
 pub const FRAME_DIBITS: usize = 72;
 /// Number of hexbits in a coded voice header packet.
@@ -35,6 +44,10 @@ pub const EXTRA_PIECE_DIBITS: usize = 20;
 pub const EXTRA_WORD_DIBITS: usize = 5;
 /// Number of dibits in the voice data fragment.
 pub const DATA_FRAG_DIBITS: usize = 8;
+/// Number of dibits in a coded voice frame group, made up of 9 voice frames, 6 LC/CC
+/// pieces, and 2 data fragments.
+pub const FRAME_GROUP_DIBITS: usize =
+    FRAME_DIBITS * 9 + EXTRA_PIECE_DIBITS * 6 + DATA_FRAG_DIBITS * 2;
 /// Number of dibits in each coded word that makes up the voice header packet.
 pub const HEADER_WORD_DIBITS: usize = 9;
 /// Number of dibits in each coded word that makes up the voice LC terminator packet.
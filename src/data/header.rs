@@ -1,10 +1,13 @@
-//! Header generation for data packets.
+//! Header generation and parsing for data packets.
 //!
 //! A header has several user-filled fields followed by a 16-bit checksum over those
 //! fields.
 
+use consts::DATA_BLOCK_BYTES;
 use data::crc;
-use data::fields;
+use data::fields::{self, DataPacketOpcode};
+use trunking::tsbk::TsbkOpcode;
+use util::{slice_u16, slice_u24};
 
 /// Packet header block for confirmed data packet.
 pub type ConfirmedHeader = Header<ConfirmedFields>;
@@ -255,6 +258,158 @@ impl<F: BufWrite> Header<F> {
     }
 }
 
+/// Buffer of bytes that represents a data packet header.
+pub type Buf = [u8; DATA_BLOCK_BYTES];
+
+/// Fields common to all data packet headers.
+#[derive(Copy, Clone)]
+pub struct DataHeaderFields(Buf);
+
+impl DataHeaderFields {
+    /// Interpret the given bytes as a data packet header.
+    pub fn new(buf: Buf) -> DataHeaderFields { DataHeaderFields(buf) }
+
+    /// Retrieve the raw header bytes, including the checksum.
+    pub fn bytes(&self) -> &Buf { &self.0 }
+
+    /// Whether the packet requires confirmation (A/N bit.)
+    pub fn confirmed(&self) -> bool { self.0[0] >> 6 & 1 == 1 }
+    /// Whether the packet is an outbound message (I/O bit.)
+    pub fn outbound(&self) -> bool { self.0[0] >> 5 & 1 == 1 }
+    /// Packet format, which determines how the rest of the header is interpreted.
+    pub fn format(&self) -> Option<DataPacketOpcode> {
+        DataPacketOpcode::from_bits(self.0[0] & 0x1F)
+    }
+    /// Destination service of the packet. This isn't present in response packets.
+    pub fn sap(&self) -> Option<fields::ServiceAccessPoint> {
+        fields::ServiceAccessPoint::from_bits(self.0[1] & 0x3F)
+    }
+    /// Manufacturer ID, which determines if the packet is standardized.
+    pub fn mfg(&self) -> u8 { self.0[2] }
+    /// Logical link ID of the source or destination subscriber.
+    pub fn logical_link(&self) -> u32 { slice_u24(&self.0[3..=5]) }
+    /// Whether the packet is "complete", not being partially retransmitted (FMF bit.)
+    pub fn full_pkt(&self) -> bool { self.0[6] >> 7 == 1 }
+    /// Number of data blocks that follow the header.
+    pub fn blocks(&self) -> u8 { self.0[6] & 0x7F }
+    /// Number of pad bytes at the end of the data.
+    pub fn pads(&self) -> u8 { self.0[7] & 0x1F }
+    /// Whether the receiver should resynchronize its sequence numbers (Syn bit.) This is
+    /// only used in confirmed packets.
+    pub fn resync(&self) -> bool { self.0[8] >> 7 == 1 }
+    /// Packet sequence number, N(S). This is only used in confirmed packets.
+    pub fn pkt_seq(&self) -> u8 { self.0[8] >> 4 & 0b111 }
+    /// Fragment sequence number, FSNF. This is only used in confirmed packets.
+    pub fn frag_seq(&self) -> u8 { self.0[8] & 0xF }
+    /// Byte offset into the data payload where data header stops and data information
+    /// begins.
+    pub fn data_offset(&self) -> u8 { self.0[9] & 0x3F }
+
+    /// Transmitted CRC.
+    pub fn crc(&self) -> u16 { slice_u16(&self.0[10..]) }
+
+    /// Calculate 16-bit CRC over header fields.
+    pub fn calc_crc(&self) -> u16 {
+        crc::CRC16::new()
+            .feed_bytes(self.0[..10].iter().cloned())
+            .finish() as u16
+    }
+
+    /// Verify if the calculated CRC matches the transmitted one.
+    pub fn crc_valid(&self) -> bool { self.crc() == self.calc_crc() }
+}
+
+/// Kind of response to a confirmed data packet.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ResponseType {
+    /// All blocks were received successfully.
+    Ack,
+    /// The packet had an illegal format.
+    IllegalFormat,
+    /// The packet checksum failed.
+    PacketChecksum,
+    /// The recipient ran out of memory.
+    MemoryFull,
+    /// The fragment sequence number was out of sequence.
+    FragmentSequence,
+    /// The packet was undeliverable.
+    Undeliverable,
+    /// The packet sequence number was out of sequence.
+    PacketSequence,
+    /// The recipient was not a valid user on the system.
+    InvalidUser,
+    /// Some blocks should be retransmitted.
+    SelectiveRetry,
+}
+
+impl ResponseType {
+    /// Try to parse a response type from the given 2-bit class and 3-bit type.
+    pub fn from_bits(class: u8, kind: u8) -> Option<ResponseType> {
+        use self::ResponseType::*;
+
+        assert!(class >> 2 == 0);
+        assert!(kind >> 3 == 0);
+
+        match (class, kind) {
+            (0b00, 0b001) => Some(Ack),
+            (0b01, 0b000) => Some(IllegalFormat),
+            (0b01, 0b001) => Some(PacketChecksum),
+            (0b01, 0b010) => Some(MemoryFull),
+            (0b01, 0b011) => Some(FragmentSequence),
+            (0b01, 0b100) => Some(Undeliverable),
+            (0b01, 0b101) => Some(PacketSequence),
+            (0b01, 0b110) => Some(InvalidUser),
+            (0b10, _) => Some(SelectiveRetry),
+            _ => None,
+        }
+    }
+}
+
+/// Header of a response packet, sent in reply to a confirmed data packet.
+pub struct ResponseHeader(Buf);
+
+impl ResponseHeader {
+    /// Create a new `ResponseHeader` decoder from the base header decoder.
+    pub fn new(header: DataHeaderFields) -> Self { ResponseHeader(header.0) }
+
+    /// Response class.
+    pub fn class(&self) -> u8 { self.0[1] >> 6 }
+    /// Response type within the class.
+    pub fn kind(&self) -> u8 { self.0[1] >> 3 & 0b111 }
+    /// Response status, the N(R) sequence number of the packet being responded to.
+    pub fn status(&self) -> u8 { self.0[1] & 0b111 }
+    /// Interpreted class and type of the response.
+    pub fn response(&self) -> Option<ResponseType> {
+        ResponseType::from_bits(self.class(), self.kind())
+    }
+    /// Logical link ID of the unit the response is addressed to.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[3..=5]) }
+    /// Logical link ID of the unit sending the response, if present.
+    pub fn src_unit(&self) -> Option<u32> {
+        if self.0[6] >> 7 == 0 {
+            Some(slice_u24(&self.0[7..=9]))
+        } else {
+            None
+        }
+    }
+}
+
+/// Header of a multiblock trunking (MBT) packet, which carries an alternate-format
+/// trunking message split over the header and following data blocks.
+pub struct TrunkingHeader(Buf);
+
+impl TrunkingHeader {
+    /// Create a new `TrunkingHeader` decoder from the base header decoder.
+    pub fn new(header: DataHeaderFields) -> Self { TrunkingHeader(header.0) }
+
+    /// Type of trunking message carried by the packet.
+    pub fn opcode(&self) -> Option<TsbkOpcode> { TsbkOpcode::from_bits(self.0[7] & 0x3F) }
+    /// Address of the unit the message is sent to or from.
+    pub fn unit(&self) -> u32 { slice_u24(&self.0[3..=5]) }
+    /// Opcode-specific arguments carried in the header.
+    pub fn args(&self) -> &[u8] { &self.0[8..=9] }
+}
+
 /// Convert the given Boolean to a single bit.
 fn bool_to_bit(b: bool) -> u8 {
     if b { 1 } else { 0 }
@@ -439,4 +594,100 @@ mod test {
     fn test_do_validate() {
         DataOffset(0b11111111).byte();
     }
+
+    #[test]
+    fn test_parse_confirmed() {
+        let (fields, checksum) = ConfirmedHeader::new(ConfirmedFields {
+            preamble: ConfirmedPreamble::outbound(),
+            sap: ServiceAccessPoint(fields::ServiceAccessPoint::PacketData),
+            mfg: Manufacturer(0x12),
+            addr: LogicalLink(0x342134),
+            blocks: BlockCount {
+                full_pkt: true,
+                count: 127,
+            },
+            pads: PadCount(3),
+            seq: Sequencing {
+                resync: false,
+                pkt_seq: 5,
+                frag_seq: 2,
+            },
+            data_offset: DataOffset(11),
+        }).build();
+
+        let mut buf = [0; 12];
+        buf[..10].copy_from_slice(&fields);
+        buf[10..].copy_from_slice(&checksum);
+
+        let h = DataHeaderFields::new(buf);
+        assert!(h.confirmed());
+        assert!(h.outbound());
+        assert_eq!(h.format(), Some(DataPacketOpcode::ConfirmedPacket));
+        assert_eq!(h.sap(), Some(fields::ServiceAccessPoint::PacketData));
+        assert_eq!(h.mfg(), 0x12);
+        assert_eq!(h.logical_link(), 0x342134);
+        assert!(h.full_pkt());
+        assert_eq!(h.blocks(), 127);
+        assert_eq!(h.pads(), 3);
+        assert!(!h.resync());
+        assert_eq!(h.pkt_seq(), 5);
+        assert_eq!(h.frag_seq(), 2);
+        assert_eq!(h.data_offset(), 11);
+        assert!(h.crc_valid());
+
+        buf[3] ^= 1;
+        assert!(!DataHeaderFields::new(buf).crc_valid());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let h = DataHeaderFields::new([
+            0b00000011, 0b01001101, 0x00, 0x12, 0x34, 0x56,
+            0b00000000, 0xAB, 0xCD, 0xEF, 0x00, 0x00,
+        ]);
+
+        assert!(!h.confirmed());
+        assert!(!h.outbound());
+        assert_eq!(h.format(), Some(DataPacketOpcode::ResponsePacket));
+
+        let r = ResponseHeader::new(h);
+        assert_eq!(r.class(), 0b01);
+        assert_eq!(r.kind(), 0b001);
+        assert_eq!(r.status(), 0b101);
+        assert_eq!(r.response(), Some(ResponseType::PacketChecksum));
+        assert_eq!(r.dest_unit(), 0x123456);
+        assert_eq!(r.src_unit(), Some(0xABCDEF));
+
+        let r = ResponseHeader::new(DataHeaderFields::new([
+            0b00100011, 0b00001000, 0x00, 0x12, 0x34, 0x56,
+            0b10000000, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]));
+
+        assert_eq!(r.response(), Some(ResponseType::Ack));
+        assert_eq!(r.status(), 0);
+        assert_eq!(r.src_unit(), None);
+
+        assert_eq!(ResponseType::from_bits(0b10, 0b000),
+                   Some(ResponseType::SelectiveRetry));
+        assert_eq!(ResponseType::from_bits(0b11, 0b000), None);
+        assert_eq!(ResponseType::from_bits(0b01, 0b111), None);
+    }
+
+    #[test]
+    fn test_parse_trunking() {
+        let h = DataHeaderFields::new([
+            0b00110111, 0b11111101, 0x00, 0x12, 0x34, 0x56,
+            0b10000010, 0b00000000, 0xDE, 0xAD, 0x00, 0x00,
+        ]);
+
+        assert!(h.outbound());
+        assert_eq!(h.format(), Some(DataPacketOpcode::TrunkingPacket));
+        assert_eq!(h.sap(), Some(fields::ServiceAccessPoint::TrunkingControl));
+        assert_eq!(h.blocks(), 2);
+
+        let t = TrunkingHeader::new(h);
+        assert_eq!(t.opcode(), Some(TsbkOpcode::GroupVoiceGrant));
+        assert_eq!(t.unit(), 0x123456);
+        assert_eq!(t.args(), &[0xDE, 0xAD]);
+    }
 }
@@ -272,13 +272,13 @@ impl Iterator for Interleaver {
     }
 }
 
-pub struct Deinterleaver<'a> {
-    dibits: &'a [bits::Dibit; consts::CODING_DIBITS],
+pub struct Deinterleaver<'a, T: 'a = bits::Dibit> {
+    dibits: &'a [T; consts::CODING_DIBITS],
     idx: Indexes<DeinterleaveRedirector>,
 }
 
-impl<'a> Deinterleaver<'a> {
-    pub fn new(dibits: &'a [bits::Dibit; consts::CODING_DIBITS]) -> Deinterleaver<'a> {
+impl<'a, T: Copy> Deinterleaver<'a, T> {
+    pub fn new(dibits: &'a [T; consts::CODING_DIBITS]) -> Deinterleaver<'a, T> {
         Deinterleaver {
             dibits: dibits,
             idx: Indexes::new(),
@@ -286,8 +286,8 @@ impl<'a> Deinterleaver<'a> {
     }
 }
 
-impl<'a> Iterator for Deinterleaver<'a> {
-    type Item = bits::Dibit;
+impl<'a, T: Copy> Iterator for Deinterleaver<'a, T> {
+    type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.idx.next().map(|idx| self.dibits[idx])
@@ -9,6 +9,7 @@ pub mod interleave;
 pub mod packet;
 pub mod params;
 pub mod payload;
+pub mod receiver;
 
 pub use self::fragment::{ConfirmedFragments, UnconfirmedFragments};
 
@@ -26,9 +27,18 @@ pub use self::header::{
     PadCount,
     Sequencing,
     DataOffset,
+    DataHeaderFields,
+    ResponseHeader,
+    ResponseType,
+    TrunkingHeader,
 };
 
 pub use self::payload::{
     ConfirmedPayload,
     UnconfirmedPayload,
 };
+
+pub use self::receiver::{
+    DataPacketReceiver,
+    DataPacket,
+};
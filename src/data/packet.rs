@@ -16,11 +16,10 @@ pub fn confirmed<S>(header: data::ConfirmedHeader, payload: data::ConfirmedPaylo
     pkt.extend({
         let (fields, checksum) = header.build();
 
-        coder::DibitCoder::new()
+        interleave::Interleaver::new(coder::DibitCoder::new()
             .feed_bytes(fields.iter().cloned())
             .feed_bytes(checksum.iter().cloned())
-            .finish()
-            .iter().cloned()
+            .finish())
     });
 
     // Add in the normal data blocks.
@@ -65,11 +64,10 @@ pub fn unconfirmed(header: data::UnconfirmedHeader, payload: data::UnconfirmedPa
     pkt.extend({
         let (fields, checksum) = header.build();
 
-        coder::DibitCoder::new()
+        interleave::Interleaver::new(coder::DibitCoder::new()
             .feed_bytes(fields.iter().cloned())
             .feed_bytes(checksum.iter().cloned())
-            .finish()
-            .iter().cloned()
+            .finish())
     });
 
     for block in payload.iter() {
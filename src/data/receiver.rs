@@ -0,0 +1,501 @@
+//! Receive data packets and decode the data header.
+
+use std;
+
+use collect_slice::CollectSlice;
+
+use bits::{Dibit, DibitBytes, SoftDibit, Tribit, TribitBytes};
+use buffer::{Buffer, DataPayloadStorage};
+use coding::trellis;
+use consts::{
+    CODING_DIBITS,
+    CONFIRMED_BLOCK_BYTES,
+    CONFIRMED_BLOCK_TRIBITS,
+    DATA_BLOCK_BYTES,
+    DATA_BLOCK_DIBITS,
+};
+use data::{crc, interleave};
+use data::fields::DataPacketOpcode;
+use data::header::DataHeaderFields;
+use error::{Result, P25Error};
+use stats::{Stats, HasStats};
+use util::{slice_u16, slice_u32};
+
+/// Number of bytes in the packet checksum at the end of the tail block.
+const PACKET_CRC_BYTES: usize = 4;
+
+/// State machine for receiving a data packet.
+///
+/// The state machine consumes dibit symbols and performs the following steps:
+///
+/// 1. Decode the 1/2-rate coded header block and verify its checksum
+/// 2. Decode the number of data blocks given by the header, using the 3/4-rate code for
+///    confirmed packets and the 1/2-rate code for all others
+/// 3. Verify the checksum of each confirmed data block
+/// 4. Verify the packet checksum in the tail block and strip off any pad bytes
+///
+/// When soft decisions are enabled, the confidence of each symbol decision is used to
+/// weigh the errors considered by the convolutional decoders.
+pub struct DataPacketReceiver {
+    /// Current buffered dibits.
+    dibits: Buffer<DataPayloadStorage>,
+    /// Whether to decode with soft decisions.
+    soft: bool,
+    /// Current state.
+    state: State,
+    /// Data and pad bytes collected from the blocks decoded so far.
+    data: Vec<u8>,
+    stats: Stats,
+}
+
+/// Internal state of the state machine.
+#[derive(Copy, Clone)]
+enum State {
+    /// Decoding the header block.
+    DecodeHeader,
+    /// Decoding data blocks, with the given number of blocks remaining.
+    DecodeBlocks(DataHeaderFields, usize),
+}
+
+impl DataPacketReceiver {
+    /// Create a new `DataPacketReceiver` in the initial state, decoding with hard
+    /// decisions.
+    pub fn new() -> DataPacketReceiver {
+        DataPacketReceiver {
+            dibits: Buffer::new(DataPayloadStorage::new()),
+            soft: false,
+            state: State::DecodeHeader,
+            data: vec![],
+            stats: Stats::default(),
+        }
+    }
+
+    /// Create a new `DataPacketReceiver` in the initial state, decoding with soft
+    /// decisions.
+    pub fn soft() -> DataPacketReceiver {
+        DataPacketReceiver {
+            soft: true,
+            ..DataPacketReceiver::new()
+        }
+    }
+
+    /// Feed in a baseband symbol, possibly producing a complete data packet. Return
+    /// `Some(Ok(pkt))` if a packet was successfully received, `Some(Err(err))` if an
+    /// error occurred, and `None` in the case of no event.
+    pub fn feed<D: Into<SoftDibit>>(&mut self, dibit: D) -> Option<Result<DataPacket>> {
+        let dibit = dibit.into();
+
+        // Without soft decisions, every bit is weighed equally.
+        let dibit = if self.soft {
+            dibit
+        } else {
+            SoftDibit::from(dibit.dibit())
+        };
+
+        let buf = match self.dibits.feed(dibit) {
+            Some(buf) => *buf,
+            None => return None,
+        };
+
+        match self.state {
+            State::DecodeHeader => self.handle_header(&buf),
+            State::DecodeBlocks(header, remain) => self.handle_block(header, remain, &buf),
+        }
+    }
+
+    /// Decode the header block and determine how many data blocks follow.
+    fn handle_header(&mut self, buf: &[SoftDibit; CODING_DIBITS])
+        -> Option<Result<DataPacket>>
+    {
+        let header = match self.decode_dibits(buf) {
+            Ok(bytes) => DataHeaderFields::new(bytes),
+            Err(err) => return Some(Err(err)),
+        };
+
+        if !header.crc_valid() {
+            return Some(Err(P25Error::DataHeaderChecksum));
+        }
+
+        if header.format().is_none() {
+            return Some(Err(P25Error::UnknownDataFormat));
+        }
+
+        if header.blocks() == 0 {
+            return Some(Ok(DataPacket::new(header, vec![])));
+        }
+
+        self.state = State::DecodeBlocks(header, header.blocks() as usize);
+
+        None
+    }
+
+    /// Decode a data block and, if it's the tail block, verify and emit the packet.
+    fn handle_block(&mut self, header: DataHeaderFields, remain: usize,
+                    buf: &[SoftDibit; CODING_DIBITS])
+        -> Option<Result<DataPacket>>
+    {
+        let tail = remain == 1;
+
+        let res = match header.format() {
+            Some(DataPacketOpcode::ConfirmedPacket) => self.decode_tribits(buf)
+                .and_then(|bytes| ConfirmedBlock(bytes).data(tail).map(|data| {
+                    self.data.extend_from_slice(data);
+                })),
+            _ => self.decode_dibits(buf).map(|bytes| {
+                self.data.extend_from_slice(&bytes[..]);
+            }),
+        };
+
+        if let Err(err) = res {
+            return Some(Err(err));
+        }
+
+        if !tail {
+            self.state = State::DecodeBlocks(header, remain - 1);
+            return None;
+        }
+
+        self.state = State::DecodeHeader;
+
+        let data = std::mem::replace(&mut self.data, vec![]);
+
+        Some(DataPacket::verify(header, data))
+    }
+
+    /// Deinterleave and decode a 1/2-rate coded block.
+    fn decode_dibits(&mut self, buf: &[SoftDibit; CODING_DIBITS])
+        -> Result<[u8; DATA_BLOCK_BYTES]>
+    {
+        let mut dibits = [Dibit::default(); DATA_BLOCK_DIBITS];
+        let mut dec = trellis::DibitDecoder::new(interleave::Deinterleaver::new(buf));
+
+        let count = (&mut dec)
+            .filter_map(|x| x.ok())
+            .collect_slice_exhaust(&mut dibits[..]);
+
+        if count != dibits.len() {
+            return Err(P25Error::DibitViterbiUnrecoverable);
+        }
+
+        self.stats.viterbi_dibit.record_fixes(dec.errors());
+
+        let mut bytes = [0; DATA_BLOCK_BYTES];
+        DibitBytes::new(dibits.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+
+        Ok(bytes)
+    }
+
+    /// Deinterleave and decode a 3/4-rate coded block.
+    fn decode_tribits(&mut self, buf: &[SoftDibit; CODING_DIBITS])
+        -> Result<[u8; CONFIRMED_BLOCK_BYTES]>
+    {
+        let mut tribits = [Tribit::default(); CONFIRMED_BLOCK_TRIBITS];
+        let mut dec = trellis::TribitDecoder::new(interleave::Deinterleaver::new(buf));
+
+        let count = (&mut dec)
+            .filter_map(|x| x.ok())
+            .collect_slice_exhaust(&mut tribits[..]);
+
+        if count != tribits.len() {
+            return Err(P25Error::TribitViterbiUnrecoverable);
+        }
+
+        self.stats.viterbi_tribit.record_fixes(dec.errors());
+
+        let mut bytes = [0; CONFIRMED_BLOCK_BYTES];
+        TribitBytes::new(tribits.iter().cloned()).collect_slice_checked(&mut bytes[..]);
+
+        Ok(bytes)
+    }
+}
+
+impl HasStats for DataPacketReceiver {
+    fn stats(&mut self) -> &mut Stats { &mut self.stats }
+}
+
+/// Decoded 3/4-rate block of a confirmed data packet.
+struct ConfirmedBlock([u8; CONFIRMED_BLOCK_BYTES]);
+
+impl ConfirmedBlock {
+    /// 7-bit serial number of the block.
+    fn serial_number(&self) -> u8 { self.0[0] >> 1 }
+
+    /// 9-bit checksum over the block.
+    fn crc(&self) -> u16 { slice_u16(&self.0[..]) & 0x1FF }
+
+    /// Verify the block checksum and return the data bytes (including any pads and
+    /// packet checksum) carried by the block. If the block is the tail block, the
+    /// checksum excludes the packet checksum.
+    fn data(&self, tail: bool) -> Result<&[u8]> {
+        let data = &self.0[2..];
+
+        let covered = if tail {
+            &data[..data.len() - PACKET_CRC_BYTES]
+        } else {
+            data
+        };
+
+        let calc = crc::CRC9::new()
+            .feed_bits(self.serial_number(), 7)
+            .feed_bytes(covered.iter().cloned())
+            .finish() as u16;
+
+        if calc == self.crc() {
+            Ok(data)
+        } else {
+            Err(P25Error::DataBlockChecksum)
+        }
+    }
+}
+
+/// A received data packet.
+pub struct DataPacket {
+    /// Packet header.
+    pub header: DataHeaderFields,
+    /// Data bytes carried by the packet, with pads and checksum removed.
+    pub data: Vec<u8>,
+}
+
+impl DataPacket {
+    /// Create a new `DataPacket` from the given header and data bytes.
+    fn new(header: DataHeaderFields, data: Vec<u8>) -> DataPacket {
+        DataPacket {
+            header: header,
+            data: data,
+        }
+    }
+
+    /// Verify the packet checksum at the end of the given block bytes and create a
+    /// packet from the remaining data bytes.
+    fn verify(header: DataHeaderFields, mut data: Vec<u8>) -> Result<DataPacket> {
+        if data.len() < PACKET_CRC_BYTES {
+            return Err(P25Error::DataPacketChecksum);
+        }
+
+        let len = data.len() - PACKET_CRC_BYTES;
+
+        let calc = crc::CRC32::new()
+            .feed_bytes(data[..len].iter().cloned())
+            .finish() as u32;
+
+        if calc != slice_u32(&data[len..]) {
+            return Err(P25Error::DataPacketChecksum);
+        }
+
+        // Trunking packets carry an opcode in place of the pad count and are never padded.
+        let pads = match header.format() {
+            Some(DataPacketOpcode::TrunkingPacket) => 0,
+            _ => header.pads() as usize,
+        };
+
+        data.truncate(len.saturating_sub(pads));
+
+        Ok(DataPacket::new(header, data))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::{self, packet};
+    use data::fields::ServiceAccessPoint;
+
+    fn feed(dibits: &[Dibit]) -> Vec<Result<DataPacket>> {
+        let mut recv = DataPacketReceiver::new();
+        dibits.iter().filter_map(|&d| recv.feed(d)).collect()
+    }
+
+    #[test]
+    fn test_confirmed() {
+        let bytes: Vec<u8> = (0..37).collect();
+        let payload = data::ConfirmedPayload::new(&bytes[..]);
+
+        let header = data::ConfirmedHeader::new(data::ConfirmedFields {
+            preamble: data::ConfirmedPreamble::outbound(),
+            sap: data::ServiceAccessPoint(ServiceAccessPoint::PacketData),
+            mfg: data::Manufacturer(0),
+            addr: data::LogicalLink(0x123456),
+            blocks: data::BlockCount {
+                full_pkt: true,
+                count: payload.blocks() as u8,
+            },
+            pads: data::PadCount(payload.pads() as u8),
+            seq: data::Sequencing {
+                resync: true,
+                pkt_seq: 0,
+                frag_seq: 0,
+            },
+            data_offset: data::DataOffset(0),
+        });
+
+        let dibits = packet::confirmed(header, payload, 0..);
+        assert_eq!(dibits.len(), 4 * CODING_DIBITS);
+
+        let mut pkts = feed(&dibits[..]);
+        assert_eq!(pkts.len(), 1);
+
+        let pkt = pkts.pop().unwrap().unwrap();
+        assert!(pkt.header.confirmed());
+        assert!(pkt.header.outbound());
+        assert_eq!(pkt.header.format(), Some(DataPacketOpcode::ConfirmedPacket));
+        assert_eq!(pkt.header.blocks(), 3);
+        assert_eq!(pkt.header.pads(), 7);
+        assert_eq!(pkt.data, bytes);
+    }
+
+    #[test]
+    fn test_unconfirmed() {
+        let bytes: Vec<u8> = (0..21).map(|x| x * 3).collect();
+        let payload = data::UnconfirmedPayload::new(&bytes[..]);
+
+        let header = data::UnconfirmedHeader::new(data::UnconfirmedFields {
+            preamble: data::UnconfirmedPreamble::inbound(),
+            sap: data::ServiceAccessPoint(ServiceAccessPoint::UnencryptedUserData),
+            mfg: data::Manufacturer(0),
+            addr: data::LogicalLink(0xABCDEF),
+            blocks: data::BlockCount {
+                full_pkt: true,
+                count: payload.blocks() as u8,
+            },
+            pads: data::PadCount(payload.pads() as u8),
+            data_offset: data::DataOffset(0),
+        });
+
+        let mut dibits = packet::unconfirmed(header, payload);
+        assert_eq!(dibits.len(), 4 * CODING_DIBITS);
+
+        let pkt = feed(&dibits[..]).pop().unwrap().unwrap();
+        assert!(!pkt.header.confirmed());
+        assert!(!pkt.header.outbound());
+        assert_eq!(pkt.header.format(), Some(DataPacketOpcode::UnconfirmedPacket));
+        assert_eq!(pkt.header.blocks(), 3);
+        assert_eq!(pkt.data, bytes);
+
+        // Replace the first data block with a valid codeword carrying different data, so
+        // the trellis decodes it cleanly and only the packet checksum catches it.
+        let mut block = [0; DATA_BLOCK_BYTES];
+        block.copy_from_slice(&bytes[..DATA_BLOCK_BYTES]);
+        block[0] ^= 0xFF;
+
+        let coded = interleave::Interleaver::new(data::coder::DibitCoder::new()
+            .feed_bytes(block.iter().cloned())
+            .finish());
+
+        for (d, c) in dibits[CODING_DIBITS..2 * CODING_DIBITS].iter_mut().zip(coded) {
+            *d = c;
+        }
+
+        match feed(&dibits[..]).pop() {
+            Some(Err(P25Error::DataPacketChecksum)) => {},
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_header_checksum() {
+        let mut buf = [0; DATA_BLOCK_BYTES];
+        buf[0] = 0b00010101;
+
+        let mut dibits = vec![];
+        dibits.extend(interleave::Interleaver::new(data::coder::DibitCoder::new()
+            .feed_bytes(buf.iter().cloned())
+            .finish()));
+
+        match feed(&dibits[..]).pop() {
+            Some(Err(P25Error::DataHeaderChecksum)) => {},
+            _ => panic!(),
+        }
+
+        let (fields, checksum) = data::UnconfirmedHeader::new(data::UnconfirmedFields {
+            preamble: data::UnconfirmedPreamble::outbound(),
+            sap: data::ServiceAccessPoint(ServiceAccessPoint::PacketData),
+            mfg: data::Manufacturer(0),
+            addr: data::LogicalLink(0),
+            blocks: data::BlockCount {
+                full_pkt: true,
+                count: 0,
+            },
+            pads: data::PadCount(0),
+            data_offset: data::DataOffset(0),
+        }).build();
+
+        let mut buf = [0; DATA_BLOCK_BYTES];
+        fields.iter().chain(checksum.iter()).cloned().collect_slice_checked(&mut buf[..]);
+
+        let h = DataHeaderFields::new(buf);
+        assert!(h.crc_valid());
+        assert_eq!(h.blocks(), 0);
+
+        let dibits: Vec<Dibit> = interleave::Interleaver::new(
+            data::coder::DibitCoder::new().feed_bytes(buf.iter().cloned()).finish()
+        ).collect();
+
+        let pkt = feed(&dibits[..]).pop().unwrap().unwrap();
+        assert_eq!(pkt.data.len(), 0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let bytes: Vec<u8> = (0..10).collect();
+        let payload = data::UnconfirmedPayload::new(&bytes[..]);
+
+        let header = data::UnconfirmedHeader::new(data::UnconfirmedFields {
+            preamble: data::UnconfirmedPreamble::outbound(),
+            sap: data::ServiceAccessPoint(ServiceAccessPoint::PacketData),
+            mfg: data::Manufacturer(0),
+            addr: data::LogicalLink(0x123),
+            blocks: data::BlockCount {
+                full_pkt: true,
+                count: payload.blocks() as u8,
+            },
+            pads: data::PadCount(payload.pads() as u8),
+            data_offset: data::DataOffset(0),
+        });
+
+        let mut dibits = packet::unconfirmed(header, payload);
+
+        // Flip a single bit in each of two widely separated dibits of the payload block.
+        dibits[CODING_DIBITS + 10] = Dibit::new(dibits[CODING_DIBITS + 10].bits() ^ 0b01);
+        dibits[CODING_DIBITS + 60] = Dibit::new(dibits[CODING_DIBITS + 60].bits() ^ 0b10);
+
+        let mut recv = DataPacketReceiver::new();
+        let pkt = dibits.iter().filter_map(|&d| recv.feed(d)).last().unwrap().unwrap();
+        assert_eq!(pkt.data, bytes);
+
+        let stats = recv.stats();
+        assert_eq!(stats.viterbi_dibit.words, 3);
+        assert_eq!(stats.viterbi_dibit.fixed, 2);
+    }
+
+    #[test]
+    fn test_trunking() {
+        let mut head = [
+            0b00110111, 0b11111101, 0x00, 0x12, 0x34, 0x56,
+            0b10000001, 0b00111010, 0xDE, 0xAD, 0x00, 0x00,
+        ];
+
+        let crc = DataHeaderFields::new(head).calc_crc();
+        head[10] = (crc >> 8) as u8;
+        head[11] = crc as u8;
+
+        let mut block = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0, 0, 0, 0];
+
+        let crc = crc::CRC32::new().feed_bytes(block[..8].iter().cloned()).finish() as u32;
+        block[8] = (crc >> 24) as u8;
+        block[9] = (crc >> 16) as u8;
+        block[10] = (crc >> 8) as u8;
+        block[11] = crc as u8;
+
+        let dibits: Vec<Dibit> = [head, block].iter()
+            .flat_map(|b| interleave::Interleaver::new(
+                data::coder::DibitCoder::new().feed_bytes(b.iter().cloned()).finish()
+            ))
+            .collect();
+
+        let pkt = feed(&dibits[..]).pop().unwrap().unwrap();
+        assert_eq!(pkt.header.format(), Some(DataPacketOpcode::TrunkingPacket));
+
+        // The opcode in the header isn't interpreted as a pad count.
+        assert_eq!(pkt.data, &block[..8]);
+    }
+}
@@ -28,8 +28,23 @@ pub enum P25Error {
     /// An ambiguous symbol or too many errors were detected when attempting to decode the
     /// dibit Viterbi code.
     DibitViterbiUnrecoverable,
+    /// An ambiguous symbol or too many errors were detected when attempting to decode the
+    /// tribit Viterbi code.
+    TribitViterbiUnrecoverable,
+    /// A data packet header failed its CRC16 check.
+    DataHeaderChecksum,
+    /// A confirmed data packet block failed its CRC9 check.
+    DataBlockChecksum,
+    /// A data packet failed its CRC32 check.
+    DataPacketChecksum,
+    /// A data packet header with an unknown format was encountered.
+    UnknownDataFormat,
     /// An unknown or corrupted NID was encountered.
     UnknownNid,
+    /// A trunking message of a type without a supported decoder was encountered.
+    UnsupportedMessage,
+    /// A trunking message was too short to hold the fields of its type.
+    TruncatedMessage,
 }
 
 /// Standard result using `P25Error`.
@@ -1,8 +1,13 @@
 //! General low-level receiver for all data units, covering frame synchronization up to
 //! symbol decoding.
 
+use std::collections::VecDeque;
+
 use baseband::decode::{Decoder, Decider};
+use bits::SoftDibit;
+use baseband::resample::Resampler;
 use baseband::sync::{SyncCorrelator, SyncDetector, SymbolThresholds, sync_threshold};
+use consts::SAMPLE_RATE;
 use error::{P25Error, Result};
 use message::nid;
 use message::status::{StreamSymbol, StatusDeinterleaver};
@@ -31,20 +36,24 @@ impl SymbolReceiver {
     }
 
     /// Feed in a baseband symbol, possibly producing a data or status symbol.
-    pub fn feed(&mut self, s: f32) -> Option<StreamSymbol> {
+    pub fn feed(&mut self, s: f32) -> Option<StreamSymbol<SoftDibit>> {
         match self.decoder.feed(s) {
             Some(dibit) => Some(self.status.feed(dibit)),
             None => None,
         }
     }
+
+    /// Current offset, in samples, of the symbol clock from the one derived from the
+    /// frame sync.
+    pub fn timing_offset(&self) -> f32 { self.decoder.timing_offset() }
 }
 
 
 /// An event seen by the low-level receiver.
 #[derive(Debug)]
 pub enum ReceiverEvent {
-    /// Data or status symbol.
-    Symbol(StreamSymbol),
+    /// Data or status symbol. Data symbols carry the confidence of each decision.
+    Symbol(StreamSymbol<SoftDibit>),
     /// Decoded NID information.
     NetworkId(nid::NetworkId),
 }
@@ -108,20 +117,40 @@ pub struct DataUnitReceiver {
     corr: SyncCorrelator,
     /// Tracks thresholds for symbol decisions.
     symthresh: SymbolThresholds,
+    /// Converts input samples to the internal sample rate, if necessary.
+    resampler: Option<Resampler>,
+    /// Resampled samples waiting to be processed.
+    pending: VecDeque<f32>,
     stats: Stats,
 }
 
 impl DataUnitReceiver {
-    /// Create a new `DataUnitReceiver` in the initial reception state.
+    /// Create a new `DataUnitReceiver` in the initial reception state, taking input
+    /// samples at `SAMPLE_RATE`.
     pub fn new() -> DataUnitReceiver {
         DataUnitReceiver {
             state: State::sync(),
             corr: SyncCorrelator::new(),
             symthresh: SymbolThresholds::new(),
+            resampler: None,
+            pending: VecDeque::new(),
             stats: Stats::default(),
         }
     }
 
+    /// Create a new `DataUnitReceiver` in the initial reception state, taking input
+    /// samples at the given rate (Hz.)
+    pub fn with_sample_rate(rate: usize) -> DataUnitReceiver {
+        DataUnitReceiver {
+            resampler: if rate == SAMPLE_RATE {
+                None
+            } else {
+                Some(Resampler::new(rate))
+            },
+            ..DataUnitReceiver::new()
+        }
+    }
+
     /// Flush any remaining padding symbols at the end of the current packet, and reenter
     /// the frame synchronization state afterwards.
     pub fn flush_pads(&mut self) {
@@ -135,6 +164,16 @@ impl DataUnitReceiver {
     /// Force the receiver into frame synchronization.
     pub fn resync(&mut self) { self.state = State::sync(); }
 
+    /// Current offset, in samples, of the symbol clock from the one derived from the
+    /// most recent frame sync, or `None` if the receiver isn't synchronized.
+    pub fn timing_offset(&self) -> Option<f32> {
+        match self.state {
+            Sync(_) => None,
+            DecodeNID(ref recv, _) | DecodePacket(ref recv) | FlushPads(ref recv) =>
+                Some(recv.timing_offset()),
+        }
+    }
+
     /// Determine the next action to take based on the given sample.
     fn handle(&mut self, s: f32) -> StateChange {
         // Continuously track the input signal power.
@@ -151,7 +190,7 @@ impl DataUnitReceiver {
             },
             DecodeNID(ref mut recv, ref mut nidrecv) => {
                 let dibit = match recv.feed(s) {
-                    Some(StreamSymbol::Data(d)) => d,
+                    Some(StreamSymbol::Data(d)) => d.dibit(),
                     Some(s) => return Event(ReceiverEvent::Symbol(s)),
                     None => return NoChange,
                 };
@@ -179,10 +218,32 @@ impl DataUnitReceiver {
         }
     }
 
-    /// Feed in a baseband symbol, possibly producing a receiver event. Return
+    /// Feed in a baseband sample, possibly producing a receiver event. Return
     /// `Some(Ok(event))` for any normal event, `Some(Err(err))` for any error, and `None`
     /// if no event occurred.
     pub fn feed(&mut self, s: f32) -> Option<Result<ReceiverEvent>> {
+        let resampler = match self.resampler {
+            Some(ref mut r) => r,
+            None => return self.process(s),
+        };
+
+        let pending = &mut self.pending;
+        resampler.feed(s, |x| pending.push_back(x));
+
+        // Process resampled samples until one produces an event, leaving the rest for
+        // the next call. At most one event is produced per symbol, so this doesn't
+        // build up.
+        while let Some(x) = self.pending.pop_front() {
+            if let Some(event) = self.process(x) {
+                return Some(event);
+            }
+        }
+
+        None
+    }
+
+    /// Process a sample at `SAMPLE_RATE`, possibly producing a receiver event.
+    fn process(&mut self, s: f32) -> Option<Result<ReceiverEvent>> {
         match self.handle(s) {
             Change(state) => {
                 self.state = state;
@@ -1,6 +1,7 @@
-//! High-level data unit and message receivers.
+//! High-level data unit and message receivers and transmit framing.
 
 pub mod data_unit;
 pub mod nid;
 pub mod receiver;
 pub mod status;
+pub mod transmit;
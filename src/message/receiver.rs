@@ -1,9 +1,14 @@
 //! High-level receiver for receiving P25 voice, data, and trunking messages.
 
+use baseband::cqpsk::CqpskFrontEnd;
+use baseband::iq::{C4fmFrontEnd, Complex};
+use data::fields::DataPacketOpcode;
+use data::receiver::{DataPacket, DataPacketReceiver};
 use error::P25Error;
 use message::data_unit::{DataUnitReceiver, ReceiverEvent};
 use message::nid::NetworkId;
 use message::status::StreamSymbol;
+use trunking::mbt::MbtFields;
 use trunking::tsbk::{TsbkFields, TsbkReceiver};
 use voice::control::LinkControlFields;
 use voice::crypto::CryptoControlFields;
@@ -36,8 +41,13 @@ pub enum MessageEvent {
     LowSpeedDataFragment(u32),
     /// A trunking signalling packet was received.
     TrunkingControl(TsbkFields),
+    /// A multiblock trunking packet, carrying an alternate-format trunking message, was
+    /// received.
+    AltTrunkingControl(MbtFields),
     /// A voice terminator link control was received.
     VoiceTerm(LinkControlFields),
+    /// A data packet was received.
+    DataPacket(DataPacket),
 }
 
 /// Internal state of the state machine.
@@ -54,6 +64,27 @@ enum State {
     DecodeLCTerminator(VoiceLCTerminatorReceiver),
     /// Decoding a trunking signalling packet.
     DecodeTSBK(TsbkReceiver),
+    /// Decoding a data packet.
+    DecodeDataPacket(DataPacketReceiver),
+}
+
+/// Front end that demodulates complex baseband input into samples for the receiver.
+enum FrontEnd {
+    /// Demodulates a C4FM signal.
+    C4fm(C4fmFrontEnd),
+    /// Demodulates a CQPSK (LSM) signal.
+    Cqpsk(CqpskFrontEnd),
+}
+
+impl FrontEnd {
+    /// Feed in a complex sample, passing each resulting sample at `SAMPLE_RATE` to the
+    /// given function.
+    fn feed<F: FnMut(f32)>(&mut self, s: Complex, out: F) {
+        match *self {
+            FrontEnd::C4fm(ref mut f) => f.feed(s, out),
+            FrontEnd::Cqpsk(ref mut f) => f.feed(s, out),
+        }
+    }
 }
 
 /// Action the state machine should take.
@@ -72,15 +103,30 @@ pub struct MessageReceiver {
     recv: DataUnitReceiver,
     /// Current state.
     state: State,
+    /// Whether to decode TSBK and data packets with soft decisions.
+    soft: bool,
     stats: Stats,
 }
 
 impl MessageReceiver {
-    /// Create a new `MessageReceiver` in the initial state.
+    /// Create a new `MessageReceiver` in the initial state, taking input samples at
+    /// `SAMPLE_RATE`.
     pub fn new() -> MessageReceiver {
+        Self::for_receiver(DataUnitReceiver::new())
+    }
+
+    /// Create a new `MessageReceiver` in the initial state, taking input samples at the
+    /// given rate (Hz.)
+    pub fn with_sample_rate(rate: usize) -> MessageReceiver {
+        Self::for_receiver(DataUnitReceiver::with_sample_rate(rate))
+    }
+
+    /// Create a new `MessageReceiver` over the given low-level receiver.
+    fn for_receiver(recv: DataUnitReceiver) -> MessageReceiver {
         MessageReceiver {
-            recv: DataUnitReceiver::new(),
+            recv: recv,
             state: State::Idle,
+            soft: false,
             stats: Stats::default(),
         }
     }
@@ -88,6 +134,14 @@ impl MessageReceiver {
     /// Force the receiver into frame synchronization.
     pub fn resync(&mut self) { self.recv.resync(); }
 
+    /// Set whether TSBK and data packets are decoded with soft decisions, starting with
+    /// the next packet.
+    pub fn set_soft_decision(&mut self, soft: bool) { self.soft = soft; }
+
+    /// Current offset, in samples, of the symbol clock from the one derived from the
+    /// most recent frame sync, or `None` if the receiver isn't synchronized.
+    pub fn timing_offset(&self) -> Option<f32> { self.recv.timing_offset() }
+
     /// Feed in a baseband sample, possibly producing a new event or message to be handled
     /// by the given handler.
     pub fn feed(&mut self, s: f32) -> Option<MessageEvent> {
@@ -118,7 +172,7 @@ impl MessageReceiver {
 
         self.stats.merge(&mut self.recv);
 
-        let dibit = match event {
+        let soft = match event {
             ReceiverEvent::NetworkId(nid) => {
                 let next = match nid.data_unit {
                     VoiceHeader =>
@@ -133,20 +187,26 @@ impl MessageReceiver {
                         DecodeLCFrameGroup(VoiceLCFrameGroupReceiver::new()),
                     VoiceCCFrameGroup =>
                         DecodeCCFrameGroup(VoiceCCFrameGroupReceiver::new()),
-                    TrunkingSignaling =>
-                        DecodeTSBK(TsbkReceiver::new()),
-                    DataPacket => {
-                        self.recv.resync();
-                        Idle
-                    },
+                    TrunkingSignaling => DecodeTSBK(if self.soft {
+                        TsbkReceiver::soft()
+                    } else {
+                        TsbkReceiver::new()
+                    }),
+                    DataPacket => DecodeDataPacket(if self.soft {
+                        DataPacketReceiver::soft()
+                    } else {
+                        DataPacketReceiver::new()
+                    }),
                 };
 
                 return EventChange(MessageEvent::PacketNID(nid), next);
             },
             ReceiverEvent::Symbol(StreamSymbol::Status(_)) => return NoChange,
-            ReceiverEvent::Symbol(StreamSymbol::Data(dibit)) => dibit,
+            ReceiverEvent::Symbol(StreamSymbol::Data(soft)) => soft,
         };
 
+        let dibit = soft.dibit();
+
         let next = match self.state {
             DecodeHeader(ref mut head) => match head.feed(dibit) {
                 Some(Ok(h)) => {
@@ -211,7 +271,7 @@ impl MessageReceiver {
                 },
                 None => NoChange,
             },
-            DecodeTSBK(ref mut dec) => match dec.feed(dibit) {
+            DecodeTSBK(ref mut dec) => match dec.feed(soft) {
                 Some(Ok(tsbk)) => {
                     if tsbk.is_tail() {
                         self.recv.flush_pads();
@@ -225,6 +285,26 @@ impl MessageReceiver {
                 },
                 None => NoChange,
             },
+            DecodeDataPacket(ref mut dec) => match dec.feed(soft) {
+                Some(Ok(pkt)) => {
+                    self.recv.flush_pads();
+
+                    let event = match pkt.header.format() {
+                        Some(DataPacketOpcode::TrunkingPacket) => {
+                            let mbt = MbtFields::new(pkt.header, pkt.data);
+                            MessageEvent::AltTrunkingControl(mbt.unwrap())
+                        },
+                        _ => MessageEvent::DataPacket(pkt),
+                    };
+
+                    EventChange(event, Idle)
+                },
+                Some(Err(err)) => {
+                    self.recv.resync();
+                    EventChange(MessageEvent::Error(err), Idle)
+                },
+                None => NoChange,
+            },
             Idle => NoChange,
         };
 
@@ -234,6 +314,7 @@ impl MessageReceiver {
             DecodeCCFrameGroup(ref mut fg) => self.stats.merge(fg),
             DecodeLCTerminator(ref mut term) => self.stats.merge(term),
             DecodeTSBK(ref mut tsbk) => self.stats.merge(tsbk),
+            DecodeDataPacket(ref mut pkt) => self.stats.merge(pkt),
             Idle => {},
         }
 
@@ -244,3 +325,50 @@ impl MessageReceiver {
 impl HasStats for MessageReceiver {
     fn stats(&mut self) -> &mut Stats { &mut self.stats }
 }
+
+/// Receives P25 messages from complex baseband input, demodulating it with a front end
+/// into the samples consumed by a `MessageReceiver`.
+pub struct ComplexMessageReceiver {
+    /// Demodulates the input.
+    front: FrontEnd,
+    /// Receives messages from the demodulated samples.
+    recv: MessageReceiver,
+}
+
+impl ComplexMessageReceiver {
+    /// Create a new `ComplexMessageReceiver` in the initial state, taking complex
+    /// baseband samples of a C4FM signal at the given rate (Hz.)
+    pub fn c4fm(rate: usize) -> ComplexMessageReceiver {
+        Self::with_front_end(FrontEnd::C4fm(C4fmFrontEnd::new(rate)))
+    }
+
+    /// Create a new `ComplexMessageReceiver` in the initial state, taking complex
+    /// baseband samples of a CQPSK (LSM) signal at the given rate (Hz.)
+    pub fn cqpsk(rate: usize) -> ComplexMessageReceiver {
+        Self::with_front_end(FrontEnd::Cqpsk(CqpskFrontEnd::new(rate)))
+    }
+
+    /// Create a new `ComplexMessageReceiver` taking input through the given front end.
+    fn with_front_end(front: FrontEnd) -> ComplexMessageReceiver {
+        ComplexMessageReceiver {
+            front: front,
+            recv: MessageReceiver::new(),
+        }
+    }
+
+    /// Receiver fed by the front end.
+    pub fn receiver(&mut self) -> &mut MessageReceiver { &mut self.recv }
+
+    /// Feed in a complex baseband sample, passing each resulting event or message to the
+    /// given function.
+    ///
+    /// The front end can produce zero or several samples for each input sample when
+    /// resampling, so events are passed to the function rather than returned.
+    pub fn feed<F: FnMut(MessageEvent)>(&mut self, s: Complex, mut handle: F) {
+        let recv = &mut self.recv;
+
+        self.front.feed(s, |x| if let Some(e) = recv.feed(x) {
+            handle(e);
+        });
+    }
+}
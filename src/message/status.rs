@@ -93,11 +93,11 @@ impl StatusCode {
 
 /// A symbol in a transmitted P25 stream.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum StreamSymbol {
+pub enum StreamSymbol<T = bits::Dibit> {
     /// Current symbol is a status code.
     Status(StatusCode),
     /// Current symbol is a data dibit.
-    Data(bits::Dibit),
+    Data(T),
 }
 
 /// Deinterleave a P25 transmitted stream into status codes and data symbols.
@@ -120,12 +120,12 @@ impl StatusDeinterleaver {
     }
 
     /// Parse the given symbol as a status or data symbol.
-    pub fn feed(&mut self, d: bits::Dibit) -> StreamSymbol {
+    pub fn feed<T: Into<bits::Dibit>>(&mut self, d: T) -> StreamSymbol<T> {
         self.pos += 1;
         self.pos %= DIBITS_PER_UPDATE;
 
         if self.pos == 0 {
-            Status(StatusCode::from_dibit(d))
+            Status(StatusCode::from_dibit(d.into()))
         } else {
             Data(d)
         }
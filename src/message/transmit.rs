@@ -0,0 +1,127 @@
+//! Frame complete data units for transmission.
+//!
+//! Every data unit starts with the frame sync sequence and the BCH-coded NID, followed by
+//! the coded payload for the data unit type. A status symbol is inserted after every 70
+//! bits, counting from the start of the frame sync, and the data unit is padded with null
+//! dibits so that it ends on a status symbol.
+
+use baseband::sync::SYNC_GENERATOR;
+use bits::{Dibit, Dibits};
+use message::nid::{DataUnit, NetworkAccessCode, NetworkId};
+use message::status::{StatusCode, StatusInterleaver};
+
+use consts::{
+    CODING_DIBITS,
+    EXTRA_HEXBITS,
+    FRAME_GROUP_DIBITS,
+    HEADER_HEXBITS,
+    HEADER_WORD_DIBITS,
+    LC_TERM_WORD_DIBITS,
+};
+
+/// Construct a complete data unit of the given type, carrying the given coded payload
+/// dibits. The returned dibits include the frame sync, NID with the given NAC, status
+/// symbols with the given status code, and trailing pads, and so can be transmitted as
+/// is.
+///
+/// Panics if the payload length isn't valid for the data unit type.
+pub fn data_unit<T>(nac: NetworkAccessCode, du: DataUnit, payload: T, status: StatusCode)
+    -> Vec<Dibit> where T: IntoIterator<Item = Dibit>
+{
+    let nid = NetworkId::new(nac, du);
+
+    let mut dibits = vec![];
+    dibits.extend(Dibits::new(SYNC_GENERATOR.iter().cloned()));
+    dibits.extend(Dibits::new(nid.encode().iter().cloned()));
+
+    let start = dibits.len();
+    dibits.extend(payload);
+
+    assert!(valid_payload(du, dibits.len() - start));
+
+    StatusInterleaver::new(dibits.into_iter(), status).collect()
+}
+
+/// Check if the given number of payload dibits is valid for the given data unit type.
+fn valid_payload(du: DataUnit, len: usize) -> bool {
+    use message::nid::DataUnit::*;
+
+    match du {
+        VoiceHeader => len == HEADER_HEXBITS * HEADER_WORD_DIBITS,
+        VoiceSimpleTerminator => len == 0,
+        VoiceLCTerminator => len == EXTRA_HEXBITS / 2 * LC_TERM_WORD_DIBITS,
+        VoiceLCFrameGroup | VoiceCCFrameGroup => len == FRAME_GROUP_DIBITS,
+        DataPacket => len > 0 && len % CODING_DIBITS == 0,
+        TrunkingSignaling => len > 0 && len <= 3 * CODING_DIBITS &&
+            len % CODING_DIBITS == 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use consts::{NID_DIBITS, SYNC_SYMBOLS};
+    use message::nid::NidReceiver;
+    use message::status::{StatusDeinterleaver, StreamSymbol};
+
+    #[test]
+    fn test_data_unit() {
+        use message::nid::DataUnit::*;
+
+        let units = [
+            (VoiceHeader, 324, 396),
+            (VoiceSimpleTerminator, 0, 72),
+            (VoiceLCTerminator, 144, 216),
+            (VoiceLCFrameGroup, 784, 864),
+            (VoiceCCFrameGroup, 784, 864),
+            (DataPacket, 3 * CODING_DIBITS, 360),
+            (TrunkingSignaling, CODING_DIBITS, 180),
+        ];
+
+        for &(du, len, total) in units.iter() {
+            let payload: Vec<Dibit> = (0..len).map(|i| Dibit::new(i as u8 % 4)).collect();
+            let dibits = data_unit(NetworkAccessCode::Other(0x123), du,
+                                   payload.iter().cloned(), StatusCode::InboundBusy);
+
+            assert_eq!(dibits.len(), total);
+
+            let sync: Vec<Dibit> = Dibits::new(SYNC_GENERATOR.iter().cloned()).collect();
+            assert_eq!(&dibits[..SYNC_SYMBOLS], &sync[..]);
+
+            let mut stream = StatusDeinterleaver::new();
+            let mut statuses = 0;
+
+            let data: Vec<Dibit> = dibits[SYNC_SYMBOLS..].iter()
+                .filter_map(|&d| match stream.feed(d) {
+                    StreamSymbol::Data(d) => Some(d),
+                    StreamSymbol::Status(s) => {
+                        assert_eq!(s, StatusCode::InboundBusy);
+                        statuses += 1;
+                        None
+                    },
+                })
+                .collect();
+
+            assert_eq!(statuses, total / 36);
+
+            let mut nid = NidReceiver::new();
+            let nid = data[..NID_DIBITS].iter()
+                .filter_map(|&d| nid.feed(d))
+                .next().unwrap().unwrap();
+
+            assert_eq!(nid.access_code, NetworkAccessCode::Other(0x123));
+            assert_eq!(nid.data_unit, du);
+
+            let rest = &data[NID_DIBITS..];
+            assert_eq!(&rest[..len], &payload[..]);
+            assert!(rest[len..].iter().all(|d| d.bits() == 0));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_payload() {
+        data_unit(NetworkAccessCode::Default, DataUnit::TrunkingSignaling,
+                  (0..4 * CODING_DIBITS).map(|_| Dibit::new(0)), StatusCode::InboundIdle);
+    }
+}
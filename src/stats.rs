@@ -151,7 +151,10 @@ impl Stats {
             RsMediumUnrecoverable => self.rs_med.record_err(),
             RsLongUnrecoverable => self.rs_long.record_err(),
             DibitViterbiUnrecoverable => self.viterbi_dibit.record_err(),
-            UnknownNid => {},
+            TribitViterbiUnrecoverable => self.viterbi_tribit.record_err(),
+            DataHeaderChecksum | DataBlockChecksum | DataPacketChecksum |
+                UnknownDataFormat | UnknownNid | UnsupportedMessage |
+                TruncatedMessage => {},
         }
     }
 }
@@ -0,0 +1,380 @@
+//! Track which calls are active on which traffic channels from control channel grants and
+//! updates.
+//!
+//! A call starts when a grant or update is seen for a channel that has no call or has a
+//! call for a different talkgroup or unit, and ends when it's replaced by another call or
+//! when no grants or updates have been seen for it for a timeout period.
+
+use trunking::fields::{self, Channel, ChannelParamsMap, GroupTrafficUpdate,
+                       ServiceOptions, TalkGroup};
+use trunking::tsbk::{TsbkFields, TsbkOpcode, GroupVoiceGrant, UnitTrafficChannel,
+                     PhoneGrant, GroupTrafficUpdateExplicit};
+
+/// Default time (seconds) without grants or updates after which a call is ended.
+const DEFAULT_TIMEOUT: f32 = 3.0;
+
+/// A call occupying a traffic channel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ActiveCall {
+    /// Channel the call is carried on.
+    pub channel: Channel,
+    /// Frequency (Hz) that subscribers receive the call on, if the channel parameters
+    /// are known.
+    pub rx_freq: Option<u32>,
+    /// Frequency (Hz) that subscribers transmit on, if the channel parameters are known.
+    pub tx_freq: Option<u32>,
+    /// Talkgroup of a group call.
+    pub talkgroup: Option<TalkGroup>,
+    /// Originating unit of the call, if known.
+    pub src_unit: Option<u32>,
+    /// Destination unit of a unit-to-unit call.
+    pub dest_unit: Option<u32>,
+    /// Most recent options granted for the call, if known.
+    pub opts: Option<ServiceOptions>,
+    /// Time (seconds) since the call started.
+    pub duration: f32,
+    /// Time (seconds) since the last grant or update for the call.
+    idle: f32,
+}
+
+impl ActiveCall {
+    /// Whether the call is marked as an emergency.
+    pub fn emergency(&self) -> bool { self.opts.map_or(false, |o| o.emergency()) }
+    /// Whether the call is encrypted.
+    pub fn protected(&self) -> bool { self.opts.map_or(false, |o| o.protected()) }
+    /// Priority of the call, if known.
+    pub fn prio(&self) -> Option<u8> { self.opts.map(|o| o.prio()) }
+
+    /// Whether the given call is the same conversation as this one.
+    fn same(&self, other: &ActiveCall) -> bool {
+        self.talkgroup == other.talkgroup && self.dest_unit == other.dest_unit
+    }
+
+    /// Refresh the call with the given newer information.
+    fn merge(&mut self, other: &ActiveCall) {
+        self.rx_freq = other.rx_freq.or(self.rx_freq);
+        self.tx_freq = other.tx_freq.or(self.tx_freq);
+        self.src_unit = other.src_unit.or(self.src_unit);
+        self.opts = other.opts.or(self.opts);
+        self.idle = 0.0;
+    }
+}
+
+/// Change in the calls active on traffic channels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CallEvent {
+    /// A new call started on a traffic channel.
+    Start(ActiveCall),
+    /// A grant or update was seen for an ongoing call.
+    Continue(ActiveCall),
+    /// A call ended, either because it timed out or was replaced by another call on the
+    /// same channel.
+    End(ActiveCall),
+}
+
+/// Tracks the calls active on each traffic channel.
+pub struct CallTable {
+    /// Currently active calls, at most one per channel.
+    calls: Vec<ActiveCall>,
+    /// Time (seconds) without grants or updates after which a call is ended.
+    timeout: f32,
+}
+
+impl CallTable {
+    /// Create a new `CallTable` with no active calls.
+    pub fn new() -> CallTable {
+        CallTable {
+            calls: vec![],
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set the time (seconds) without grants or updates after which a call is ended.
+    pub fn set_timeout(&mut self, secs: f32) { self.timeout = secs; }
+
+    /// Currently active calls.
+    pub fn calls(&self) -> &[ActiveCall] { &self.calls[..] }
+
+    /// Retrieve the active call on the given channel, if any.
+    pub fn lookup(&self, ch: Channel) -> Option<&ActiveCall> {
+        self.calls.iter().find(|c| c.channel == ch)
+    }
+
+    /// Update the table with the given TSBK, using the given channel parameters to
+    /// resolve frequencies, and return any resulting call events.
+    pub fn feed_tsbk(&mut self, tsbk: &TsbkFields, channels: &ChannelParamsMap)
+        -> Vec<CallEvent>
+    {
+        let mut events = vec![];
+
+        if !fields::is_standard_mfg(tsbk.mfg()) || !tsbk.crc_valid() {
+            return events;
+        }
+
+        match tsbk.opcode() {
+            Some(TsbkOpcode::GroupVoiceGrant) => {
+                let g = GroupVoiceGrant::new(*tsbk);
+                let mut call = call(g.channel(), g.channel(), channels);
+                call.talkgroup = Some(g.talkgroup());
+                call.src_unit = Some(g.src_unit());
+                call.opts = Some(g.opts());
+
+                self.assign(call, &mut events);
+            },
+            Some(TsbkOpcode::GroupVoiceUpdate) => {
+                let u = GroupTrafficUpdate::new(tsbk.payload()).updates();
+
+                for (i, &(ch, tg)) in u.iter().enumerate() {
+                    // Skip unused and repeated entries.
+                    if tg == TalkGroup::Nobody || i > 0 && u[0] == (ch, tg) {
+                        continue;
+                    }
+
+                    let mut call = call(ch, ch, channels);
+                    call.talkgroup = Some(tg);
+
+                    self.assign(call, &mut events);
+                }
+            },
+            Some(TsbkOpcode::GroupVoiceUpdateExplicit) => {
+                let u = GroupTrafficUpdateExplicit::new(*tsbk);
+
+                // The call is keyed on the outbound CHANNEL(T), and subscribers transmit
+                // on the separate inbound CHANNEL(R).
+                let mut call = call(u.rx_channel(), u.tx_channel(), channels);
+                call.talkgroup = Some(u.talkgroup());
+                call.opts = Some(u.opts());
+
+                self.assign(call, &mut events);
+            },
+            Some(TsbkOpcode::UnitVoiceGrant) | Some(TsbkOpcode::UnitVoiceUpdate) => {
+                let g = UnitTrafficChannel::new(*tsbk);
+                let mut call = call(g.channel(), g.channel(), channels);
+                call.src_unit = Some(g.src_unit());
+                call.dest_unit = Some(g.dest_unit());
+
+                self.assign(call, &mut events);
+            },
+            Some(TsbkOpcode::PhoneGrant) => {
+                let g = PhoneGrant::new(*tsbk);
+                let mut call = call(g.channel(), g.channel(), channels);
+                call.src_unit = Some(g.unit());
+                call.opts = Some(g.opts());
+
+                self.assign(call, &mut events);
+            },
+            _ => {},
+        }
+
+        events
+    }
+
+    /// Advance the table by the given time (seconds) and return an `End` event for each
+    /// call that timed out.
+    pub fn advance(&mut self, secs: f32) -> Vec<CallEvent> {
+        let timeout = self.timeout;
+
+        for call in self.calls.iter_mut() {
+            call.duration += secs;
+            call.idle += secs;
+        }
+
+        let (expired, active) = self.calls.drain(..).partition(|c| c.idle >= timeout);
+        self.calls = active;
+
+        expired.into_iter().map(CallEvent::End).collect()
+    }
+
+    /// Assign the given call to its channel, ending any other call on the channel.
+    fn assign(&mut self, call: ActiveCall, events: &mut Vec<CallEvent>) {
+        let idx = match self.calls.iter().position(|c| c.channel == call.channel) {
+            Some(idx) => idx,
+            None => {
+                self.calls.push(call);
+                events.push(CallEvent::Start(call));
+                return;
+            },
+        };
+
+        if self.calls[idx].same(&call) {
+            self.calls[idx].merge(&call);
+            events.push(CallEvent::Continue(self.calls[idx]));
+        } else {
+            events.push(CallEvent::End(self.calls[idx]));
+            self.calls[idx] = call;
+            events.push(CallEvent::Start(call));
+        }
+    }
+}
+
+/// Create a new call on the given receive and transmit channels with no other
+/// information.
+fn call(rx: Channel, tx: Channel, channels: &ChannelParamsMap) -> ActiveCall {
+    ActiveCall {
+        channel: rx,
+        rx_freq: channels.rx_freq(rx),
+        tx_freq: channels.tx_freq(tx),
+        talkgroup: None,
+        src_unit: None,
+        dest_unit: None,
+        opts: None,
+        duration: 0.0,
+        idle: 0.0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use trunking::fields::ChannelParamsUpdate;
+    use trunking::tsbk::TsbkBuilder;
+
+    fn channels() -> ChannelParamsMap {
+        let mut m = ChannelParamsMap::default();
+
+        m.update(&ChannelParamsUpdate::new(&[
+            0b01100011, 0b00100010, 0b11010000, 0b00110010,
+            0b00001010, 0b00100101, 0b00010000, 0b10100010,
+        ]));
+
+        m
+    }
+
+    #[test]
+    fn test_group_call() {
+        let m = channels();
+        let mut t = CallTable::new();
+
+        let grant = TsbkBuilder::standard(TsbkOpcode::GroupVoiceGrant, [
+            0x83, 0x60, 0x09, 0x12, 0x34, 0xAB, 0xCD, 0xEF,
+        ]).build();
+
+        let e = t.feed_tsbk(&grant, &m);
+        assert_eq!(e.len(), 1);
+
+        match e[0] {
+            CallEvent::Start(c) => {
+                assert_eq!(c.channel, Channel::new(&[0x60, 0x09]));
+                assert_eq!(c.rx_freq, Some(851_062_500));
+                assert_eq!(c.tx_freq, Some(806_062_500));
+                assert_eq!(c.talkgroup, Some(TalkGroup::Other(0x1234)));
+                assert_eq!(c.src_unit, Some(0xABCDEF));
+                assert_eq!(c.dest_unit, None);
+                assert!(c.emergency());
+                assert!(!c.protected());
+                assert_eq!(c.prio(), Some(3));
+            },
+            _ => panic!(),
+        }
+
+        t.advance(2.0);
+
+        let update = TsbkBuilder::standard(TsbkOpcode::GroupVoiceUpdate, [
+            0x60, 0x09, 0x12, 0x34, 0x60, 0x09, 0x12, 0x34,
+        ]).build();
+
+        let e = t.feed_tsbk(&update, &m);
+        assert_eq!(e.len(), 1);
+
+        match e[0] {
+            CallEvent::Continue(c) => {
+                assert_eq!(c.src_unit, Some(0xABCDEF));
+                assert!(c.emergency());
+                assert_eq!(c.duration, 2.0);
+            },
+            _ => panic!(),
+        }
+
+        assert!(t.advance(2.0).is_empty());
+        assert_eq!(t.calls().len(), 1);
+
+        let e = t.advance(1.0);
+        assert_eq!(e.len(), 1);
+
+        match e[0] {
+            CallEvent::End(c) => {
+                assert_eq!(c.talkgroup, Some(TalkGroup::Other(0x1234)));
+                assert_eq!(c.duration, 5.0);
+            },
+            _ => panic!(),
+        }
+
+        assert!(t.calls().is_empty());
+    }
+
+    #[test]
+    fn test_replace() {
+        let m = channels();
+        let mut t = CallTable::new();
+
+        let update = TsbkBuilder::standard(TsbkOpcode::GroupVoiceUpdate, [
+            0x60, 0x09, 0x12, 0x34, 0x60, 0x0A, 0x56, 0x78,
+        ]).build();
+
+        let e = t.feed_tsbk(&update, &m);
+        assert_eq!(e.len(), 2);
+        assert_eq!(t.calls().len(), 2);
+
+        let c = t.lookup(Channel::new(&[0x60, 0x0A])).unwrap();
+        assert_eq!(c.rx_freq, Some(851_068_750));
+        assert_eq!(c.opts, None);
+
+        let grant = TsbkBuilder::standard(TsbkOpcode::UnitVoiceGrant, [
+            0x60, 0x0A, 0x00, 0x00, 0x01, 0x00, 0x00, 0x02,
+        ]).build();
+
+        let e = t.feed_tsbk(&grant, &m);
+        assert_eq!(e.len(), 2);
+
+        match (e[0], e[1]) {
+            (CallEvent::End(a), CallEvent::Start(b)) => {
+                assert_eq!(a.talkgroup, Some(TalkGroup::Other(0x5678)));
+                assert_eq!(b.talkgroup, None);
+                assert_eq!(b.src_unit, Some(2));
+                assert_eq!(b.dest_unit, Some(1));
+            },
+            _ => panic!(),
+        }
+
+        assert_eq!(t.calls().len(), 2);
+    }
+
+    #[test]
+    fn test_explicit() {
+        let m = channels();
+        let mut t = CallTable::new();
+
+        let update = TsbkBuilder::standard(TsbkOpcode::GroupVoiceUpdateExplicit, [
+            0x40, 0x00, 0x60, 0x01, 0x60, 0x02, 0x00, 0x10,
+        ]).build();
+
+        let e = t.feed_tsbk(&update, &m);
+
+        match e[0] {
+            CallEvent::Start(c) => {
+                assert_eq!(c.channel, Channel::new(&[0x60, 0x01]));
+                assert_eq!(c.rx_freq, Some(851_012_500));
+                assert_eq!(c.tx_freq, Some(806_018_750));
+                assert!(c.protected());
+            },
+            _ => panic!(),
+        }
+
+        // Unknown channel parameters and unrelated packets.
+        let grant = TsbkBuilder::standard(TsbkOpcode::PhoneGrant, [
+            0x00, 0x50, 0x01, 0x00, 0x10, 0x00, 0x00, 0x05,
+        ]).build();
+
+        match t.feed_tsbk(&grant, &m)[0] {
+            CallEvent::Start(c) => {
+                assert_eq!(c.rx_freq, None);
+                assert_eq!(c.src_unit, Some(5));
+            },
+            _ => panic!(),
+        }
+
+        let ack = TsbkBuilder::standard(TsbkOpcode::AckResponse, [0; 8]).build();
+        assert!(t.feed_tsbk(&ack, &m).is_empty());
+        assert_eq!(t.calls().len(), 2);
+    }
+}
@@ -1,8 +1,104 @@
 //! Decode various trunking-related packet fields.
 
 use util::{slice_u16, slice_u24, slice_u32};
+use voice::crypto::CryptoAlgorithm;
+
+/// Manufacturer ID of standard packets.
+pub const MFG_STANDARD: u8 = 0x00;
+/// Alternate manufacturer ID of standard packets.
+pub const MFG_STANDARD_ALT: u8 = 0x01;
+/// Manufacturer ID of Motorola packets.
+pub const MFG_MOTOROLA: u8 = 0x90;
+/// Manufacturer ID of Harris packets.
+pub const MFG_HARRIS: u8 = 0xA4;
+
+/// Check if the given manufacturer ID denotes a standard packet.
+pub fn is_standard_mfg(mfg: u8) -> bool {
+    mfg == MFG_STANDARD || mfg == MFG_STANDARD_ALT
+}
+
+/// Packet whose 6-bit opcode is interpreted according to its manufacturer ID, such as a
+/// TSBK or link control word.
+pub trait VendorPacket {
+    /// Opcodes of standard packets.
+    type Opcode;
+
+    /// Manufacturer ID of the packet.
+    fn mfg(&self) -> u8;
+    /// Raw 6-bit opcode of the packet.
+    fn opcode_bits(&self) -> u8;
+    /// Interpret the given 6-bit opcode of a standard packet.
+    fn standard_opcode(bits: u8) -> Option<Self::Opcode>;
+}
+
+/// Interpretation of a packet based on its manufacturer ID.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PacketKind<O, P> {
+    /// Standard packet with the given opcode.
+    Standard(O),
+    /// Manufacturer-specific packet decoded into a typed payload.
+    Vendor(P),
+    /// Packet with no decoder for its manufacturer ID and opcode.
+    Unknown {
+        /// Manufacturer ID.
+        mfg: u8,
+        /// 6-bit opcode.
+        opcode: u8,
+    },
+}
+
+/// Decoder for the manufacturer-specific packets of a single manufacturer, which returns
+/// `None` for opcodes it doesn't recognize.
+pub type VendorDecoder<T, P> = fn(&T) -> Option<P>;
+
+/// Registry of decoders for manufacturer-specific packets.
+///
+/// Packets with a non-standard manufacturer ID have their own opcode space and payload
+/// layouts, so they're only interpreted by a decoder registered for that manufacturer.
+pub struct VendorRegistry<T: VendorPacket, P> {
+    /// Decoder for each registered manufacturer ID.
+    decoders: Vec<(u8, VendorDecoder<T, P>)>,
+}
+
+impl<T: VendorPacket, P> VendorRegistry<T, P> {
+    /// Create a new `VendorRegistry` with no registered decoders.
+    pub fn new() -> Self {
+        VendorRegistry {
+            decoders: vec![],
+        }
+    }
+
+    /// Register the given decoder for packets with the given manufacturer ID, replacing
+    /// any existing decoder for that ID.
+    pub fn register(&mut self, mfg: u8, dec: VendorDecoder<T, P>) {
+        assert!(!is_standard_mfg(mfg));
+
+        self.decoders.retain(|&(m, _)| m != mfg);
+        self.decoders.push((mfg, dec));
+    }
+
+    /// Interpret the given packet.
+    pub fn decode(&self, pkt: &T) -> PacketKind<T::Opcode, P> {
+        let mfg = pkt.mfg();
+        let opcode = pkt.opcode_bits();
+
+        if is_standard_mfg(mfg) {
+            return match T::standard_opcode(opcode) {
+                Some(op) => PacketKind::Standard(op),
+                None => PacketKind::Unknown { mfg: mfg, opcode: opcode },
+            };
+        }
+
+        self.decoders.iter()
+            .find(|&&(m, _)| m == mfg)
+            .and_then(|&(_, dec)| dec(pkt))
+            .map(PacketKind::Vendor)
+            .unwrap_or(PacketKind::Unknown { mfg: mfg, opcode: opcode })
+    }
+}
 
 /// Options that can be requested/granted by a service.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct ServiceOptions(u8);
 
 impl ServiceOptions {
@@ -73,6 +169,18 @@ impl TalkGroup {
             _ => Other(bits),
         }
     }
+
+    /// Convert the talkgroup to its 16-bit representation.
+    pub fn to_bits(self) -> u16 {
+        use self::TalkGroup::*;
+
+        match self {
+            Nobody => 0x0000,
+            Default => 0x0001,
+            Everbody => 0xFFFF,
+            Other(bits) => bits,
+        }
+    }
 }
 
 /// Supported services of a control channel.
@@ -101,7 +209,7 @@ impl SystemServices {
 }
 
 /// Maps channel identifiers (maximum 16 per control channel) to their tuning parameters.
-#[derive(Default)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct ChannelParamsMap([Option<ChannelParams>; 16]);
 
 impl ChannelParamsMap {
@@ -114,6 +222,21 @@ impl ChannelParamsMap {
     pub fn lookup(&self, id: u8) -> Option<ChannelParams> {
         self.0[id as usize]
     }
+
+    /// Receive frequency of the given channel in Hz, if its parameters are known.
+    pub fn rx_freq(&self, ch: Channel) -> Option<u32> {
+        self.lookup(ch.id()).map(|p| p.rx_freq(ch.number()))
+    }
+
+    /// Transmit frequency of the given channel in Hz, if its parameters are known.
+    pub fn tx_freq(&self, ch: Channel) -> Option<u32> {
+        self.lookup(ch.id()).map(|p| p.tx_freq(ch.number()))
+    }
+
+    /// TDMA timeslot of the given channel, if its parameters are known.
+    pub fn slot(&self, ch: Channel) -> Option<u8> {
+        self.lookup(ch.id()).map(|p| p.slot(ch.number()))
+    }
 }
 
 /// Computes TX/RX frequencies and bandwidth for channel numbers within a site.
@@ -127,6 +250,8 @@ pub struct ChannelParams {
     offset: i32,
     /// Channel bandwidth in Hz.
     pub bandwidth: u32,
+    /// Number of TDMA timeslots carried on each frequency, which is 1 for FDMA channels.
+    pub slots: u8,
 }
 
 impl ChannelParams {
@@ -141,21 +266,44 @@ impl ChannelParams {
             spacing: spacing as u32 * 125,
             offset: if offset >> 8 == 0 { -off } else { off },
             bandwidth: bandwidth as u32 * 125,
+            slots: 1,
+        }
+    }
+
+    /// Create a new `ChannelParams` from the given base frequency (5Hz steps),
+    /// bandwidth (Hz), TX offset (channel spacing steps), inter-channel spacing (125Hz
+    /// steps), and number of TDMA slots per frequency.
+    fn explicit(base: u32, bandwidth: u32, offset: u16, spacing: u16, slots: u8)
+        -> ChannelParams
+    {
+        // The MSB denotes the sign and the lower 13 bits are the actual offset.
+        let off = (offset as i32 & 0x1FFF) * spacing as i32 * 125;
+
+        ChannelParams {
+            base: base * 5,
+            spacing: spacing as u32 * 125,
+            offset: if offset >> 13 == 0 { -off } else { off },
+            bandwidth: bandwidth,
+            slots: slots,
         }
     }
 
     /// Receive frequency for the given channel number in Hz.
     pub fn rx_freq(&self, ch: u16) -> u32 {
-        self.base + self.spacing * ch as u32
+        self.base + self.spacing * (ch / self.slots as u16) as u32
     }
 
+    /// TDMA timeslot of the given channel number within its frequency.
+    pub fn slot(&self, ch: u16) -> u8 { (ch % self.slots as u16) as u8 }
+
     /// Transmit frequency for the given channel number in Hz.
     pub fn tx_freq(&self, ch: u16) -> u32 {
-        self.rx_freq(ch) + self.offset as u32
+        (self.rx_freq(ch) as i32 + self.offset) as u32
     }
 }
 
 /// Options for a P25 site.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct SiteOptions(u8);
 
 impl SiteOptions {
@@ -221,13 +369,47 @@ impl<'a> AdjacentSite<'a> {
     pub fn services(&self) -> SystemServices { SystemServices::new(self.0[7]) }
 }
 
+/// Layout of a channel identifier update.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ChannelParamsFormat {
+    /// 700/800MHz FDMA layout, with bandwidth in 125Hz steps and TX offset in 250kHz
+    /// steps.
+    Standard,
+    /// VHF/UHF FDMA layout, with a bandwidth code and TX offset in channel spacing steps.
+    /// This is also used by the explicit channel identifier link control.
+    VhfUhf,
+    /// TDMA layout, with a channel type code and TX offset in channel spacing steps.
+    Tdma,
+}
+
 /// Advertisement of parameters used to calculate TX/RX frequencies within the given
 /// associated channel.
-pub struct ChannelParamsUpdate<'a>(&'a [u8]);
+///
+/// Note that this can be used for `ChannelParamsUpdate`, `ChannelParamsUpdateVu`,
+/// `ChannelParamsUpdateTdma`, and the `ChannelParamsExplicit` link control.
+pub struct ChannelParamsUpdate<'a>(&'a [u8], ChannelParamsFormat);
 
 impl<'a> ChannelParamsUpdate<'a> {
-    /// Create a new `ChannelParamsUpdate` decoder from the given payload bytes.
-    pub fn new(payload: &'a [u8]) -> Self { ChannelParamsUpdate(payload) }
+    /// Create a new `ChannelParamsUpdate` decoder for the 700/800MHz layout from the
+    /// given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self {
+        ChannelParamsUpdate(payload, ChannelParamsFormat::Standard)
+    }
+
+    /// Create a new `ChannelParamsUpdate` decoder for the VHF/UHF layout from the given
+    /// payload bytes.
+    pub fn vhf_uhf(payload: &'a [u8]) -> Self {
+        ChannelParamsUpdate(payload, ChannelParamsFormat::VhfUhf)
+    }
+
+    /// Create a new `ChannelParamsUpdate` decoder for the TDMA layout from the given
+    /// payload bytes.
+    pub fn tdma(payload: &'a [u8]) -> Self {
+        ChannelParamsUpdate(payload, ChannelParamsFormat::Tdma)
+    }
+
+    /// Layout of the update.
+    pub fn format(&self) -> ChannelParamsFormat { self.1 }
 
     /// Channel ID associated with the enclosed parameters (can be up to 16 per control
     /// channel.)
@@ -235,7 +417,20 @@ impl<'a> ChannelParamsUpdate<'a> {
 
     /// Parameters for the associated channel.
     pub fn params(&self) -> ChannelParams {
-        ChannelParams::new(self.base(), self.bandwidth(), self.offset(), self.spacing())
+        use self::ChannelParamsFormat::*;
+
+        match self.1 {
+            Standard => ChannelParams::new(self.base(), self.bandwidth(), self.offset(),
+                                           self.spacing()),
+            VhfUhf => ChannelParams::explicit(self.base(), self.bandwidth_vu(),
+                                              self.offset_explicit(), self.spacing(), 1),
+            Tdma => {
+                let (bandwidth, slots) = self.channel_type();
+
+                ChannelParams::explicit(self.base(), bandwidth, self.offset_explicit(),
+                                        self.spacing(), slots)
+            },
+        }
     }
 
     /// Bandwidth in steps of 125Hz.
@@ -243,11 +438,42 @@ impl<'a> ChannelParamsUpdate<'a> {
         (self.0[0] as u16 & 0xF) << 5 | (self.0[1] >> 3) as u16
     }
 
+    /// Bandwidth in Hz, decoded from the 4-bit VHF/UHF bandwidth code, or 0 if the code
+    /// is reserved.
+    fn bandwidth_vu(&self) -> u32 {
+        match self.0[0] & 0xF {
+            0b0100 => 6_250,
+            0b0101 => 12_500,
+            _ => 0,
+        }
+    }
+
+    /// Bandwidth in Hz and number of slots per frequency, decoded from the 4-bit TDMA
+    /// channel type code. Reserved codes are treated as a single slot with unknown
+    /// bandwidth.
+    fn channel_type(&self) -> (u32, u8) {
+        match self.0[0] & 0xF {
+            0b0000 => (6_250, 1),
+            0b0001 => (12_500, 1),
+            0b0010 => (6_250, 1),
+            0b0011 => (12_500, 2),
+            0b0100 => (25_000, 4),
+            0b0101 => (12_500, 2),
+            _ => (0, 1),
+        }
+    }
+
     /// Offset of TX frequency from base RX frequency in steps of 250kHz.
     fn offset(&self) -> u16 {
         (self.0[1] as u16 & 0x7) << 6 | (self.0[2] >> 2) as u16
     }
 
+    /// Offset of TX frequency from base RX frequency in steps of the channel spacing,
+    /// with the sign in the MSB.
+    fn offset_explicit(&self) -> u16 {
+        (self.0[1] as u16) << 6 | (self.0[2] >> 2) as u16
+    }
+
     /// Spacing between individual channel numbers in steps of 125Hz.
     fn spacing(&self) -> u16 {
         (self.0[2] as u16 & 0x3) << 8 | self.0[3] as u16
@@ -354,6 +580,85 @@ impl RegResponse {
     }
 }
 
+/// Reason a service request was denied.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "ser", derive(Serialize))]
+pub enum DenyReason {
+    /// Requesting unit isn't valid on the system.
+    RequestingUnitInvalid,
+    /// Requesting unit isn't authorized for the service.
+    RequestingUnitUnauthorized,
+    /// Target unit isn't valid on the system.
+    TargetUnitInvalid,
+    /// Target unit isn't authorized for the service.
+    TargetUnitUnauthorized,
+    /// Target unit refused the call.
+    TargetUnitRefused,
+    /// Target talkgroup isn't valid on the system.
+    TargetGroupInvalid,
+    /// Target talkgroup isn't authorized for the service.
+    TargetGroupUnauthorized,
+    /// Dialed phone number was invalid.
+    InvalidDialing,
+    /// Dialed phone number isn't authorized.
+    PhoneNumberUnauthorized,
+    /// PSTN address isn't valid.
+    PstnAddressInvalid,
+    /// Call timed out.
+    CallTimeout,
+    /// Call was ended by the landline party.
+    LandlineTerminated,
+    /// Call was ended by the subscriber unit.
+    SubscriberTerminated,
+    /// Call was preempted by a higher priority call.
+    CallPreempted,
+    /// Site access was denied.
+    SiteAccessDenied,
+    /// Requested service options aren't valid for the service.
+    OptionsInvalid,
+    /// Requested protection option isn't valid.
+    ProtectionInvalid,
+    /// Requested duplex option isn't valid.
+    DuplexInvalid,
+    /// Requested circuit/packet mode option isn't valid.
+    ModeInvalid,
+    /// System doesn't support the service.
+    Unsupported,
+    /// Reserved or user-defined reason.
+    Other(u8),
+}
+
+impl DenyReason {
+    /// Parse a deny reason from the given 8 bits.
+    pub fn from_bits(bits: u8) -> DenyReason {
+        use self::DenyReason::*;
+
+        match bits {
+            0x10 => RequestingUnitInvalid,
+            0x11 => RequestingUnitUnauthorized,
+            0x20 => TargetUnitInvalid,
+            0x21 => TargetUnitUnauthorized,
+            0x2F => TargetUnitRefused,
+            0x30 => TargetGroupInvalid,
+            0x31 => TargetGroupUnauthorized,
+            0x40 => InvalidDialing,
+            0x41 => PhoneNumberUnauthorized,
+            0x42 => PstnAddressInvalid,
+            0x50 => CallTimeout,
+            0x51 => LandlineTerminated,
+            0x52 => SubscriberTerminated,
+            0x5F => CallPreempted,
+            0x60 => SiteAccessDenied,
+            0xF0 => OptionsInvalid,
+            0xF1 => ProtectionInvalid,
+            0xF2 => DuplexInvalid,
+            0xF3 => ModeInvalid,
+            0xFF => Unsupported,
+            b => Other(b),
+        }
+    }
+}
+
 /// Request for a target unit to call a source unit.
 pub struct UnitCallAlert<'a>(&'a [u8]);
 
@@ -395,6 +700,116 @@ impl<'a> PhoneAlert<'a> {
     pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[5..=7]) }
 }
 
+/// Status of a unit, sent to a target unit.
+pub struct UnitStatusUpdate<'a>(&'a [u8]);
+
+impl<'a> UnitStatusUpdate<'a> {
+    /// Create a new `UnitStatusUpdate` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { UnitStatusUpdate(payload) }
+
+    /// User-defined status.
+    pub fn user_status(&self) -> u8 { self.0[0] }
+    /// Unit-defined status.
+    pub fn unit_status(&self) -> u8 { self.0[1] }
+    /// Target unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[2..=4]) }
+    /// Unit whose status is given.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[5..=7]) }
+}
+
+/// Request for a target unit to report its status to a source unit.
+///
+/// Note that this can be used for both the `UnitStatusQuery` TSBK and the
+/// `UnitStatusRequest` link control.
+pub struct UnitStatusQuery<'a>(&'a [u8]);
+
+impl<'a> UnitStatusQuery<'a> {
+    /// Create a new `UnitStatusQuery` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { UnitStatusQuery(payload) }
+
+    /// Unit being queried.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[2..=4]) }
+    /// Requesting unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[5..=7]) }
+}
+
+/// Short predefined message sent to a target unit.
+pub struct UnitShortMessage<'a>(&'a [u8]);
+
+impl<'a> UnitShortMessage<'a> {
+    /// Create a new `UnitShortMessage` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { UnitShortMessage(payload) }
+
+    /// User-defined message code.
+    pub fn msg(&self) -> u16 { slice_u16(&self.0[0..=1]) }
+    /// Target unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[2..=4]) }
+    /// Sending unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[5..=7]) }
+}
+
+/// Command for a target unit to perform an extended function, such as a radio check or
+/// inhibit.
+pub struct ExtendedFunction<'a>(&'a [u8]);
+
+impl<'a> ExtendedFunction<'a> {
+    /// Create a new `ExtendedFunction` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { ExtendedFunction(payload) }
+
+    /// Class of the function.
+    pub fn class(&self) -> u8 { self.0[0] }
+    /// Function within the class.
+    pub fn operand(&self) -> u8 { self.0[1] }
+    /// Arguments of the function, usually the unit requesting it.
+    pub fn args(&self) -> u32 { slice_u24(&self.0[2..=4]) }
+    /// Target unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[5..=7]) }
+}
+
+/// Request for a target unit to report its current talkgroup affiliation.
+pub struct GroupAffiliationQuery<'a>(&'a [u8]);
+
+impl<'a> GroupAffiliationQuery<'a> {
+    /// Create a new `GroupAffiliationQuery` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { GroupAffiliationQuery(payload) }
+
+    /// Unit being queried.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[2..=4]) }
+    /// Requesting unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[5..=7]) }
+}
+
+/// Services available and supported by the current system.
+pub struct SystemServiceBroadcast<'a>(&'a [u8]);
+
+impl<'a> SystemServiceBroadcast<'a> {
+    /// Create a new `SystemServiceBroadcast` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { SystemServiceBroadcast(payload) }
+
+    /// Bitmap of services currently available on the system.
+    pub fn available(&self) -> u32 { slice_u24(&self.0[1..=3]) }
+    /// Bitmap of services supported by the system, whether or not currently available.
+    pub fn supported(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Minimum priority level a service request must have to be considered.
+    pub fn request_prio(&self) -> u8 { self.0[7] }
+}
+
+/// Encryption parameters assigned to a unit for protected services, as carried by the
+/// protection parameter broadcast and update messages.
+pub struct ProtectionParams<'a>(&'a [u8]);
+
+impl<'a> ProtectionParams<'a> {
+    /// Create a new `ProtectionParams` decoder from the given payload bytes.
+    pub fn new(payload: &'a [u8]) -> Self { ProtectionParams(payload) }
+
+    /// Encryption algorithm to use.
+    pub fn alg(&self) -> CryptoAlgorithm { CryptoAlgorithm::from_bits(self.0[2]) }
+    /// Encryption key to use.
+    pub fn key(&self) -> u16 { slice_u16(&self.0[3..=4]) }
+    /// Unit the parameters are assigned to.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[5..=7]) }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -410,6 +825,71 @@ mod test {
         assert_eq!(p.rx_freq(0b1001), 851_062_500);
     }
 
+    #[test]
+    fn test_channel_params_vhf_uhf() {
+        // 151.0MHz base, 12.5kHz bandwidth, +600kHz offset, 2.5kHz spacing.
+        let buf = [0x25, 0x83, 0xC0, 0x14, 0x01, 0xCC, 0xD0, 0xC0];
+        let u = ChannelParamsUpdate::vhf_uhf(&buf[..]);
+
+        assert_eq!(u.format(), ChannelParamsFormat::VhfUhf);
+        assert_eq!(u.id(), 2);
+
+        let p = u.params();
+        assert_eq!(p.base, 151_000_000);
+        assert_eq!(p.spacing, 2_500);
+        assert_eq!(p.offset, 600_000);
+        assert_eq!(p.bandwidth, 12_500);
+        assert_eq!(p.slots, 1);
+        assert_eq!(p.rx_freq(4), 151_010_000);
+        assert_eq!(p.tx_freq(4), 151_610_000);
+
+        // Negative offset.
+        let buf = [0x25, 0x03, 0xC0, 0x14, 0x01, 0xCC, 0xD0, 0xC0];
+        let p = ChannelParamsUpdate::vhf_uhf(&buf[..]).params();
+        assert_eq!(p.offset, -600_000);
+        assert_eq!(p.tx_freq(4), 150_410_000);
+    }
+
+    #[test]
+    fn test_channel_params_tdma() {
+        // 851.00625MHz base, 2-slot 12.5kHz, -45MHz offset, 12.5kHz spacing.
+        let buf = [0x33, 0x38, 0x40, 0x64, 0x0A, 0x25, 0x10, 0xA2];
+        let u = ChannelParamsUpdate::tdma(&buf[..]);
+
+        assert_eq!(u.id(), 3);
+
+        let p = u.params();
+        assert_eq!(p.base, 851_006_250);
+        assert_eq!(p.spacing, 12_500);
+        assert_eq!(p.offset, -45_000_000);
+        assert_eq!(p.bandwidth, 12_500);
+        assert_eq!(p.slots, 2);
+        assert_eq!(p.rx_freq(6), 851_043_750);
+        assert_eq!(p.rx_freq(7), 851_043_750);
+        assert_eq!(p.slot(6), 0);
+        assert_eq!(p.slot(7), 1);
+        assert_eq!(p.tx_freq(7), 806_043_750);
+
+        let mut m = ChannelParamsMap::default();
+        m.update(&u);
+
+        let ch = Channel::new(&[0x30, 0x07]);
+        assert_eq!(m.rx_freq(ch), Some(851_043_750));
+        assert_eq!(m.slot(ch), Some(1));
+    }
+
+    #[test]
+    fn test_channel_params_map() {
+        let mut m = ChannelParamsMap::default();
+        m.0[6] = Some(ChannelParams::new(170201250, 0x64, 0b010110100, 0x32));
+
+        let ch = Channel::new(&[0x60, 0x09]);
+        assert_eq!(m.rx_freq(ch), Some(851_062_500));
+        assert_eq!(m.tx_freq(ch), Some(806_062_500));
+        assert_eq!(m.rx_freq(Channel::new(&[0x50, 0x09])), None);
+        assert_eq!(m.tx_freq(Channel::new(&[0x50, 0x09])), None);
+    }
+
     #[test]
     fn test_group_traffic_updates() {
         let buf = [
@@ -432,4 +912,44 @@ mod test {
         assert_eq!(u[1].0.number(), 0b000100000001);
         assert_eq!(u[1].1, TalkGroup::Other(0b1010101010101010));
     }
+
+    #[test]
+    fn test_unit_status_update() {
+        let buf = [0x12, 0x34, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45];
+        let u = UnitStatusUpdate::new(&buf[..]);
+
+        assert_eq!(u.user_status(), 0x12);
+        assert_eq!(u.unit_status(), 0x34);
+        assert_eq!(u.dest_unit(), 0xABCDEF);
+        assert_eq!(u.src_unit(), 0x012345);
+
+        let m = UnitShortMessage::new(&buf[..]);
+        assert_eq!(m.msg(), 0x1234);
+        assert_eq!(m.dest_unit(), 0xABCDEF);
+        assert_eq!(m.src_unit(), 0x012345);
+
+        let f = ExtendedFunction::new(&buf[..]);
+        assert_eq!(f.class(), 0x12);
+        assert_eq!(f.operand(), 0x34);
+        assert_eq!(f.args(), 0xABCDEF);
+        assert_eq!(f.dest_unit(), 0x012345);
+    }
+
+    #[test]
+    fn test_system_service_broadcast() {
+        let buf = [0x00, 0x12, 0x34, 0x56, 0xFF, 0xFF, 0xFE, 0x07];
+        let s = SystemServiceBroadcast::new(&buf[..]);
+
+        assert_eq!(s.available(), 0x123456);
+        assert_eq!(s.supported(), 0xFFFFFE);
+        assert_eq!(s.request_prio(), 0x07);
+    }
+
+    #[test]
+    fn test_deny_reason() {
+        assert_eq!(DenyReason::from_bits(0x10), DenyReason::RequestingUnitInvalid);
+        assert_eq!(DenyReason::from_bits(0x2F), DenyReason::TargetUnitRefused);
+        assert_eq!(DenyReason::from_bits(0xFF), DenyReason::Unsupported);
+        assert_eq!(DenyReason::from_bits(0x80), DenyReason::Other(0x80));
+    }
 }
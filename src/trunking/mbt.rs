@@ -0,0 +1,455 @@
+//! Decode multiblock trunking (MBT) packets.
+//!
+//! MBT packets carry alternate-format trunking messages in a data packet, for messages
+//! whose explicit channels or extended addresses don't fit in a single TSBK. The fields
+//! of each message are split between the data header, which carries the opcode, a unit
+//! address, and two argument bytes, and the data blocks that follow.
+//!
+//! Each message follows the alternate-format layout of the same message in TIA-102.AABC,
+//! named by its mnemonic there. Explicit channels come in the order CHANNEL(T), the
+//! outbound channel the site transmits on, then CHANNEL(R), the inbound channel it
+//! receives on.
+
+use data::fields::DataPacketOpcode;
+use data::header::{DataHeaderFields, TrunkingHeader};
+use error::{P25Error, Result};
+use trunking::fields::{self, Channel, ServiceOptions, SystemServices, TalkGroup,
+                       RegResponse};
+use trunking::tsbk::{TsbkOpcode, InboundTsbkOpcode};
+use util::{slice_u16, slice_u24};
+
+/// A received multiblock trunking packet.
+#[derive(Clone)]
+pub struct MbtFields {
+    /// Data header.
+    header: DataHeaderFields,
+    /// Data bytes carried by the blocks, with the packet checksum removed.
+    data: Vec<u8>,
+}
+
+impl MbtFields {
+    /// Create a new `MbtFields` from the given data header and block bytes, or return
+    /// `None` if the header isn't for a trunking packet.
+    pub fn new(header: DataHeaderFields, data: Vec<u8>) -> Option<MbtFields> {
+        if header.format() != Some(DataPacketOpcode::TrunkingPacket) {
+            return None;
+        }
+
+        Some(MbtFields {
+            header: header,
+            data: data,
+        })
+    }
+
+    /// Data header of the packet.
+    pub fn header(&self) -> TrunkingHeader { TrunkingHeader::new(self.header) }
+    /// Whether the packet was sent by the system rather than a subscriber.
+    pub fn outbound(&self) -> bool { self.header.outbound() }
+    /// Manufacturer ID, which determines if the packet is standardized.
+    pub fn mfg(&self) -> u8 { self.header.mfg() }
+    /// Type of message carried by the packet, interpreting the packet as sent by the
+    /// system.
+    pub fn opcode(&self) -> Option<TsbkOpcode> { self.header().opcode() }
+    /// Type of message carried by the packet, interpreting the packet as sent by a
+    /// subscriber unit.
+    pub fn inbound_opcode(&self) -> Option<InboundTsbkOpcode> {
+        InboundTsbkOpcode::from_bits(self.header.bytes()[7] & 0x3F)
+    }
+    /// Data bytes carried by the blocks following the header.
+    pub fn data(&self) -> &[u8] { &self.data[..] }
+
+    /// Decode the standard outbound message carried by the packet.
+    ///
+    /// Return `UnsupportedMessage` if the packet is inbound, manufacturer-specific, or of
+    /// a type without a decoder here, and `TruncatedMessage` if it doesn't have enough
+    /// blocks for the fields of its type.
+    pub fn message(&self) -> Result<MbtMessage<'_>> {
+        if !fields::is_standard_mfg(self.mfg()) || !self.outbound() {
+            return Err(P25Error::UnsupportedMessage);
+        }
+
+        let msg = match self.opcode() {
+            Some(TsbkOpcode::GroupVoiceGrant) =>
+                GroupVoiceGrantExplicit::new(self).map(MbtMessage::GroupVoiceGrant),
+            Some(TsbkOpcode::UnitVoiceGrant) =>
+                UnitVoiceGrantExtended::new(self).map(MbtMessage::UnitVoiceGrant),
+            Some(TsbkOpcode::UnitRegResponse) =>
+                UnitRegResponseExtended::new(self).map(MbtMessage::UnitRegResponse),
+            Some(TsbkOpcode::RfssStatusBroadcast) =>
+                RfssStatusExplicit::new(self).map(MbtMessage::RfssStatus),
+            Some(TsbkOpcode::NetworkStatusBroadcast) =>
+                NetworkStatusExplicit::new(self).map(MbtMessage::NetworkStatus),
+            Some(TsbkOpcode::AdjacentSite) =>
+                AdjacentSiteExplicit::new(self).map(MbtMessage::AdjacentSite),
+            _ => return Err(P25Error::UnsupportedMessage),
+        };
+
+        match msg {
+            Some(msg) => Ok(msg),
+            None => Err(P25Error::TruncatedMessage),
+        }
+    }
+
+    /// Check if the blocks carry at least the given number of data bytes.
+    fn has(&self, len: usize) -> bool { self.data.len() >= len }
+
+    /// Header bytes.
+    fn head(&self) -> &[u8] { &self.header.bytes()[..] }
+}
+
+/// Standard outbound message carried by an MBT packet.
+pub enum MbtMessage<'a> {
+    GroupVoiceGrant(GroupVoiceGrantExplicit<'a>),
+    UnitVoiceGrant(UnitVoiceGrantExtended<'a>),
+    UnitRegResponse(UnitRegResponseExtended<'a>),
+    RfssStatus(RfssStatusExplicit<'a>),
+    NetworkStatus(NetworkStatusExplicit<'a>),
+    AdjacentSite(AdjacentSiteExplicit<'a>),
+}
+
+/// Indicates a talkgroup has been granted a voice traffic channel, with explicit
+/// transmit and receive channels (GRP_V_CH_GRANT.)
+///
+/// The header carries the source address in octets 3–5 and the service options in octet
+/// 8. The blocks carry CHANNEL(T) in octets 2–3, CHANNEL(R) in octets 4–5, and the
+/// talkgroup in octets 6–7, for 8 octets in all.
+pub struct GroupVoiceGrantExplicit<'a>(&'a MbtFields);
+
+impl<'a> GroupVoiceGrantExplicit<'a> {
+    /// Create a new `GroupVoiceGrantExplicit` decoder from the base MBT decoder, or
+    /// return `None` if the packet is too short.
+    pub fn new(mbt: &'a MbtFields) -> Option<Self> {
+        if mbt.has(8) { Some(GroupVoiceGrantExplicit(mbt)) } else { None }
+    }
+
+    /// Options requested/granted for the traffic channel.
+    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.0.head()[8]) }
+    /// Unit that initiated the conversation.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0.head()[3..=5]) }
+    /// Channel the subscriber receives on, i.e. CHANNEL(T).
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0.data[2..=3]) }
+    /// Channel the subscriber transmits on, i.e. CHANNEL(R).
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0.data[4..=5]) }
+    /// Talkgroup for the conversation.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0.data[6..=7]) }
+}
+
+/// Indicates a pair of units have been granted a voice traffic channel, with the fully
+/// qualified ID of the source unit and explicit transmit and receive channels
+/// (UU_V_CH_GRANT.)
+///
+/// The header carries the destination address in octets 3–5. The blocks carry the
+/// source WACN ID in the upper 20 bits of octets 0–2, the source System ID in the lower
+/// 12 bits of octets 2–3, the source ID in octets 4–6, CHANNEL(T) in octets 7–8, and
+/// CHANNEL(R) in octets 9–10, for 11 octets in all.
+pub struct UnitVoiceGrantExtended<'a>(&'a MbtFields);
+
+impl<'a> UnitVoiceGrantExtended<'a> {
+    /// Create a new `UnitVoiceGrantExtended` decoder from the base MBT decoder, or
+    /// return `None` if the packet is too short.
+    pub fn new(mbt: &'a MbtFields) -> Option<Self> {
+        if mbt.has(11) { Some(UnitVoiceGrantExtended(mbt)) } else { None }
+    }
+
+    /// Destination unit of the call.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0.head()[3..=5]) }
+    /// WACN ID of the source unit's home system.
+    pub fn src_wacn(&self) -> u32 { slice_u24(&self.0.data[0..=2]) >> 4 }
+    /// System ID of the source unit's home system.
+    pub fn src_system(&self) -> u16 { slice_u16(&self.0.data[2..=3]) & 0xFFF }
+    /// ID of the source unit within its home system.
+    pub fn src_id(&self) -> u32 { slice_u24(&self.0.data[4..=6]) }
+    /// Channel the subscribers receive on, i.e. CHANNEL(T).
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0.data[7..=8]) }
+    /// Channel the subscribers transmit on, i.e. CHANNEL(R).
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0.data[9..=10]) }
+}
+
+/// Response given to a unit registration, with the fully qualified ID of the unit
+/// (U_REG_RSP.)
+///
+/// The header carries the destination address in octets 3–5 and the response in the
+/// lower 2 bits of octet 8. The blocks carry the WACN ID in the upper 20 bits of octets
+/// 0–2, the System ID in the lower 12 bits of octets 2–3, the unit ID in octets 4–6, and
+/// the assigned address in octets 7–9, for 10 octets in all.
+pub struct UnitRegResponseExtended<'a>(&'a MbtFields);
+
+impl<'a> UnitRegResponseExtended<'a> {
+    /// Create a new `UnitRegResponseExtended` decoder from the base MBT decoder, or
+    /// return `None` if the packet is too short.
+    pub fn new(mbt: &'a MbtFields) -> Option<Self> {
+        if mbt.has(10) { Some(UnitRegResponseExtended(mbt)) } else { None }
+    }
+
+    /// System response to the registration request.
+    pub fn response(&self) -> RegResponse {
+        RegResponse::from_bits(self.0.head()[8] & 0b11)
+    }
+    /// Address the response is sent to.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0.head()[3..=5]) }
+    /// WACN ID of the unit's home system.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0.data[0..=2]) >> 4 }
+    /// System ID of the unit's home system.
+    pub fn system(&self) -> u16 { slice_u16(&self.0.data[2..=3]) & 0xFFF }
+    /// ID of the unit within its home system.
+    pub fn src_id(&self) -> u32 { slice_u24(&self.0.data[4..=6]) }
+    /// Address assigned to the unit on the current system.
+    pub fn src_addr(&self) -> u32 { slice_u24(&self.0.data[7..=9]) }
+}
+
+/// Advertisement of parameters for the current site, with explicit transmit and receive
+/// channels (RFSS_STS_BCST.)
+///
+/// The header carries the area in octet 3, the networked flag in bit 4 of octet 4, the
+/// System ID in the lower 12 bits of octets 4–5, the RFSS ID in octet 8, and the Site ID
+/// in octet 9. The blocks carry CHANNEL(T) in octets 0–1, CHANNEL(R) in octets 2–3, and
+/// the services in octet 4, for 5 octets in all.
+pub struct RfssStatusExplicit<'a>(&'a MbtFields);
+
+impl<'a> RfssStatusExplicit<'a> {
+    /// Create a new `RfssStatusExplicit` decoder from the base MBT decoder, or return
+    /// `None` if the packet is too short.
+    pub fn new(mbt: &'a MbtFields) -> Option<Self> {
+        if mbt.has(5) { Some(RfssStatusExplicit(mbt)) } else { None }
+    }
+
+    /// Location registration area of current site.
+    pub fn area(&self) -> u8 { self.0.head()[3] }
+    /// Whether the site is networked with the RFSS controller, which determines if it
+    /// can communicate with other sites.
+    pub fn networked(&self) -> bool { self.0.head()[4] & 0b10000 != 0 }
+    /// System ID of current site within WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0.head()[4..=5]) & 0xFFF }
+    /// RF Subsystem ID of current site within System.
+    pub fn rfss(&self) -> u8 { self.0.head()[8] }
+    /// Site ID of current site within RFSS.
+    pub fn site(&self) -> u8 { self.0.head()[9] }
+    /// Channel the current site's control channel transmits on, i.e. CHANNEL(T).
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0.data[0..=1]) }
+    /// Channel the current site's control channel receives on, i.e. CHANNEL(R).
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0.data[2..=3]) }
+    /// Services supported by the current site.
+    pub fn services(&self) -> SystemServices { SystemServices::new(self.0.data[4]) }
+}
+
+/// Advertisement of parameters for the current WACN, with explicit transmit and receive
+/// channels (NET_STS_BCST.)
+///
+/// The header carries the area in octet 3 and the System ID in the lower 12 bits of
+/// octets 4–5. The blocks carry the WACN ID in the upper 20 bits of octets 0–2,
+/// CHANNEL(T) in octets 3–4, CHANNEL(R) in octets 5–6, and the services in octet 7, for 8
+/// octets in all.
+pub struct NetworkStatusExplicit<'a>(&'a MbtFields);
+
+impl<'a> NetworkStatusExplicit<'a> {
+    /// Create a new `NetworkStatusExplicit` decoder from the base MBT decoder, or return
+    /// `None` if the packet is too short.
+    pub fn new(mbt: &'a MbtFields) -> Option<Self> {
+        if mbt.has(8) { Some(NetworkStatusExplicit(mbt)) } else { None }
+    }
+
+    /// Location registration area of the current site.
+    pub fn area(&self) -> u8 { self.0.head()[3] }
+    /// System ID of the current site within the WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0.head()[4..=5]) & 0xFFF }
+    /// WACN ID within the communications network.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0.data[0..=2]) >> 4 }
+    /// Channel the current site's control channel transmits on, i.e. CHANNEL(T).
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0.data[3..=4]) }
+    /// Channel the current site's control channel receives on, i.e. CHANNEL(R).
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0.data[5..=6]) }
+    /// Services supported by the current site.
+    pub fn services(&self) -> SystemServices { SystemServices::new(self.0.data[7]) }
+}
+
+/// Advertisement of an adjacent/nearby site, with explicit transmit and receive
+/// channels (ADJ_STS_BCST.)
+///
+/// The header carries the area in octet 3, the site options in the upper 4 bits of octet
+/// 4, the System ID in the lower 12 bits of octets 4–5, the RFSS ID in octet 8, and the
+/// Site ID in octet 9. The blocks carry CHANNEL(T) in octets 0–1, CHANNEL(R) in octets
+/// 2–3, and the services in octet 4, for 5 octets in all.
+pub struct AdjacentSiteExplicit<'a>(&'a MbtFields);
+
+impl<'a> AdjacentSiteExplicit<'a> {
+    /// Create a new `AdjacentSiteExplicit` decoder from the base MBT decoder, or return
+    /// `None` if the packet is too short.
+    pub fn new(mbt: &'a MbtFields) -> Option<Self> {
+        if mbt.has(5) { Some(AdjacentSiteExplicit(mbt)) } else { None }
+    }
+
+    /// Location registration area of adjacent site.
+    pub fn area(&self) -> u8 { self.0.head()[3] }
+    /// Description of adjacent site.
+    pub fn opts(&self) -> fields::SiteOptions {
+        fields::SiteOptions::new(self.0.head()[4] >> 4)
+    }
+    /// System ID of adjacent site within WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0.head()[4..=5]) & 0xFFF }
+    /// RF Subsystem ID of adjacent site within the System.
+    pub fn rfss(&self) -> u8 { self.0.head()[8] }
+    /// Site ID of adjacent site within the RFSS.
+    pub fn site(&self) -> u8 { self.0.head()[9] }
+    /// Channel the adjacent site's control channel transmits on, i.e. CHANNEL(T).
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0.data[0..=1]) }
+    /// Channel the adjacent site's control channel receives on, i.e. CHANNEL(R).
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0.data[2..=3]) }
+    /// Services supported by the adjacent site.
+    pub fn services(&self) -> SystemServices { SystemServices::new(self.0.data[4]) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mbt(opcode: u8, head: [u8; 5], args: [u8; 2], data: &[u8]) -> MbtFields {
+        let blocks = ((data.len() + 4) + 11) / 12;
+
+        MbtFields::new(DataHeaderFields::new([
+            0b00110111, 0b00111101, 0x00, head[0], head[1], head[2],
+            0x80 | blocks as u8, opcode, args[0], args[1], 0x00, 0x00,
+        ]), data.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_fields() {
+        let m = mbt(0x00, [0x12, 0x34, 0x56, 0, 0], [0x80, 0x00], &[0; 8]);
+        assert!(m.outbound());
+        assert_eq!(m.mfg(), 0);
+        assert_eq!(m.opcode(), Some(TsbkOpcode::GroupVoiceGrant));
+        assert_eq!(m.inbound_opcode(), Some(InboundTsbkOpcode::GroupVoiceRequest));
+        assert_eq!(m.header().unit(), 0x123456);
+        assert_eq!(m.data().len(), 8);
+
+        // Non-trunking packets are rejected.
+        assert!(MbtFields::new(DataHeaderFields::new([0b00010101; 12]), vec![]).is_none());
+    }
+
+    #[test]
+    fn test_group_voice_grant() {
+        let m = mbt(0x00, [0xAB, 0xCD, 0xEF, 0, 0], [0x83, 0x00], &[
+            0x00, 0x00, 0x60, 0x01, 0x61, 0x02, 0x12, 0x34,
+        ]);
+
+        let g = match m.message() {
+            Ok(MbtMessage::GroupVoiceGrant(g)) => g,
+            _ => panic!(),
+        };
+
+        assert!(g.opts().emergency());
+        assert_eq!(g.opts().prio(), 3);
+        assert_eq!(g.src_unit(), 0xABCDEF);
+        assert_eq!(g.rx_channel().id(), 6);
+        assert_eq!(g.rx_channel().number(), 1);
+        assert_eq!(g.tx_channel().number(), 0x102);
+        assert_eq!(g.talkgroup(), TalkGroup::Other(0x1234));
+
+        // Too short for the message.
+        let m = mbt(0x00, [0xAB, 0xCD, 0xEF, 0, 0], [0x83, 0x00], &[0; 4]);
+        assert_eq!(m.message().err(), Some(P25Error::TruncatedMessage));
+
+        // No decoder for the message.
+        let m = mbt(0x02, [0xAB, 0xCD, 0xEF, 0, 0], [0x83, 0x00], &[0; 8]);
+        assert_eq!(m.message().err(), Some(P25Error::UnsupportedMessage));
+
+        // Vendor packets aren't decoded.
+        let mut v = mbt(0x00, [0xAB, 0xCD, 0xEF, 0, 0], [0x83, 0x00], &[0; 8]);
+        let mut buf = *v.header.bytes();
+        buf[2] = 0x90;
+        v.header = DataHeaderFields::new(buf);
+        assert_eq!(v.message().err(), Some(P25Error::UnsupportedMessage));
+    }
+
+    #[test]
+    fn test_unit_voice_grant() {
+        let m = mbt(0x04, [0x00, 0x00, 0x2A, 0, 0], [0x00, 0x00], &[
+            0xBE, 0xE0, 0x01, 0x23, 0x12, 0x34, 0x56, 0x60, 0x01, 0x61, 0x02, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        let g = match m.message() {
+            Ok(MbtMessage::UnitVoiceGrant(g)) => g,
+            _ => panic!(),
+        };
+
+        assert_eq!(g.dest_unit(), 0x2A);
+        assert_eq!(g.src_wacn(), 0xBEE00);
+        assert_eq!(g.src_system(), 0x123);
+        assert_eq!(g.src_id(), 0x123456);
+        assert_eq!(g.rx_channel(), Channel::new(&[0x60, 0x01]));
+        assert_eq!(g.tx_channel(), Channel::new(&[0x61, 0x02]));
+    }
+
+    #[test]
+    fn test_unit_reg_response() {
+        let m = mbt(0x2C, [0x00, 0x00, 0x2A, 0, 0], [0x02, 0x00], &[
+            0xBE, 0xE0, 0x01, 0x23, 0x12, 0x34, 0x56, 0x00, 0x00, 0x2A, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        let r = match m.message() {
+            Ok(MbtMessage::UnitRegResponse(r)) => r,
+            _ => panic!(),
+        };
+
+        assert_eq!(r.response(), RegResponse::Deny);
+        assert_eq!(r.dest_unit(), 0x2A);
+        assert_eq!(r.wacn(), 0xBEE00);
+        assert_eq!(r.system(), 0x123);
+        assert_eq!(r.src_id(), 0x123456);
+        assert_eq!(r.src_addr(), 0x2A);
+    }
+
+    #[test]
+    fn test_status() {
+        let m = mbt(0x3A, [0x07, 0x11, 0x23, 0, 0], [0x02, 0x03], &[
+            0x60, 0x05, 0x61, 0x05, 0x70, 0x00, 0x00, 0x00,
+        ]);
+
+        let r = match m.message() {
+            Ok(MbtMessage::RfssStatus(r)) => r,
+            _ => panic!(),
+        };
+
+        assert_eq!(r.area(), 7);
+        assert!(r.networked());
+        assert_eq!(r.system(), 0x123);
+        assert_eq!(r.rfss(), 2);
+        assert_eq!(r.site(), 3);
+        assert_eq!(r.tx_channel(), Channel::new(&[0x60, 0x05]));
+        assert_eq!(r.rx_channel(), Channel::new(&[0x61, 0x05]));
+        assert!(r.services().has_voice());
+
+        let m = mbt(0x3B, [0x07, 0x01, 0x23, 0, 0], [0x00, 0x00], &[
+            0xBE, 0xE0, 0x00, 0x60, 0x05, 0x61, 0x05, 0x70,
+        ]);
+
+        let n = match m.message() {
+            Ok(MbtMessage::NetworkStatus(n)) => n,
+            _ => panic!(),
+        };
+
+        assert_eq!(n.area(), 7);
+        assert_eq!(n.system(), 0x123);
+        assert_eq!(n.wacn(), 0xBEE00);
+        assert_eq!(n.tx_channel(), Channel::new(&[0x60, 0x05]));
+        assert_eq!(n.rx_channel(), Channel::new(&[0x61, 0x05]));
+        assert!(n.services().has_registration());
+
+        let m = mbt(0x3C, [0x07, 0x41, 0x23, 0, 0], [0x02, 0x04], &[
+            0x60, 0x08, 0x61, 0x08, 0x70, 0x00, 0x00, 0x00,
+        ]);
+
+        let a = match m.message() {
+            Ok(MbtMessage::AdjacentSite(a)) => a,
+            _ => panic!(),
+        };
+
+        assert!(a.opts().failing());
+        assert_eq!(a.system(), 0x123);
+        assert_eq!(a.rfss(), 2);
+        assert_eq!(a.site(), 4);
+        assert_eq!(a.rx_channel(), Channel::new(&[0x61, 0x08]));
+        assert!(a.services().has_data());
+    }
+}
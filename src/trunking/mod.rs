@@ -1,4 +1,11 @@
 //! Trunking control message decoding.
+//!
+//! The trackers in `system`, `calls`, and `units` only consider standard packets, and
+//! ignore TSBKs with an invalid CRC.
 
+pub mod calls;
 pub mod fields;
+pub mod mbt;
+pub mod system;
 pub mod tsbk;
+pub mod units;
@@ -0,0 +1,764 @@
+//! Track the state of a trunked site from its control channel broadcasts.
+
+use trunking::fields::{self, Channel, ChannelParamsFormat, ChannelParamsMap,
+                       ChannelParamsUpdate, SiteOptions, SystemServices};
+use trunking::mbt::{MbtFields, MbtMessage};
+use trunking::tsbk::TsbkFields;
+use voice::control::{self, LinkControlFields};
+
+/// Information about a site adjacent to the current one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AdjacentSiteInfo {
+    /// Location registration area of the site.
+    pub area: u8,
+    /// System ID of the site within the WACN.
+    pub system: u16,
+    /// RF Subsystem ID of the site within the System.
+    pub rfss: u8,
+    /// Site ID of the site within the RFSS.
+    pub site: u8,
+    /// Control channel of the site.
+    pub channel: Channel,
+    /// Inbound channel of the site's control channel, if broadcast explicitly.
+    pub rx_channel: Option<Channel>,
+    /// Services supported by the site.
+    pub services: SystemServices,
+    /// Status of the site, if broadcast.
+    pub opts: Option<SiteOptions>,
+}
+
+/// Services available on the system, as advertised by a system service broadcast.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ServiceInfo {
+    /// Bitmap of services currently available.
+    pub available: u32,
+    /// Bitmap of services supported, whether or not currently available.
+    pub supported: u32,
+    /// Minimum priority level a service request must have to be considered.
+    pub request_prio: u8,
+}
+
+/// Snapshot of everything known about the current site.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SiteInfo {
+    /// WACN ID of the site.
+    pub wacn: Option<u32>,
+    /// System ID of the site within the WACN.
+    pub system: Option<u16>,
+    /// RF Subsystem ID of the site within the System.
+    pub rfss: Option<u8>,
+    /// Site ID of the site within the RFSS.
+    pub site: Option<u8>,
+    /// Location registration area of the site.
+    pub area: Option<u8>,
+    /// Whether the site is networked with the RFSS controller.
+    pub networked: Option<bool>,
+    /// Control channel of the site.
+    pub channel: Option<Channel>,
+    /// Inbound channel of the control channel, if broadcast explicitly.
+    pub rx_channel: Option<Channel>,
+    /// Services supported by the site.
+    pub services: Option<SystemServices>,
+    /// Services available on the system.
+    pub service_info: Option<ServiceInfo>,
+    /// Tuning parameters of each channel identifier.
+    pub channels: ChannelParamsMap,
+    /// Adjacent sites, in the order they were first seen.
+    pub adjacent: Vec<AdjacentSiteInfo>,
+    /// Alternate control channels of the site and their supported services, in the order
+    /// they were first seen.
+    pub alts: Vec<(Channel, SystemServices)>,
+}
+
+/// Part of the site state that was changed by a packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SiteChange {
+    /// Parameters of the given channel identifier were added or changed.
+    ChannelParams(u8),
+    /// WACN, System, RFSS, Site ID, area, or control channel changed.
+    Identity,
+    /// Services supported by the site changed.
+    Services,
+    /// Services available on the system changed.
+    ServiceInfo,
+    /// The adjacent site at the given index in the list was added or changed.
+    AdjacentSite(usize),
+    /// An alternate control channel was added or changed.
+    AltControlChannels,
+}
+
+/// Parts of the current site's status carried by a status broadcast.
+#[derive(Default)]
+struct Status {
+    wacn: Option<u32>,
+    system: Option<u16>,
+    rfss: Option<u8>,
+    site: Option<u8>,
+    area: Option<u8>,
+    networked: Option<bool>,
+    channel: Option<Channel>,
+    rx_channel: Option<Channel>,
+    services: Option<SystemServices>,
+}
+
+/// Tracks the state of a site from its TSBK, MBT, and link control broadcasts.
+///
+/// Explicit broadcasts, which carry separate outbound and inbound channels, update the
+/// same state as their implicit forms, with the outbound channel, CHANNEL(T), taken as
+/// the control channel.
+pub struct SiteState {
+    /// Current state.
+    info: SiteInfo,
+}
+
+impl SiteState {
+    /// Create a new `SiteState` with nothing known about the site.
+    pub fn new() -> SiteState {
+        SiteState {
+            info: SiteInfo::default(),
+        }
+    }
+
+    /// Snapshot of the current state.
+    pub fn snapshot(&self) -> SiteInfo { self.info.clone() }
+
+    /// Tuning parameters of each channel identifier.
+    pub fn channels(&self) -> &ChannelParamsMap { &self.info.channels }
+
+    /// Update the state with the given TSBK, returning every part of the state that
+    /// changed.
+    pub fn feed_tsbk(&mut self, tsbk: &TsbkFields) -> Vec<SiteChange> {
+        use trunking::tsbk::TsbkOpcode::*;
+
+        if !fields::is_standard_mfg(tsbk.mfg()) || !tsbk.crc_valid() {
+            return vec![];
+        }
+
+        let payload = tsbk.payload();
+
+        match tsbk.opcode() {
+            Some(ChannelParamsUpdate) =>
+                self.channel_params(payload, ChannelParamsFormat::Standard),
+            Some(ChannelParamsUpdateVu) =>
+                self.channel_params(payload, ChannelParamsFormat::VhfUhf),
+            Some(ChannelParamsUpdateTdma) =>
+                self.channel_params(payload, ChannelParamsFormat::Tdma),
+            Some(NetworkStatusBroadcast) => self.status(network_status(payload)),
+            Some(RfssStatusBroadcast) => self.status(rfss_status(payload)),
+            Some(AdjacentSite) => self.adjacent(adjacent_site(payload)),
+            Some(AltControlChannel) => {
+                let a = fields::AltControlChannel::new(payload);
+                self.alts(a.rfss(), a.site(), &a.alts()[..])
+            },
+            Some(SystemServiceBroadcast) => self.service_info(service_info(payload)),
+            _ => vec![],
+        }
+    }
+
+    /// Update the state with the given link control word, returning every part of the
+    /// state that changed.
+    pub fn feed_lc(&mut self, lc: &LinkControlFields) -> Vec<SiteChange> {
+        use voice::control::LinkControlOpcode::*;
+
+        if !fields::is_standard_mfg(lc.mfg()) {
+            return vec![];
+        }
+
+        let payload = lc.payload();
+
+        match lc.opcode() {
+            Some(ChannelParamsUpdate) =>
+                self.channel_params(payload, ChannelParamsFormat::Standard),
+            Some(ChannelParamsExplicit) =>
+                self.channel_params(payload, ChannelParamsFormat::VhfUhf),
+            Some(NetworkStatusBroadcast) => self.status(network_status(payload)),
+            Some(RfssStatusBroadcast) => self.status(rfss_status(payload)),
+            Some(AdjacentSite) => self.adjacent(adjacent_site(payload)),
+            Some(AltControlChannel) => {
+                let a = fields::AltControlChannel::new(payload);
+                self.alts(a.rfss(), a.site(), &a.alts()[..])
+            },
+            Some(SystemServiceBroadcast) => self.service_info(service_info(payload)),
+            Some(NetworkStatusExplicit) => {
+                let n = control::NetworkStatusExplicit::new(*lc);
+
+                self.status(Status {
+                    wacn: Some(n.wacn()),
+                    system: Some(n.system()),
+                    channel: Some(n.tx_channel()),
+                    rx_channel: Some(n.rx_channel()),
+                    ..Status::default()
+                })
+            },
+            Some(RfssStatusExplicit) => {
+                let r = control::RfssStatusExplicit::new(*lc);
+
+                self.status(Status {
+                    rfss: Some(r.rfss()),
+                    site: Some(r.site()),
+                    area: Some(r.area()),
+                    channel: Some(r.tx_channel()),
+                    rx_channel: Some(r.rx_channel()),
+                    services: Some(r.services()),
+                    ..Status::default()
+                })
+            },
+            Some(AdjacentSiteExplicit) => {
+                let a = control::AdjacentSiteExplicit::new(*lc);
+
+                // The explicit form leaves out the System ID, so the adjacent site is
+                // taken to be in the current system.
+                let system = match self.info.system {
+                    Some(s) => s,
+                    None => return vec![],
+                };
+
+                self.adjacent(AdjacentSiteInfo {
+                    area: a.area(),
+                    system: system,
+                    rfss: a.rfss(),
+                    site: a.site(),
+                    channel: a.tx_channel(),
+                    rx_channel: Some(a.rx_channel()),
+                    services: a.services(),
+                    opts: None,
+                })
+            },
+            Some(AltControlChannelExplicit) => {
+                let a = control::AltControlChannelExplicit::new(*lc);
+                self.alts(a.rfss(), a.site(), &[(a.tx_channel(), a.services())])
+            },
+            _ => vec![],
+        }
+    }
+
+    /// Update the state with the given MBT packet, returning every part of the state that
+    /// changed.
+    pub fn feed_mbt(&mut self, mbt: &MbtFields) -> Vec<SiteChange> {
+        match mbt.message() {
+            Ok(MbtMessage::NetworkStatus(n)) => self.status(Status {
+                wacn: Some(n.wacn()),
+                system: Some(n.system()),
+                area: Some(n.area()),
+                channel: Some(n.tx_channel()),
+                rx_channel: Some(n.rx_channel()),
+                services: Some(n.services()),
+                ..Status::default()
+            }),
+            Ok(MbtMessage::RfssStatus(r)) => self.status(Status {
+                system: Some(r.system()),
+                rfss: Some(r.rfss()),
+                site: Some(r.site()),
+                area: Some(r.area()),
+                networked: Some(r.networked()),
+                channel: Some(r.tx_channel()),
+                rx_channel: Some(r.rx_channel()),
+                services: Some(r.services()),
+                ..Status::default()
+            }),
+            Ok(MbtMessage::AdjacentSite(a)) => self.adjacent(AdjacentSiteInfo {
+                area: a.area(),
+                system: a.system(),
+                rfss: a.rfss(),
+                site: a.site(),
+                channel: a.tx_channel(),
+                rx_channel: Some(a.rx_channel()),
+                services: a.services(),
+                opts: Some(a.opts()),
+            }),
+            _ => vec![],
+        }
+    }
+
+    /// Update the parameters of a channel identifier.
+    fn channel_params(&mut self, payload: &[u8], format: ChannelParamsFormat)
+        -> Vec<SiteChange>
+    {
+        let upd = match format {
+            ChannelParamsFormat::Standard => ChannelParamsUpdate::new(payload),
+            ChannelParamsFormat::VhfUhf => ChannelParamsUpdate::vhf_uhf(payload),
+            ChannelParamsFormat::Tdma => ChannelParamsUpdate::tdma(payload),
+        };
+
+        let channels = &mut self.info.channels;
+        let prev = channels.lookup(upd.id());
+
+        channels.update(&upd);
+
+        if prev == channels.lookup(upd.id()) {
+            vec![]
+        } else {
+            vec![SiteChange::ChannelParams(upd.id())]
+        }
+    }
+
+    /// Update the current site's status with the parts carried by a broadcast.
+    fn status(&mut self, s: Status) -> Vec<SiteChange> {
+        let info = &mut self.info;
+
+        let ident = update(&mut info.wacn, s.wacn) |
+            update(&mut info.system, s.system) |
+            update(&mut info.rfss, s.rfss) |
+            update(&mut info.site, s.site) |
+            update(&mut info.area, s.area) |
+            update(&mut info.networked, s.networked) |
+            update(&mut info.channel, s.channel) |
+            update(&mut info.rx_channel, s.rx_channel);
+
+        let mut changes = vec![];
+
+        if ident {
+            changes.push(SiteChange::Identity);
+        }
+
+        if update(&mut info.services, s.services) {
+            changes.push(SiteChange::Services);
+        }
+
+        changes
+    }
+
+    /// Add or update an adjacent site.
+    fn adjacent(&mut self, site: AdjacentSiteInfo) -> Vec<SiteChange> {
+        let adjacent = &mut self.info.adjacent;
+
+        let pos = adjacent.iter().position(|s| {
+            s.system == site.system && s.rfss == site.rfss && s.site == site.site
+        });
+
+        match pos {
+            Some(idx) if adjacent[idx] == site => vec![],
+            Some(idx) => {
+                adjacent[idx] = site;
+                vec![SiteChange::AdjacentSite(idx)]
+            },
+            None => {
+                adjacent.push(site);
+                vec![SiteChange::AdjacentSite(adjacent.len() - 1)]
+            },
+        }
+    }
+
+    /// Add or update the given alternate control channels of the given site.
+    fn alts(&mut self, rfss: u8, site: u8, alts: &[(Channel, SystemServices)])
+        -> Vec<SiteChange>
+    {
+        let info = &mut self.info;
+
+        // Alternates are only meaningful for the site they're broadcast on.
+        if info.rfss.map_or(false, |r| r != rfss) || info.site.map_or(false, |s| s != site)
+        {
+            return vec![];
+        }
+
+        let mut change = false;
+
+        for &(ch, services) in alts.iter() {
+            // Unused entries are filled with the current control channel.
+            if Some(ch) == info.channel {
+                continue;
+            }
+
+            match info.alts.iter().position(|&(c, _)| c == ch) {
+                Some(idx) if info.alts[idx].1 == services => {},
+                Some(idx) => {
+                    info.alts[idx].1 = services;
+                    change = true;
+                },
+                None => {
+                    info.alts.push((ch, services));
+                    change = true;
+                },
+            }
+        }
+
+        if change {
+            vec![SiteChange::AltControlChannels]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Update the services available on the system.
+    fn service_info(&mut self, s: ServiceInfo) -> Vec<SiteChange> {
+        if update(&mut self.info.service_info, Some(s)) {
+            vec![SiteChange::ServiceInfo]
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Decode the status carried by a network status broadcast payload.
+fn network_status(payload: &[u8]) -> Status {
+    let n = fields::NetworkStatusBroadcast::new(payload);
+
+    Status {
+        wacn: Some(n.wacn()),
+        system: Some(n.system()),
+        area: Some(n.area()),
+        channel: Some(n.channel()),
+        services: Some(n.services()),
+        ..Status::default()
+    }
+}
+
+/// Decode the status carried by an RFSS status broadcast payload.
+fn rfss_status(payload: &[u8]) -> Status {
+    let r = fields::RfssStatusBroadcast::new(payload);
+
+    Status {
+        system: Some(r.system()),
+        rfss: Some(r.rfss()),
+        site: Some(r.site()),
+        area: Some(r.area()),
+        networked: Some(r.networked()),
+        channel: Some(r.channel()),
+        services: Some(r.services()),
+        ..Status::default()
+    }
+}
+
+/// Decode the adjacent site carried by an adjacent site broadcast payload.
+fn adjacent_site(payload: &[u8]) -> AdjacentSiteInfo {
+    let a = fields::AdjacentSite::new(payload);
+
+    AdjacentSiteInfo {
+        area: a.area(),
+        system: a.system(),
+        rfss: a.rfss(),
+        site: a.site(),
+        channel: a.channel(),
+        rx_channel: None,
+        services: a.services(),
+        opts: Some(a.opts()),
+    }
+}
+
+/// Decode the services carried by a system service broadcast payload.
+fn service_info(payload: &[u8]) -> ServiceInfo {
+    let s = fields::SystemServiceBroadcast::new(payload);
+
+    ServiceInfo {
+        available: s.available(),
+        supported: s.supported(),
+        request_prio: s.request_prio(),
+    }
+}
+
+/// Set the given field to the given value, if any, returning whether it changed.
+fn update<T: PartialEq>(field: &mut Option<T>, val: Option<T>) -> bool {
+    if val.is_none() || *field == val {
+        false
+    } else {
+        *field = val;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::header::DataHeaderFields;
+    use trunking::tsbk::{TsbkBuilder, TsbkOpcode};
+
+    #[test]
+    fn test_identity() {
+        let mut s = SiteState::new();
+
+        let net = TsbkBuilder::standard(TsbkOpcode::NetworkStatusBroadcast, [
+            0x07, 0xBE, 0xE0, 0x01, 0x23, 0x10, 0x05, 0x70,
+        ]).build();
+
+        // Services are first seen along with the identity.
+        assert_eq!(s.feed_tsbk(&net), vec![SiteChange::Identity, SiteChange::Services]);
+        assert_eq!(s.feed_tsbk(&net), vec![]);
+
+        let rfss = TsbkBuilder::standard(TsbkOpcode::RfssStatusBroadcast, [
+            0x07, 0x11, 0x23, 0x02, 0x03, 0x10, 0x05, 0x70,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&rfss), vec![SiteChange::Identity]);
+        assert_eq!(s.feed_tsbk(&rfss), vec![]);
+
+        let rfss = TsbkBuilder::standard(TsbkOpcode::RfssStatusBroadcast, [
+            0x07, 0x11, 0x23, 0x02, 0x03, 0x10, 0x05, 0x50,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&rfss), vec![SiteChange::Services]);
+
+        let info = s.snapshot();
+        assert_eq!(info.wacn, Some(0xBEE00));
+        assert_eq!(info.system, Some(0x123));
+        assert_eq!(info.rfss, Some(2));
+        assert_eq!(info.site, Some(3));
+        assert_eq!(info.area, Some(7));
+        assert_eq!(info.networked, Some(true));
+        assert_eq!(info.channel.unwrap().number(), 5);
+        assert!(!info.services.unwrap().has_voice());
+
+        // Packets with a bad CRC are ignored.
+        let bad = TsbkFields::new([
+            0x3B, 0x00, 0x07, 0xBE, 0xE0, 0x02, 0x23, 0x10, 0x05, 0x70, 0x00, 0x00,
+        ]);
+        assert_eq!(s.feed_tsbk(&bad), vec![]);
+        assert_eq!(s.snapshot().wacn, Some(0xBEE00));
+    }
+
+    #[test]
+    fn test_channel_params() {
+        let mut s = SiteState::new();
+
+        let t = TsbkBuilder::standard(TsbkOpcode::ChannelParamsUpdate, [
+            0b01100011, 0b00100010, 0b11010000, 0b00110010,
+            0b00001010, 0b00100101, 0b00010000, 0b10100010,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&t), vec![SiteChange::ChannelParams(6)]);
+        assert_eq!(s.feed_tsbk(&t), vec![]);
+        assert_eq!(s.channels().lookup(6).unwrap().rx_freq(0b1001), 851_062_500);
+
+        let lc = LinkControlFields::new([
+            0b01011000, 0b01110011, 0b00100010, 0b11010000, 0b00110010,
+            0b00001010, 0b00100101, 0b00010000, 0b10100010,
+        ]);
+
+        assert_eq!(s.feed_lc(&lc), vec![SiteChange::ChannelParams(7)]);
+        assert!(s.snapshot().channels.lookup(7).is_some());
+
+        let t = TsbkBuilder::standard(TsbkOpcode::ChannelParamsUpdateVu, [
+            0x25, 0x83, 0xC0, 0x14, 0x01, 0xCC, 0xD0, 0xC0,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&t), vec![SiteChange::ChannelParams(2)]);
+        assert_eq!(s.channels().tx_freq(Channel::new(&[0x20, 0x04])), Some(151_610_000));
+
+        let t = TsbkBuilder::standard(TsbkOpcode::ChannelParamsUpdateTdma, [
+            0x33, 0x38, 0x40, 0x64, 0x0A, 0x25, 0x10, 0xA2,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&t), vec![SiteChange::ChannelParams(3)]);
+        assert_eq!(s.channels().slot(Channel::new(&[0x30, 0x07])), Some(1));
+
+        let lc = LinkControlFields::new([
+            0x59, 0x45, 0x83, 0xC0, 0x14, 0x01, 0xCC, 0xD0, 0xC0,
+        ]);
+
+        assert_eq!(s.feed_lc(&lc), vec![SiteChange::ChannelParams(4)]);
+        assert_eq!(s.channels().lookup(4).unwrap().bandwidth, 12_500);
+    }
+
+    #[test]
+    fn test_adjacent() {
+        let mut s = SiteState::new();
+
+        let a = TsbkBuilder::standard(TsbkOpcode::AdjacentSite, [
+            0x07, 0x21, 0x23, 0x02, 0x04, 0x10, 0x08, 0x70,
+        ]).build();
+        let b = TsbkBuilder::standard(TsbkOpcode::AdjacentSite, [
+            0x07, 0x01, 0x23, 0x02, 0x05, 0x10, 0x09, 0x70,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&a), vec![SiteChange::AdjacentSite(0)]);
+        assert_eq!(s.feed_tsbk(&b), vec![SiteChange::AdjacentSite(1)]);
+        assert_eq!(s.feed_tsbk(&a), vec![]);
+
+        let a = TsbkBuilder::standard(TsbkOpcode::AdjacentSite, [
+            0x07, 0x41, 0x23, 0x02, 0x04, 0x10, 0x08, 0x70,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&a), vec![SiteChange::AdjacentSite(0)]);
+
+        let info = s.snapshot();
+        assert_eq!(info.adjacent.len(), 2);
+        assert!(info.adjacent[0].opts.unwrap().failing());
+        assert_eq!(info.adjacent[0].site, 4);
+        assert_eq!(info.adjacent[1].site, 5);
+        assert!(info.adjacent[1].services.has_voice());
+    }
+
+    #[test]
+    fn test_alts() {
+        let mut s = SiteState::new();
+
+        let rfss = TsbkBuilder::standard(TsbkOpcode::RfssStatusBroadcast, [
+            0x07, 0x11, 0x23, 0x02, 0x03, 0x10, 0x05, 0x70,
+        ]).build();
+        s.feed_tsbk(&rfss);
+
+        let alt = TsbkBuilder::standard(TsbkOpcode::AltControlChannel, [
+            0x02, 0x03, 0x10, 0x06, 0x70, 0x10, 0x05, 0x70,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&alt), vec![SiteChange::AltControlChannels]);
+        assert_eq!(s.feed_tsbk(&alt), vec![]);
+
+        // Alternates of another site are ignored.
+        let other = TsbkBuilder::standard(TsbkOpcode::AltControlChannel, [
+            0x02, 0x04, 0x10, 0x07, 0x70, 0x10, 0x08, 0x70,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&other), vec![]);
+
+        let info = s.snapshot();
+        assert_eq!(info.alts.len(), 1);
+        assert_eq!(info.alts[0].0.number(), 6);
+
+        // Vendor packets are ignored.
+        let lc = LinkControlFields::new([0x21, 0x90, 0x03, 0x10, 0x09, 0x70, 0, 0, 0]);
+        assert_eq!(s.feed_lc(&lc), vec![]);
+    }
+
+    #[test]
+    fn test_explicit() {
+        let mut s = SiteState::new();
+
+        let adj = LinkControlFields::new([
+            0x67, 0x07, 0x10, 0x08, 0x02, 0x04, 0x10, 0x09, 0x70,
+        ]);
+
+        // Adjacent sites can't be placed until the System ID is known.
+        assert_eq!(s.feed_lc(&adj), vec![]);
+
+        let net = LinkControlFields::new([
+            0x69, 0xBE, 0xE0, 0x01, 0x23, 0x10, 0x05, 0x10, 0x06,
+        ]);
+
+        assert_eq!(s.feed_lc(&net), vec![SiteChange::Identity]);
+        assert_eq!(s.feed_lc(&net), vec![]);
+
+        let rfss = LinkControlFields::new([
+            0x68, 0x07, 0x02, 0x03, 0x10, 0x05, 0x10, 0x06, 0x70,
+        ]);
+
+        assert_eq!(s.feed_lc(&rfss), vec![SiteChange::Identity, SiteChange::Services]);
+        assert_eq!(s.feed_lc(&rfss), vec![]);
+
+        let info = s.snapshot();
+        assert_eq!(info.wacn, Some(0xBEE00));
+        assert_eq!(info.system, Some(0x123));
+        assert_eq!(info.rfss, Some(2));
+        assert_eq!(info.site, Some(3));
+        assert_eq!(info.area, Some(7));
+        assert_eq!(info.channel.unwrap().number(), 5);
+        assert_eq!(info.rx_channel.unwrap().number(), 6);
+        assert!(info.services.unwrap().has_voice());
+
+        assert_eq!(s.feed_lc(&adj), vec![SiteChange::AdjacentSite(0)]);
+        assert_eq!(s.feed_lc(&adj), vec![]);
+
+        let info = s.snapshot();
+        assert_eq!(info.adjacent[0].system, 0x123);
+        assert_eq!(info.adjacent[0].site, 4);
+        assert_eq!(info.adjacent[0].channel.number(), 8);
+        assert_eq!(info.adjacent[0].rx_channel.unwrap().number(), 9);
+        assert_eq!(info.adjacent[0].opts, None);
+
+        // The implicit form of the same site replaces the explicit one.
+        let a = TsbkBuilder::standard(TsbkOpcode::AdjacentSite, [
+            0x07, 0x01, 0x23, 0x02, 0x04, 0x10, 0x08, 0x70,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&a), vec![SiteChange::AdjacentSite(0)]);
+
+        let info = s.snapshot();
+        assert_eq!(info.adjacent.len(), 1);
+        assert_eq!(info.adjacent[0].rx_channel, None);
+        assert!(info.adjacent[0].opts.is_some());
+
+        let alt = LinkControlFields::new([
+            0x66, 0x02, 0x03, 0x10, 0x07, 0x10, 0x08, 0x70, 0x00,
+        ]);
+
+        assert_eq!(s.feed_lc(&alt), vec![SiteChange::AltControlChannels]);
+        assert_eq!(s.feed_lc(&alt), vec![]);
+
+        // Alternates of another site are ignored.
+        let other = LinkControlFields::new([
+            0x66, 0x02, 0x04, 0x10, 0x0A, 0x10, 0x0B, 0x70, 0x00,
+        ]);
+
+        assert_eq!(s.feed_lc(&other), vec![]);
+
+        let info = s.snapshot();
+        assert_eq!(info.alts.len(), 1);
+        assert_eq!(info.alts[0].0.number(), 7);
+    }
+
+    #[test]
+    fn test_service_info() {
+        let mut s = SiteState::new();
+
+        let t = TsbkBuilder::standard(TsbkOpcode::SystemServiceBroadcast, [
+            0x00, 0x12, 0x34, 0x56, 0xFF, 0xFF, 0xFF, 0x03,
+        ]).build();
+
+        assert_eq!(s.feed_tsbk(&t), vec![SiteChange::ServiceInfo]);
+        assert_eq!(s.feed_tsbk(&t), vec![]);
+        assert_eq!(s.snapshot().service_info, Some(ServiceInfo {
+            available: 0x123456,
+            supported: 0xFFFFFF,
+            request_prio: 3,
+        }));
+
+        let lc = LinkControlFields::new([
+            0x60, 0x00, 0x12, 0x34, 0x00, 0xFF, 0xFF, 0xFF, 0x05,
+        ]);
+
+        assert_eq!(s.feed_lc(&lc), vec![SiteChange::ServiceInfo]);
+        assert_eq!(s.snapshot().service_info, Some(ServiceInfo {
+            available: 0x123400,
+            supported: 0xFFFFFF,
+            request_prio: 5,
+        }));
+    }
+
+    #[test]
+    fn test_mbt() {
+        fn mbt(opcode: u8, head: [u8; 3], args: [u8; 2], data: &[u8]) -> MbtFields {
+            MbtFields::new(DataHeaderFields::new([
+                0b00110111, 0b00111101, 0x00, head[0], head[1], head[2],
+                0x81, opcode, args[0], args[1], 0x00, 0x00,
+            ]), data.to_vec()).unwrap()
+        }
+
+        let mut s = SiteState::new();
+
+        let net = mbt(0x3B, [0x07, 0x01, 0x23], [0x00, 0x00], &[
+            0xBE, 0xE0, 0x00, 0x10, 0x05, 0x10, 0x06, 0x70,
+        ]);
+
+        assert_eq!(s.feed_mbt(&net), vec![SiteChange::Identity, SiteChange::Services]);
+        assert_eq!(s.feed_mbt(&net), vec![]);
+
+        let rfss = mbt(0x3A, [0x07, 0x11, 0x23], [0x02, 0x03], &[
+            0x10, 0x05, 0x10, 0x06, 0x70, 0x00, 0x00, 0x00,
+        ]);
+
+        assert_eq!(s.feed_mbt(&rfss), vec![SiteChange::Identity]);
+
+        let info = s.snapshot();
+        assert_eq!(info.wacn, Some(0xBEE00));
+        assert_eq!(info.system, Some(0x123));
+        assert_eq!(info.rfss, Some(2));
+        assert_eq!(info.site, Some(3));
+        assert_eq!(info.networked, Some(true));
+        assert_eq!(info.channel.unwrap().number(), 5);
+        assert_eq!(info.rx_channel.unwrap().number(), 6);
+
+        let adj = mbt(0x3C, [0x07, 0x41, 0x23], [0x02, 0x04], &[
+            0x10, 0x08, 0x10, 0x09, 0x70, 0x00, 0x00, 0x00,
+        ]);
+
+        assert_eq!(s.feed_mbt(&adj), vec![SiteChange::AdjacentSite(0)]);
+
+        let info = s.snapshot();
+        assert_eq!(info.adjacent[0].site, 4);
+        assert_eq!(info.adjacent[0].rx_channel.unwrap().number(), 9);
+        assert!(info.adjacent[0].opts.unwrap().failing());
+
+        // Packets too short for their message are ignored.
+        let short = mbt(0x3C, [0x07, 0x41, 0x23], [0x02, 0x05], &[0x10, 0x08]);
+        assert_eq!(s.feed_mbt(&short), vec![]);
+    }
+}
@@ -1,17 +1,21 @@
-//! Receive Trunking Signalling Block (TSBK) packets and decode the various TSBK payloads.
+//! Receive Trunking Signalling Block (TSBK) packets and decode the various TSBK payloads,
+//! and build TSBK packets for transmission.
 
 use collect_slice::CollectSlice;
 
-use bits::{Dibit, DibitBytes};
+use bits::{Dibit, DibitBytes, SoftDibit};
 use buffer::{Buffer, DataPayloadStorage};
 use coding::trellis;
-use consts::{TSBK_DIBITS, TSBK_BYTES};
-use data::{crc, interleave};
+use consts::{CODING_DIBITS, TSBK_DIBITS, TSBK_BYTES};
+use data::{coder, crc, interleave};
 use error::{Result, P25Error};
+use message::nid::{DataUnit, NetworkAccessCode};
+use message::status::StatusCode;
+use message::transmit;
 use stats::{Stats, HasStats};
 use util::{slice_u16, slice_u24};
 
-use trunking::fields::{Channel, TalkGroup, ServiceOptions, RegResponse};
+use trunking::fields::{self, Channel, TalkGroup, ServiceOptions, RegResponse, DenyReason};
 
 /// State machine for receiving a TSBK packet.
 ///
@@ -21,45 +25,69 @@ use trunking::fields::{Channel, TalkGroup, ServiceOptions, RegResponse};
 /// 2. Descramble symbols using the same deinterleaver as data packets
 /// 3. Decode 1/2-rate convolutional code and attempt to correct any errors
 /// 4. Group dibits into a buffer of bytes for further interpretation
+///
+/// When soft decisions are enabled, the confidence of each symbol decision is used to
+/// weigh the errors considered by the convolutional decoder.
 pub struct TsbkReceiver {
     /// Current buffered dibits.
     dibits: Buffer<DataPayloadStorage>,
+    /// Whether to decode with soft decisions.
+    soft: bool,
     stats: Stats,
 }
 
 impl TsbkReceiver {
-    /// Create a new `TsbkReceiver` in the initial state.
+    /// Create a new `TsbkReceiver` in the initial state, decoding with hard decisions.
     pub fn new() -> TsbkReceiver {
         TsbkReceiver {
             dibits: Buffer::new(DataPayloadStorage::new()),
+            soft: false,
             stats: Stats::default(),
         }
     }
 
+    /// Create a new `TsbkReceiver` in the initial state, decoding with soft decisions.
+    pub fn soft() -> TsbkReceiver {
+        TsbkReceiver {
+            soft: true,
+            ..TsbkReceiver::new()
+        }
+    }
+
     /// Feed in a baseband symbol, possibly producing a complete TSBK packet. Return
     /// `Some(Ok(pkt))` if a packet was successfully received, `Some(Err(err))` if an
     /// error occurred, and `None` in the case of no event.
-    pub fn feed(&mut self, dibit: Dibit) -> Option<Result<TsbkFields>> {
-        let (count, dibits) = {
+    pub fn feed<D: Into<SoftDibit>>(&mut self, dibit: D) -> Option<Result<TsbkFields>> {
+        let dibit = dibit.into();
+
+        // Without soft decisions, every bit is weighed equally.
+        let dibit = if self.soft {
+            dibit
+        } else {
+            SoftDibit::from(dibit.dibit())
+        };
+
+        let (count, errs, dibits) = {
             let buf = match self.dibits.feed(dibit) {
                 Some(buf) => buf,
                 None => return None,
             };
 
             let mut dibits = [Dibit::default(); TSBK_DIBITS];
-            let count = trellis::DibitDecoder::new(interleave::Deinterleaver::new(buf))
+            let mut dec = trellis::DibitDecoder::new(interleave::Deinterleaver::new(buf));
+
+            let count = (&mut dec)
                 .filter_map(|x| x.ok())
                 .collect_slice_exhaust(&mut dibits[..]);
 
-            (count, dibits)
+            (count, dec.errors(), dibits)
         };
 
         if count != dibits.len() {
             return Some(Err(P25Error::DibitViterbiUnrecoverable));
         }
 
-        // TODO: determine number of corrected bits.
-        self.stats.viterbi_dibit.record_fixes(0);
+        self.stats.viterbi_dibit.record_fixes(errs);
 
         let mut bytes = [0; TSBK_BYTES];
         DibitBytes::new(dibits.iter().cloned()).collect_slice_checked(&mut bytes[..]);
@@ -72,7 +100,7 @@ impl HasStats for TsbkReceiver {
     fn stats(&mut self) -> &mut Stats { &mut self.stats }
 }
 
-/// Type of a TSBK payload.
+/// Type of a TSBK payload sent by the system (outbound.)
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum TsbkOpcode {
     GroupVoiceGrant,
@@ -111,6 +139,8 @@ pub enum TsbkOpcode {
     NetworkStatusBroadcast,
     AdjacentSite,
     ChannelParamsUpdate,
+    ChannelParamsUpdateTdma,
+    ChannelParamsUpdateVu,
     ProtectionParamBroadcast,
     ProtectionParamUpdate,
     Reserved,
@@ -136,13 +166,13 @@ impl TsbkOpcode {
             0b001000 => Some(PhoneGrant),
             0b001001 => Some(Reserved),
             0b001010 => Some(PhoneAlert),
-            0b001011...0b001111 => Some(Reserved),
+            0b001011..=0b001111 => Some(Reserved),
 
             0b010000 => Some(UnitDataGrant),
             0b010001 => Some(GroupDataGrant),
             0b010010 => Some(GroupDataUpdate),
             0b010011 => Some(GroupDataUpdateExplicit),
-            0b010100...0b010111 => Some(Reserved),
+            0b010100..=0b010111 => Some(Reserved),
 
             0b011000 => Some(UnitStatusUpdate),
             0b011001 => Some(Reserved),
@@ -170,7 +200,10 @@ impl TsbkOpcode {
             0b101110 => Some(UnitAuthCommand),
             0b101111 => Some(UnitDeregAck),
 
-            0b110000...0b110101 => Some(Reserved),
+            0b110000..=0b110010 => Some(Reserved),
+            0b110011 => Some(ChannelParamsUpdateTdma),
+            0b110100 => Some(ChannelParamsUpdateVu),
+            0b110101 => Some(Reserved),
             0b110110 => Some(RoamingAddrCommand),
             0b110111 => Some(RoamingAddrUpdate),
 
@@ -188,6 +221,114 @@ impl TsbkOpcode {
     }
 }
 
+/// Type of a TSBK payload sent by a subscriber unit (inbound.)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InboundTsbkOpcode {
+    GroupVoiceRequest,
+    UnitVoiceRequest,
+    UnitAnswerResponse,
+    PhoneRequestExplicit,
+    PhoneRequestImplicit,
+    PhoneAnswerResponse,
+    UnitDataRequest,
+    GroupDataRequest,
+    DataChannelRequest,
+    DataPageResponse,
+    DataReconnectRequest,
+    UnitStatusUpdate,
+    UnitStatusResponse,
+    UnitStatusQuery,
+    UnitShortMessage,
+    UnitMonitorRequest,
+    UnitCallAlert,
+    UnitAckResponse,
+    CancelServiceRequest,
+    ExtendedFunctionResponse,
+    EmergencyAlarm,
+    GroupAffiliationRequest,
+    GroupAffiliationQueryResponse,
+    UnitDeregRequest,
+    UnitRegRequest,
+    LocRegRequest,
+    ProtectionParamRequest,
+    ChannelParamsRequest,
+    RoamingAddrRequest,
+    RoamingAddrResponse,
+    AuthResponse,
+    AuthResponseMutual,
+    AuthResult,
+    AuthDemand,
+    Reserved,
+}
+
+impl InboundTsbkOpcode {
+    /// Try to parse an opcode from the given 6 bits.
+    pub fn from_bits(bits: u8) -> Option<InboundTsbkOpcode> {
+        use self::InboundTsbkOpcode::*;
+
+        assert!(bits >> 6 == 0);
+
+        match bits {
+            0b000000 => Some(GroupVoiceRequest),
+            0b000001..=0b000011 => Some(Reserved),
+            0b000100 => Some(UnitVoiceRequest),
+            0b000101 => Some(UnitAnswerResponse),
+            0b000110..=0b000111 => Some(Reserved),
+
+            0b001000 => Some(PhoneRequestExplicit),
+            0b001001 => Some(PhoneRequestImplicit),
+            0b001010 => Some(PhoneAnswerResponse),
+            0b001011..=0b001111 => Some(Reserved),
+
+            0b010000 => Some(UnitDataRequest),
+            0b010001 => Some(GroupDataRequest),
+            0b010010 => Some(DataChannelRequest),
+            0b010011 => Some(DataPageResponse),
+            0b010100 => Some(DataReconnectRequest),
+            0b010101..=0b010111 => Some(Reserved),
+
+            0b011000 => Some(UnitStatusUpdate),
+            0b011001 => Some(UnitStatusResponse),
+            0b011010 => Some(UnitStatusQuery),
+            0b011011 => Some(Reserved),
+            0b011100 => Some(UnitShortMessage),
+            0b011101 => Some(UnitMonitorRequest),
+            0b011110 => Some(Reserved),
+            0b011111 => Some(UnitCallAlert),
+
+            0b100000 => Some(UnitAckResponse),
+            0b100001..=0b100010 => Some(Reserved),
+            0b100011 => Some(CancelServiceRequest),
+            0b100100 => Some(ExtendedFunctionResponse),
+            0b100101..=0b100110 => Some(Reserved),
+            0b100111 => Some(EmergencyAlarm),
+
+            0b101000 => Some(GroupAffiliationRequest),
+            0b101001 => Some(GroupAffiliationQueryResponse),
+            0b101010 => Some(Reserved),
+            0b101011 => Some(UnitDeregRequest),
+            0b101100 => Some(UnitRegRequest),
+            0b101101 => Some(LocRegRequest),
+            0b101110..=0b110001 => Some(Reserved),
+
+            0b110010 => Some(ProtectionParamRequest),
+            0b110011 => Some(Reserved),
+            0b110100 => Some(ChannelParamsRequest),
+            0b110101 => Some(Reserved),
+            0b110110 => Some(RoamingAddrRequest),
+            0b110111 => Some(RoamingAddrResponse),
+
+            0b111000 => Some(AuthResponse),
+            0b111001 => Some(AuthResponseMutual),
+            0b111010 => Some(AuthResult),
+            0b111011 => Some(AuthDemand),
+            0b111100..=0b111111 => Some(Reserved),
+
+            _ => None,
+        }
+    }
+}
+
 /// Buffer of bytes that represents a TSBK packet.
 pub type Buf = [u8; TSBK_BYTES];
 
@@ -203,8 +344,14 @@ impl TsbkFields {
     pub fn is_tail(&self) -> bool { self.0[0] >> 7 == 1 }
     /// Whether the packet is encrypted.
     pub fn protected(&self) -> bool { self.0[0] >> 6 & 1 == 1 }
-    /// Type of data contained in the payload.
+    /// Type of data contained in the payload, interpreting the packet as sent by the
+    /// system.
     pub fn opcode(&self) -> Option<TsbkOpcode> { TsbkOpcode::from_bits(self.0[0] & 0x3F) }
+    /// Type of data contained in the payload, interpreting the packet as sent by a
+    /// subscriber unit.
+    pub fn inbound_opcode(&self) -> Option<InboundTsbkOpcode> {
+        InboundTsbkOpcode::from_bits(self.0[0] & 0x3F)
+    }
     /// Manufacturer ID, which determines if the packet is standardized.
     pub fn mfg(&self) -> u8 { self.0[1] }
     /// Transmitted CRC.
@@ -224,6 +371,172 @@ impl TsbkFields {
 
     /// Bytes that make up the payload of the packet.
     pub fn payload(&self) -> &[u8] { &self.0[2..=9] }
+
+    /// Code and interleave the packet into the block of dibits carried over the air.
+    pub fn encode(&self) -> [Dibit; CODING_DIBITS] {
+        let mut dibits = [Dibit::default(); CODING_DIBITS];
+
+        interleave::Interleaver::new(coder::DibitCoder::new()
+            .feed_bytes(self.0.iter().cloned())
+            .finish())
+            .collect_slice_checked(&mut dibits[..]);
+
+        dibits
+    }
+}
+
+/// Builds a TSBK packet with a valid CRC.
+pub struct TsbkBuilder(Buf);
+
+impl TsbkBuilder {
+    /// Create a new `TsbkBuilder` for a packet with the given 6-bit opcode, manufacturer
+    /// ID, and payload bytes. The packet is initially unprotected and not marked as the
+    /// last in its group.
+    pub fn new(opcode: u8, mfg: u8, payload: [u8; 8]) -> TsbkBuilder {
+        assert!(opcode >> 6 == 0);
+
+        let mut buf = [0; TSBK_BYTES];
+
+        buf[0] = opcode;
+        buf[1] = mfg;
+        buf[2..=9].copy_from_slice(&payload[..]);
+
+        TsbkBuilder(buf)
+    }
+
+    /// Create a new `TsbkBuilder` for a standard packet of the given type with the given
+    /// payload bytes.
+    #[cfg(test)]
+    pub fn standard(opcode: TsbkOpcode, payload: [u8; 8]) -> TsbkBuilder {
+        let bits = (0..64).find(|&b| TsbkOpcode::from_bits(b) == Some(opcode)).unwrap();
+        TsbkBuilder::new(bits, 0, payload)
+    }
+
+    /// Set whether the packet is the last one in the TSBK group.
+    pub fn tail(mut self, tail: bool) -> Self {
+        self.0[0] = self.0[0] & 0x7F | (tail as u8) << 7;
+        self
+    }
+
+    /// Set whether the packet is encrypted.
+    pub fn protected(mut self, protected: bool) -> Self {
+        self.0[0] = self.0[0] & 0xBF | (protected as u8) << 6;
+        self
+    }
+
+    /// Fill in the CRC and return the finished packet.
+    pub fn build(self) -> TsbkFields {
+        let mut buf = self.0;
+        let crc = TsbkFields(buf).calc_crc();
+
+        buf[10] = (crc >> 8) as u8;
+        buf[11] = crc as u8;
+
+        TsbkFields(buf)
+    }
+}
+
+impl fields::VendorPacket for TsbkFields {
+    type Opcode = TsbkOpcode;
+
+    fn mfg(&self) -> u8 { TsbkFields::mfg(self) }
+    fn opcode_bits(&self) -> u8 { self.0[0] & 0x3F }
+    fn standard_opcode(bits: u8) -> Option<TsbkOpcode> { TsbkOpcode::from_bits(bits) }
+}
+
+/// Interpretation of a TSBK packet based on its manufacturer ID.
+pub type TsbkKind<P> = fields::PacketKind<TsbkOpcode, P>;
+
+/// Registry of decoders for manufacturer-specific TSBK packets.
+pub type TsbkRegistry<P> = fields::VendorRegistry<TsbkFields, P>;
+
+/// Manufacturer-specific TSBK payloads supported by the built-in decoders.
+#[derive(Debug)]
+pub enum VendorTsbk {
+    /// Motorola talkgroups were added to a patch or regroup.
+    MotorolaPatchAdd(MotorolaPatch),
+    /// Motorola talkgroups were removed from a patch or regroup.
+    MotorolaPatchDelete(MotorolaPatch),
+    /// Motorola control channel base station identification.
+    MotorolaBaseStationId(MotorolaBaseStationId),
+}
+
+impl fields::VendorRegistry<TsbkFields, VendorTsbk> {
+    /// Create a new `TsbkRegistry` with all built-in manufacturer decoders registered.
+    pub fn builtin() -> Self {
+        let mut reg = Self::new();
+        reg.register(fields::MFG_MOTOROLA, decode_motorola);
+        reg
+    }
+}
+
+/// Decode a Motorola manufacturer-specific packet.
+pub fn decode_motorola(tsbk: &TsbkFields) -> Option<VendorTsbk> {
+    match tsbk.0[0] & 0x3F {
+        0x00 => Some(VendorTsbk::MotorolaPatchAdd(MotorolaPatch(tsbk.0))),
+        0x01 => Some(VendorTsbk::MotorolaPatchDelete(MotorolaPatch(tsbk.0))),
+        0x0B => Some(VendorTsbk::MotorolaBaseStationId(MotorolaBaseStationId(tsbk.0))),
+        _ => None,
+    }
+}
+
+/// Motorola patch or dynamic regroup of several talkgroups into a supergroup.
+#[derive(Debug)]
+pub struct MotorolaPatch(Buf);
+
+impl MotorolaPatch {
+    /// Supergroup that the talkgroups are patched into.
+    pub fn supergroup(&self) -> TalkGroup { TalkGroup::new(&self.0[2..=3]) }
+
+    /// Talkgroups added to or removed from the supergroup. Unused entries are usually
+    /// filled with the supergroup itself.
+    pub fn groups(&self) -> [TalkGroup; 3] {
+        [
+            TalkGroup::new(&self.0[4..=5]),
+            TalkGroup::new(&self.0[6..=7]),
+            TalkGroup::new(&self.0[8..=9]),
+        ]
+    }
+}
+
+/// Motorola control channel base station identification, carrying the station's
+/// callsign as broadcast in Morse code.
+#[derive(Debug)]
+pub struct MotorolaBaseStationId(Buf);
+
+impl MotorolaBaseStationId {
+    /// Station callsign, with trailing spaces removed.
+    pub fn callsign(&self) -> String {
+        let bits = (slice_u24(&self.0[2..=4]) as u64) << 24 |
+                   slice_u24(&self.0[5..=7]) as u64;
+
+        let s: String = (0..8).map(|i| {
+            match (bits >> (42 - 6 * i)) & 0x3F {
+                0 => ' ',
+                c => (c as u8 + 43) as char,
+            }
+        }).collect();
+
+        s.trim_end().to_string()
+    }
+
+    /// Channel of the control channel.
+    pub fn channel(&self) -> Channel { Channel::new(&self.0[8..=9]) }
+}
+
+/// Construct a trunking signalling data unit carrying the given group of 1 to 3 TSBK
+/// packets, the last of which must be marked as the tail. The returned dibits include the
+/// frame sync, NID, status symbols with the given status code, and trailing pads, and so
+/// can be transmitted as is.
+pub fn data_unit(nac: NetworkAccessCode, tsbks: &[TsbkFields], status: StatusCode)
+    -> Vec<Dibit>
+{
+    assert!(tsbks.len() >= 1 && tsbks.len() <= 3);
+    assert!(tsbks.iter().enumerate().all(|(i, t)| t.is_tail() == (i == tsbks.len() - 1)));
+
+    let payload = tsbks.iter().flat_map(|t| t.encode().to_vec());
+
+    transmit::data_unit(nac, DataUnit::TrunkingSignaling, payload, status)
 }
 
 /// Response given to a location registration request.
@@ -351,10 +664,441 @@ impl GroupDataGrant {
     pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
 }
 
+/// Updates subscribers about an ongoing talkgroup conversation, with explicit transmit
+/// and receive channels.
+///
+/// Note that this decoder can be used with `GroupVoiceUpdateExplicit` and
+/// `GroupDataUpdateExplicit` packets.
+pub struct GroupTrafficUpdateExplicit(Buf);
+
+impl GroupTrafficUpdateExplicit {
+    /// Create a new `GroupTrafficUpdateExplicit` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { GroupTrafficUpdateExplicit(tsbk.0) }
+
+    /// Options granted for the traffic channel.
+    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.0[2]) }
+    /// Channel the subscriber receives on, i.e. the outbound CHANNEL(T).
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0[4..=5]) }
+    /// Channel the subscriber transmits on, i.e. the inbound CHANNEL(R).
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0[6..=7]) }
+    /// Talkgroup of the conversation.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0[8..=9]) }
+}
+
+/// Command for a target unit to transmit so its surroundings can be monitored.
+pub struct UnitMonitor(Buf);
+
+impl UnitMonitor {
+    /// Create a new `UnitMonitor` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { UnitMonitor(tsbk.0) }
+
+    /// Whether the target unit should transmit without any indication to its user.
+    pub fn silent(&self) -> bool { self.0[3] >> 7 == 1 }
+    /// Multiplier applied to the system-defined transmit time.
+    pub fn tx_mult(&self) -> u8 { self.0[3] & 0b11 }
+    /// Unit to be monitored.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Unit requesting the monitoring.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Acknowledgement from the system of a service request.
+pub struct AckResponse(Buf);
+
+impl AckResponse {
+    /// Create a new `AckResponse` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { AckResponse(tsbk.0) }
+
+    /// Whether the additional information fields are valid.
+    pub fn has_info(&self) -> bool { self.0[2] >> 7 == 1 }
+    /// Whether the additional information holds the WACN and System ID of the target unit
+    /// rather than the requesting unit.
+    pub fn extended(&self) -> bool { self.0[2] >> 6 & 1 == 1 }
+    /// Inbound opcode of the acknowledged service request.
+    pub fn service(&self) -> u8 { self.0[2] & 0x3F }
+    /// WACN ID of target unit, valid only if `extended()` is true.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0[3..=5]) >> 4 }
+    /// System ID of target unit, valid only if `extended()` is true.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[5..=6]) & 0xFFF }
+    /// Requesting unit, valid only if `extended()` is false.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Unit being acknowledged.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Indicates a service request has been queued until resources are available.
+pub struct QueuedResponse(Buf);
+
+impl QueuedResponse {
+    /// Create a new `QueuedResponse` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { QueuedResponse(tsbk.0) }
+
+    /// Whether the additional information field is valid.
+    pub fn has_info(&self) -> bool { self.0[2] >> 7 == 1 }
+    /// Inbound opcode of the queued service request.
+    pub fn service(&self) -> u8 { self.0[2] & 0x3F }
+    /// Reason the request was queued.
+    pub fn reason(&self) -> u8 { self.0[3] }
+    /// Additional information about the request, usually the target address.
+    pub fn info(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Requesting unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Indicates a service request has been denied.
+pub struct DenyResponse(Buf);
+
+impl DenyResponse {
+    /// Create a new `DenyResponse` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { DenyResponse(tsbk.0) }
+
+    /// Whether the additional information field is valid.
+    pub fn has_info(&self) -> bool { self.0[2] >> 7 == 1 }
+    /// Inbound opcode of the denied service request.
+    pub fn service(&self) -> u8 { self.0[2] & 0x3F }
+    /// Reason the request was denied.
+    pub fn reason(&self) -> DenyReason { DenyReason::from_bits(self.0[3]) }
+    /// Additional information about the request, usually the target address.
+    pub fn info(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Requesting unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Response given to a talkgroup affiliation request.
+pub struct GroupAffiliationResponse(Buf);
+
+impl GroupAffiliationResponse {
+    /// Create a new `GroupAffiliationResponse` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { GroupAffiliationResponse(tsbk.0) }
+
+    /// Whether the affiliation is valid only at the current site, rather than
+    /// system-wide.
+    pub fn local(&self) -> bool { self.0[2] >> 7 == 0 }
+    /// System response to the affiliation request.
+    pub fn response(&self) -> RegResponse { RegResponse::from_bits(self.0[2] & 0b11) }
+    /// Announcement group that includes the affiliated talkgroup.
+    pub fn announce_group(&self) -> TalkGroup { TalkGroup::new(&self.0[3..=4]) }
+    /// Talkgroup the unit is affiliated with.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0[5..=6]) }
+    /// Requesting unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Command for a target unit to register with the system.
+pub struct UnitRegCommand(Buf);
+
+impl UnitRegCommand {
+    /// Create a new `UnitRegCommand` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { UnitRegCommand(tsbk.0) }
+
+    /// Unit that should register.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Unit or system requesting the registration.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Command for a target unit to authenticate with the given system.
+pub struct UnitAuthCommand(Buf);
+
+impl UnitAuthCommand {
+    /// Create a new `UnitAuthCommand` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { UnitAuthCommand(tsbk.0) }
+
+    /// WACN ID within the communication network.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0[3..=5]) >> 4 }
+    /// System ID within WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[5..=6]) & 0xFFF }
+    /// Unit that should authenticate.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Operation performed on the roaming address stack of a unit.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "ser", derive(Serialize))]
+pub enum RoamingStackOp {
+    /// Clear the stack.
+    Clear,
+    /// Write the given address to the stack.
+    Write,
+    /// Delete the given address from the stack.
+    Delete,
+    /// Report the contents of the stack.
+    Read,
+    /// Reserved operation.
+    Other(u8),
+}
+
+impl RoamingStackOp {
+    /// Parse a stack operation from the given 8 bits.
+    pub fn from_bits(bits: u8) -> RoamingStackOp {
+        use self::RoamingStackOp::*;
+
+        match bits {
+            0x00 => Clear,
+            0x01 => Write,
+            0x02 => Delete,
+            0x03 => Read,
+            b => Other(b),
+        }
+    }
+}
+
+/// Command for a target unit to modify or report its list of roaming systems.
+pub struct RoamingAddrCommand(Buf);
+
+impl RoamingAddrCommand {
+    /// Create a new `RoamingAddrCommand` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { RoamingAddrCommand(tsbk.0) }
+
+    /// Operation to perform.
+    pub fn op(&self) -> RoamingStackOp { RoamingStackOp::from_bits(self.0[2]) }
+    /// WACN ID of the roaming address.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0[3..=5]) >> 4 }
+    /// System ID of the roaming address.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[5..=6]) & 0xFFF }
+    /// Target unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Roaming address sent to a target unit, one of possibly several in sequence.
+pub struct RoamingAddrUpdate(Buf);
+
+impl RoamingAddrUpdate {
+    /// Create a new `RoamingAddrUpdate` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { RoamingAddrUpdate(tsbk.0) }
+
+    /// Whether this is the last update in the sequence.
+    pub fn last(&self) -> bool { self.0[2] >> 7 == 1 }
+    /// Sequence number of the update.
+    pub fn seq(&self) -> u8 { self.0[2] & 0xF }
+    /// WACN ID of the roaming address.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0[3..=5]) >> 4 }
+    /// System ID of the roaming address.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[5..=6]) & 0xFFF }
+    /// Target unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Request from a unit for a talkgroup voice channel.
+pub struct GroupVoiceRequest(Buf);
+
+impl GroupVoiceRequest {
+    /// Create a new `GroupVoiceRequest` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { GroupVoiceRequest(tsbk.0) }
+
+    /// Options requested for the traffic channel.
+    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.0[2]) }
+    /// Talkgroup for the conversation.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0[5..=6]) }
+    /// Requesting unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Request from a unit for a unit-to-unit voice channel.
+pub struct UnitVoiceRequest(Buf);
+
+impl UnitVoiceRequest {
+    /// Create a new `UnitVoiceRequest` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { UnitVoiceRequest(tsbk.0) }
+
+    /// Options requested for the traffic channel.
+    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.0[2]) }
+    /// Unit being called.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Requesting unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Answer of a called unit to a unit-to-unit call.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "ser", derive(Serialize))]
+pub enum CallAnswer {
+    /// Proceed with the call.
+    Proceed,
+    /// Deny the call.
+    Deny,
+    /// Reserved answer.
+    Other(u8),
+}
+
+impl CallAnswer {
+    /// Parse a call answer from the given 8 bits.
+    pub fn from_bits(bits: u8) -> CallAnswer {
+        use self::CallAnswer::*;
+
+        match bits {
+            0x20 => Proceed,
+            0x21 => Deny,
+            b => Other(b),
+        }
+    }
+}
+
+/// Response from a called unit to a unit-to-unit call request.
+pub struct UnitAnswerResponse(Buf);
+
+impl UnitAnswerResponse {
+    /// Create a new `UnitAnswerResponse` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { UnitAnswerResponse(tsbk.0) }
+
+    /// Options requested for the traffic channel.
+    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.0[2]) }
+    /// Answer to the call.
+    pub fn answer(&self) -> CallAnswer { CallAnswer::from_bits(self.0[3]) }
+    /// Called unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[4..=6]) }
+    /// Calling unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Emergency alarm raised by a unit.
+pub struct EmergencyAlarm(Buf);
+
+impl EmergencyAlarm {
+    /// Create a new `EmergencyAlarm` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { EmergencyAlarm(tsbk.0) }
+
+    /// Talkgroup of the unit raising the alarm.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0[5..=6]) }
+    /// Unit raising the alarm.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Request from a unit to affiliate with a talkgroup.
+pub struct GroupAffiliationRequest(Buf);
+
+impl GroupAffiliationRequest {
+    /// Create a new `GroupAffiliationRequest` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { GroupAffiliationRequest(tsbk.0) }
+
+    /// System ID of the talkgroup within the WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[3..=4]) & 0xFFF }
+    /// Requested talkgroup.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0[5..=6]) }
+    /// Requesting unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Request from a unit to register with or deregister from a system.
+///
+/// Note that this decoder can be used with `UnitRegRequest` and `UnitDeregRequest`
+/// packets.
+pub struct UnitRegRequest(Buf);
+
+impl UnitRegRequest {
+    /// Create a new `UnitRegRequest` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { UnitRegRequest(tsbk.0) }
+
+    /// WACN ID within the communication network.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0[3..=5]) >> 4 }
+    /// System ID within WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[5..=6]) & 0xFFF }
+    /// ID of requesting unit.
+    pub fn src_id(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
+/// Request from a unit to register at a new location.
+pub struct LocRegRequest(Buf);
+
+impl LocRegRequest {
+    /// Create a new `LocRegRequest` decoder from the base TSBK decoder.
+    pub fn new(tsbk: TsbkFields) -> Self { LocRegRequest(tsbk.0) }
+
+    /// Location registration area of the new location.
+    pub fn area(&self) -> u8 { self.0[3] }
+    /// Talkgroup of the requesting unit.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0[5..=6]) }
+    /// Requesting unit.
+    pub fn src_unit(&self) -> u32 { slice_u24(&self.0[7..=9]) }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use consts::{NID_DIBITS, SYNC_SYMBOLS};
+    use message::status::{StatusDeinterleaver, StreamSymbol};
     use trunking::fields::*;
+    use voice::crypto::CryptoAlgorithm;
+
+    #[test]
+    fn test_builder() {
+        let t = TsbkBuilder::new(0b111001, 0b00000001, [
+            0b11110000,
+            0b00001111,
+            0b10101010,
+            0b01010101,
+            0b00000000,
+            0b11111111,
+            0b11001100,
+            0b00110011,
+        ]).tail(true).build();
+
+        assert!(t.is_tail());
+        assert!(!t.protected());
+        assert_eq!(t.opcode(), Some(TsbkOpcode::AltControlChannel));
+        assert_eq!(t.mfg(), 0b00000001);
+        assert_eq!(t.crc(), 0b0111010000111100);
+        assert!(t.crc_valid());
+
+        let t = TsbkBuilder::new(0b000000, 0, [0; 8])
+            .protected(true)
+            .tail(true)
+            .tail(false)
+            .build();
+
+        assert!(!t.is_tail());
+        assert!(t.protected());
+        assert_eq!(t.opcode(), Some(TsbkOpcode::GroupVoiceGrant));
+        assert!(t.crc_valid());
+    }
+
+    #[test]
+    fn test_data_unit() {
+        let payloads = [
+            (0b000000, [1, 2, 3, 4, 5, 6, 7, 8]),
+            (0b111010, [8, 7, 6, 5, 4, 3, 2, 1]),
+            (0b111101, [0xAA; 8]),
+        ];
+
+        for n in 1..=3 {
+            let group: Vec<TsbkFields> = payloads[..n].iter().enumerate()
+                .map(|(i, &(opcode, payload))| {
+                    TsbkBuilder::new(opcode, 0, payload).tail(i == n - 1).build()
+                })
+                .collect();
+
+            let dibits = data_unit(NetworkAccessCode::Default, &group[..],
+                                   StatusCode::InboundIdle);
+
+            // Always ends on a status symbol.
+            assert_eq!(dibits.len() % 36, 0);
+            assert_eq!(dibits.len(), [180, 288, 360][n - 1]);
+
+            let mut stream = StatusDeinterleaver::new();
+            let data: Vec<Dibit> = dibits[SYNC_SYMBOLS..].iter()
+                .filter_map(|&d| match stream.feed(d) {
+                    StreamSymbol::Data(d) => Some(d),
+                    StreamSymbol::Status(s) => {
+                        assert_eq!(s, StatusCode::InboundIdle);
+                        None
+                    },
+                })
+                .collect();
+
+            let mut recv = TsbkReceiver::new();
+            let pkts: Vec<TsbkFields> = data[NID_DIBITS..].iter()
+                .filter_map(|&d| recv.feed(d))
+                .map(|r| r.unwrap())
+                .collect();
+
+            assert_eq!(pkts.len(), n);
+
+            for (pkt, tsbk) in pkts.iter().zip(group.iter()) {
+                assert!(pkt.crc_valid());
+                assert_eq!(pkt.0, tsbk.0);
+            }
+        }
+    }
 
     #[test]
     fn test_tsbk_fields() {
@@ -450,6 +1194,11 @@ mod test {
         assert_eq!(t.opcode(), Some(TsbkOpcode::ChannelParamsUpdate));
         let p = ChannelParamsUpdate::new(t.payload());
 
+        assert_eq!(TsbkOpcode::from_bits(0b110011),
+                   Some(TsbkOpcode::ChannelParamsUpdateTdma));
+        assert_eq!(TsbkOpcode::from_bits(0b110100),
+                   Some(TsbkOpcode::ChannelParamsUpdateVu));
+
         assert_eq!(p.id(), 0b0110);
         assert_eq!(p.params().bandwidth, 12_500);
         assert_eq!(p.params().rx_freq(0b1001), 851_062_500);
@@ -856,4 +1605,352 @@ mod test {
         assert_eq!(g.talkgroup(), TalkGroup::Other(0b1111000010001001));
         assert_eq!(g.src_unit(), 0b111000110100010011101010);
     }
+
+    #[test]
+    fn test_group_traffic_update_explicit() {
+        let t = TsbkBuilder::new(0b010011, 0, [
+            0b00100101, 0, 0x10, 0x05, 0x20, 0x06, 0x12, 0x34,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::GroupDataUpdateExplicit));
+        let u = GroupTrafficUpdateExplicit::new(t);
+        assert!(u.opts().full_duplex());
+        assert_eq!(u.opts().prio(), 5);
+        assert_eq!(u.rx_channel().id(), 1);
+        assert_eq!(u.rx_channel().number(), 5);
+        assert_eq!(u.tx_channel().id(), 2);
+        assert_eq!(u.tx_channel().number(), 6);
+        assert_eq!(u.talkgroup(), TalkGroup::Other(0x1234));
+
+        let t = TsbkBuilder::new(0b010010, 0, [
+            0x10, 0x05, 0x12, 0x34, 0x10, 0x06, 0xFF, 0xFF,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::GroupDataUpdate));
+        let u = GroupTrafficUpdate::new(t.payload()).updates();
+        assert_eq!(u[0].0.number(), 5);
+        assert_eq!(u[0].1, TalkGroup::Other(0x1234));
+        assert_eq!(u[1].0.number(), 6);
+        assert_eq!(u[1].1, TalkGroup::Everbody);
+    }
+
+    #[test]
+    fn test_unit_messages() {
+        let payload = [0x12, 0x34, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45];
+
+        let t = TsbkBuilder::new(0b011000, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::UnitStatusUpdate));
+        let u = UnitStatusUpdate::new(t.payload());
+        assert_eq!(u.user_status(), 0x12);
+        assert_eq!(u.unit_status(), 0x34);
+        assert_eq!(u.dest_unit(), 0xABCDEF);
+        assert_eq!(u.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b011010, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::UnitStatusQuery));
+        let q = UnitStatusQuery::new(t.payload());
+        assert_eq!(q.dest_unit(), 0xABCDEF);
+        assert_eq!(q.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b011100, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::UnitShortMessage));
+        assert_eq!(UnitShortMessage::new(t.payload()).msg(), 0x1234);
+
+        let t = TsbkBuilder::new(0b100100, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::ExtendedFunctionResponse));
+        let f = ExtendedFunction::new(t.payload());
+        assert_eq!(f.class(), 0x12);
+        assert_eq!(f.operand(), 0x34);
+        assert_eq!(f.args(), 0xABCDEF);
+        assert_eq!(f.dest_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b101010, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::GroupAffiliationQuery));
+        let q = GroupAffiliationQuery::new(t.payload());
+        assert_eq!(q.dest_unit(), 0xABCDEF);
+        assert_eq!(q.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b011101, 0, [
+            0, 0b10000010, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::UnitMonitor));
+        let m = UnitMonitor::new(t);
+        assert!(m.silent());
+        assert_eq!(m.tx_mult(), 2);
+        assert_eq!(m.dest_unit(), 0xABCDEF);
+        assert_eq!(m.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b101101, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::UnitRegCommand));
+        let r = UnitRegCommand::new(t);
+        assert_eq!(r.dest_unit(), 0xABCDEF);
+        assert_eq!(r.src_unit(), 0x012345);
+    }
+
+    #[test]
+    fn test_responses() {
+        let t = TsbkBuilder::new(0b100000, 0, [
+            0b11000100, 0xBE, 0xE0, 0x01, 0x23, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::AckResponse));
+        let a = AckResponse::new(t);
+        assert!(a.has_info());
+        assert!(a.extended());
+        assert_eq!(a.service(), 0b000100);
+        assert_eq!(a.wacn(), 0xBEE00);
+        assert_eq!(a.system(), 0x123);
+        assert_eq!(a.dest_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b100001, 0, [
+            0b10000000, 0x2F, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::QueuedResponse));
+        let q = QueuedResponse::new(t);
+        assert!(q.has_info());
+        assert_eq!(q.service(), 0);
+        assert_eq!(q.reason(), 0x2F);
+        assert_eq!(q.info(), 0xABCDEF);
+        assert_eq!(q.dest_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b100111, 0, [
+            0b00000100, 0x31, 0x00, 0x12, 0x34, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::DenyResponse));
+        let d = DenyResponse::new(t);
+        assert!(!d.has_info());
+        assert_eq!(d.service(), 0b000100);
+        assert_eq!(d.reason(), DenyReason::TargetGroupUnauthorized);
+        assert_eq!(d.info(), 0x1234);
+        assert_eq!(d.dest_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b101000, 0, [
+            0b10000010, 0x00, 0x01, 0x12, 0x34, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::GroupAffiliationResponse));
+        let g = GroupAffiliationResponse::new(t);
+        assert!(!g.local());
+        assert_eq!(g.response(), RegResponse::Deny);
+        assert_eq!(g.announce_group(), TalkGroup::Default);
+        assert_eq!(g.talkgroup(), TalkGroup::Other(0x1234));
+        assert_eq!(g.dest_unit(), 0x012345);
+    }
+
+    #[test]
+    fn test_unit_auth_roaming() {
+        let payload = [0x01, 0xBE, 0xE0, 0x01, 0x23, 0x01, 0x23, 0x45];
+
+        let t = TsbkBuilder::new(0b101110, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::UnitAuthCommand));
+        let a = UnitAuthCommand::new(t);
+        assert_eq!(a.wacn(), 0xBEE00);
+        assert_eq!(a.system(), 0x123);
+        assert_eq!(a.dest_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b110110, 0, payload).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::RoamingAddrCommand));
+        let r = RoamingAddrCommand::new(t);
+        assert_eq!(r.op(), RoamingStackOp::Write);
+        assert_eq!(r.wacn(), 0xBEE00);
+        assert_eq!(r.system(), 0x123);
+        assert_eq!(r.dest_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b110111, 0, [
+            0b10000011, 0xBE, 0xE0, 0x01, 0x23, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::RoamingAddrUpdate));
+        let u = RoamingAddrUpdate::new(t);
+        assert!(u.last());
+        assert_eq!(u.seq(), 3);
+        assert_eq!(u.wacn(), 0xBEE00);
+        assert_eq!(u.system(), 0x123);
+        assert_eq!(u.dest_unit(), 0x012345);
+    }
+
+    #[test]
+    fn test_system_service_broadcast() {
+        let t = TsbkBuilder::new(0b111000, 0, [
+            0x00, 0x00, 0x10, 0xF0, 0x00, 0x30, 0xF0, 0x01,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::SystemServiceBroadcast));
+        let s = SystemServiceBroadcast::new(t.payload());
+        assert_eq!(s.available(), 0x0010F0);
+        assert_eq!(s.supported(), 0x0030F0);
+        assert_eq!(s.request_prio(), 1);
+    }
+
+    #[test]
+    fn test_protection_params() {
+        let t = TsbkBuilder::new(0b111110, 0, [
+            0x00, 0x00, 0x84, 0x12, 0x34, 0xAB, 0xCD, 0xEF,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::ProtectionParamBroadcast));
+        let p = ProtectionParams::new(t.payload());
+        assert_eq!(p.alg(), CryptoAlgorithm::Aes);
+        assert_eq!(p.key(), 0x1234);
+        assert_eq!(p.dest_unit(), 0xABCDEF);
+
+        let t = TsbkBuilder::new(0b111111, 0, [
+            0x00, 0x00, 0x81, 0x00, 0x01, 0x00, 0x00, 0x2A,
+        ]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::ProtectionParamUpdate));
+        let p = ProtectionParams::new(t.payload());
+        assert_eq!(p.alg(), CryptoAlgorithm::Des);
+        assert_eq!(p.key(), 1);
+        assert_eq!(p.dest_unit(), 0x2A);
+    }
+
+    #[test]
+    fn test_inbound_opcode() {
+        let t = TsbkBuilder::new(0b101000, 0, [0; 8]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::GroupAffiliationResponse));
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::GroupAffiliationRequest));
+
+        let t = TsbkBuilder::new(0b100111, 0, [0; 8]).build();
+        assert_eq!(t.opcode(), Some(TsbkOpcode::DenyResponse));
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::EmergencyAlarm));
+
+        for bits in 0..64 {
+            assert!(InboundTsbkOpcode::from_bits(bits).is_some());
+        }
+    }
+
+    #[test]
+    fn test_inbound_requests() {
+        let t = TsbkBuilder::new(0b000000, 0, [
+            0b10000011, 0, 0, 0x12, 0x34, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::GroupVoiceRequest));
+        let r = GroupVoiceRequest::new(t);
+        assert!(r.opts().emergency());
+        assert_eq!(r.opts().prio(), 3);
+        assert_eq!(r.talkgroup(), TalkGroup::Other(0x1234));
+        assert_eq!(r.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b000100, 0, [
+            0b00000100, 0, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::UnitVoiceRequest));
+        let r = UnitVoiceRequest::new(t);
+        assert_eq!(r.opts().prio(), 4);
+        assert_eq!(r.dest_unit(), 0xABCDEF);
+        assert_eq!(r.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b000101, 0, [
+            0, 0x21, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::UnitAnswerResponse));
+        let r = UnitAnswerResponse::new(t);
+        assert_eq!(r.answer(), CallAnswer::Deny);
+        assert_eq!(r.dest_unit(), 0xABCDEF);
+        assert_eq!(r.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b100111, 0, [
+            0, 0, 0, 0x00, 0x01, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::EmergencyAlarm));
+        let a = EmergencyAlarm::new(t);
+        assert_eq!(a.talkgroup(), TalkGroup::Default);
+        assert_eq!(a.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b101000, 0, [
+            0, 0x01, 0x23, 0x12, 0x34, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.inbound_opcode(),
+                   Some(InboundTsbkOpcode::GroupAffiliationRequest));
+        let r = GroupAffiliationRequest::new(t);
+        assert_eq!(r.system(), 0x123);
+        assert_eq!(r.talkgroup(), TalkGroup::Other(0x1234));
+        assert_eq!(r.src_unit(), 0x012345);
+
+        let t = TsbkBuilder::new(0b101100, 0, [
+            0, 0xBE, 0xE0, 0x01, 0x23, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::UnitRegRequest));
+        let r = UnitRegRequest::new(t);
+        assert_eq!(r.wacn(), 0xBEE00);
+        assert_eq!(r.system(), 0x123);
+        assert_eq!(r.src_id(), 0x012345);
+
+        let t = TsbkBuilder::new(0b101101, 0, [
+            0, 0x42, 0, 0x12, 0x34, 0x01, 0x23, 0x45,
+        ]).build();
+        assert_eq!(t.inbound_opcode(), Some(InboundTsbkOpcode::LocRegRequest));
+        let r = LocRegRequest::new(t);
+        assert_eq!(r.area(), 0x42);
+        assert_eq!(r.talkgroup(), TalkGroup::Other(0x1234));
+        assert_eq!(r.src_unit(), 0x012345);
+    }
+
+    #[test]
+    fn test_registry() {
+        let reg = TsbkRegistry::builtin();
+
+        let t = TsbkBuilder::new(0b111000, fields::MFG_STANDARD_ALT, [0; 8]).build();
+        match reg.decode(&t) {
+            TsbkKind::Standard(TsbkOpcode::SystemServiceBroadcast) => {},
+            _ => panic!(),
+        }
+
+        let t = TsbkBuilder::new(0x00, fields::MFG_MOTOROLA, [
+            0x12, 0x34, 0x00, 0x01, 0x00, 0x02, 0x12, 0x34,
+        ]).build();
+        match reg.decode(&t) {
+            TsbkKind::Vendor(VendorTsbk::MotorolaPatchAdd(p)) => {
+                assert_eq!(p.supergroup(), TalkGroup::Other(0x1234));
+                assert_eq!(p.groups(), [
+                    TalkGroup::Default,
+                    TalkGroup::Other(2),
+                    TalkGroup::Other(0x1234),
+                ]);
+            },
+            _ => panic!(),
+        }
+
+        let t = TsbkBuilder::new(0x01, fields::MFG_MOTOROLA, [0; 8]).build();
+        match reg.decode(&t) {
+            TsbkKind::Vendor(VendorTsbk::MotorolaPatchDelete(_)) => {},
+            _ => panic!(),
+        }
+
+        // "W1AW" followed by blanks.
+        let chars = [
+            b'W' - 43, b'1' - 43, b'A' - 43, b'W' - 43, 0, 0, 0, 0,
+        ];
+        let bits = chars.iter().fold(0u64, |b, &c| b << 6 | c as u64);
+
+        let t = TsbkBuilder::new(0x0B, fields::MFG_MOTOROLA, [
+            (bits >> 40) as u8, (bits >> 32) as u8, (bits >> 24) as u8,
+            (bits >> 16) as u8, (bits >> 8) as u8, bits as u8,
+            0x10, 0x42,
+        ]).build();
+        match reg.decode(&t) {
+            TsbkKind::Vendor(VendorTsbk::MotorolaBaseStationId(b)) => {
+                assert_eq!(b.callsign(), "W1AW");
+                assert_eq!(b.channel().id(), 1);
+                assert_eq!(b.channel().number(), 0x42);
+            },
+            _ => panic!(),
+        }
+
+        // Unrecognized opcode of a known manufacturer.
+        let t = TsbkBuilder::new(0x3F, fields::MFG_MOTOROLA, [0; 8]).build();
+        match reg.decode(&t) {
+            TsbkKind::Unknown { mfg: fields::MFG_MOTOROLA, opcode: 0x3F } => {},
+            _ => panic!(),
+        }
+
+        // Unknown manufacturer.
+        let t = TsbkBuilder::new(0x00, fields::MFG_HARRIS, [0; 8]).build();
+        match reg.decode(&t) {
+            TsbkKind::Unknown { mfg: fields::MFG_HARRIS, opcode: 0x00 } => {},
+            _ => panic!(),
+        }
+
+        // Custom decoders can be registered for other manufacturers.
+        fn harris(t: &TsbkFields) -> Option<u8> { Some(t.payload()[0]) }
+
+        let mut reg = TsbkRegistry::new();
+        reg.register(fields::MFG_HARRIS, harris);
+
+        let t = TsbkBuilder::new(0x00, fields::MFG_HARRIS, [42; 8]).build();
+        assert_eq!(reg.decode(&t), TsbkKind::Vendor(42));
+    }
 }
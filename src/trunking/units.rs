@@ -0,0 +1,316 @@
+//! Track the units seen on a system and their registrations and affiliations from control
+//! channel traffic.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Values;
+
+use trunking::fields::{self, RegResponse, TalkGroup};
+use trunking::tsbk::{TsbkFields, TsbkOpcode, LocRegResponse, UnitRegResponse,
+                     UnitDeregAck, GroupAffiliationResponse, GroupVoiceGrant,
+                     UnitTrafficChannel};
+
+/// Kind of control channel traffic a unit was last seen in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize))]
+pub enum UnitActivity {
+    /// Unit registration response.
+    Registration,
+    /// Location registration response.
+    LocRegistration,
+    /// Deregistration acknowledgement.
+    Deregistration,
+    /// Talkgroup affiliation response.
+    Affiliation,
+    /// Voice channel grant, as source or destination.
+    Call,
+}
+
+/// Everything known about a unit.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "ser", derive(Serialize))]
+pub struct UnitRecord {
+    /// Address of the unit within the system.
+    pub unit: u32,
+    /// ID the unit registered with, if a registration response was seen.
+    pub id: Option<u32>,
+    /// WACN ID of the unit's system, if known.
+    pub wacn: Option<u32>,
+    /// System ID of the unit within the WACN, if known.
+    pub system: Option<u16>,
+    /// RFSS and Site ID where the unit was last seen, if known.
+    pub site: Option<(u8, u8)>,
+    /// Response to the unit's most recent registration, if any was seen.
+    pub reg: Option<RegResponse>,
+    /// Talkgroup the unit is currently affiliated with, if any.
+    pub talkgroup: Option<TalkGroup>,
+    /// Kind of traffic the unit was last seen in.
+    pub activity: UnitActivity,
+    /// Time (seconds since the registry was created) the unit was last seen.
+    pub last_seen: f64,
+}
+
+impl UnitRecord {
+    /// Create a new `UnitRecord` with only the given address known.
+    fn new(unit: u32, activity: UnitActivity) -> UnitRecord {
+        UnitRecord {
+            unit: unit,
+            id: None,
+            wacn: None,
+            system: None,
+            site: None,
+            reg: None,
+            talkgroup: None,
+            activity: activity,
+            last_seen: 0.0,
+        }
+    }
+
+    /// Whether the unit's most recent registration was accepted.
+    pub fn registered(&self) -> bool { self.reg == Some(RegResponse::Accept) }
+
+    /// Whether the unit is affiliated with the given talkgroup.
+    pub fn affiliated(&self, tg: TalkGroup) -> bool { self.talkgroup == Some(tg) }
+}
+
+/// Registry of units seen in control channel traffic.
+///
+/// Status broadcasts are used to determine the WACN, System, and site that units are
+/// seen on.
+pub struct UnitRegistry {
+    /// Records keyed by unit address.
+    units: BTreeMap<u32, UnitRecord>,
+    /// Current time (seconds.)
+    now: f64,
+    /// WACN ID of the current system, if known.
+    wacn: Option<u32>,
+    /// System ID of the current system, if known.
+    system: Option<u16>,
+    /// RFSS and Site ID of the current site, if known.
+    site: Option<(u8, u8)>,
+}
+
+impl UnitRegistry {
+    /// Create a new, empty `UnitRegistry`.
+    pub fn new() -> UnitRegistry {
+        UnitRegistry {
+            units: BTreeMap::new(),
+            now: 0.0,
+            wacn: None,
+            system: None,
+            site: None,
+        }
+    }
+
+    /// Advance the registry clock by the given time (seconds.)
+    pub fn advance(&mut self, secs: f64) { self.now += secs; }
+
+    /// Number of units in the registry.
+    pub fn len(&self) -> usize { self.units.len() }
+
+    /// Whether the registry has no units.
+    pub fn is_empty(&self) -> bool { self.units.is_empty() }
+
+    /// Retrieve the record of the given unit address, if it has been seen.
+    pub fn get(&self, unit: u32) -> Option<&UnitRecord> { self.units.get(&unit) }
+
+    /// Iterate over all unit records in order of unit address.
+    pub fn units(&self) -> Values<'_, u32, UnitRecord> { self.units.values() }
+
+    /// Retrieve the records of all units affiliated with the given talkgroup.
+    pub fn affiliated(&self, tg: TalkGroup) -> Vec<&UnitRecord> {
+        self.units().filter(|u| u.affiliated(tg)).collect()
+    }
+
+    /// Retrieve the records of all units seen within the given time (seconds) of now.
+    pub fn active(&self, secs: f64) -> Vec<&UnitRecord> {
+        let now = self.now;
+        self.units().filter(|u| now - u.last_seen <= secs).collect()
+    }
+
+    /// Copy out all unit records in order of unit address.
+    pub fn export(&self) -> Vec<UnitRecord> { self.units().cloned().collect() }
+
+    /// Update the registry with the given TSBK.
+    pub fn feed_tsbk(&mut self, tsbk: &TsbkFields) {
+        if !fields::is_standard_mfg(tsbk.mfg()) || !tsbk.crc_valid() {
+            return;
+        }
+
+        match tsbk.opcode() {
+            Some(TsbkOpcode::NetworkStatusBroadcast) => {
+                let n = fields::NetworkStatusBroadcast::new(tsbk.payload());
+                self.wacn = Some(n.wacn());
+                self.system = Some(n.system());
+            },
+            Some(TsbkOpcode::RfssStatusBroadcast) => {
+                let r = fields::RfssStatusBroadcast::new(tsbk.payload());
+                self.system = Some(r.system());
+                self.site = Some((r.rfss(), r.site()));
+            },
+            Some(TsbkOpcode::UnitRegResponse) => {
+                let r = UnitRegResponse::new(*tsbk);
+                let u = self.touch(r.src_addr(), UnitActivity::Registration);
+
+                u.id = Some(r.src_id());
+                u.system = Some(r.system());
+                u.reg = Some(r.response());
+            },
+            Some(TsbkOpcode::LocRegResponse) => {
+                let r = LocRegResponse::new(*tsbk);
+                let u = self.touch(r.dest_unit(), UnitActivity::LocRegistration);
+
+                u.site = Some((r.rfss(), r.site()));
+                u.reg = Some(r.response());
+
+                if r.response() == RegResponse::Accept {
+                    u.talkgroup = Some(r.talkgroup());
+                }
+            },
+            Some(TsbkOpcode::UnitDeregAck) => {
+                let r = UnitDeregAck::new(*tsbk);
+                let u = self.touch(r.src_unit(), UnitActivity::Deregistration);
+
+                u.wacn = Some(r.wacn());
+                u.system = Some(r.system());
+                u.reg = None;
+                u.talkgroup = None;
+            },
+            Some(TsbkOpcode::GroupAffiliationResponse) => {
+                let r = GroupAffiliationResponse::new(*tsbk);
+                let u = self.touch(r.dest_unit(), UnitActivity::Affiliation);
+
+                // Units are affiliated with a single talkgroup at a time.
+                if r.response() == RegResponse::Accept {
+                    u.talkgroup = Some(r.talkgroup());
+                }
+            },
+            Some(TsbkOpcode::GroupVoiceGrant) => {
+                let g = GroupVoiceGrant::new(*tsbk);
+                self.touch(g.src_unit(), UnitActivity::Call);
+            },
+            Some(TsbkOpcode::UnitVoiceGrant) => {
+                let g = UnitTrafficChannel::new(*tsbk);
+                self.touch(g.src_unit(), UnitActivity::Call);
+                self.touch(g.dest_unit(), UnitActivity::Call);
+            },
+            _ => {},
+        }
+    }
+
+    /// Retrieve the record of the given unit, creating it if needed, and mark it as seen
+    /// now at the current site.
+    fn touch(&mut self, unit: u32, activity: UnitActivity) -> &mut UnitRecord {
+        let u = self.units.entry(unit).or_insert_with(|| UnitRecord::new(unit, activity));
+
+        u.activity = activity;
+        u.last_seen = self.now;
+        u.wacn = self.wacn.or(u.wacn);
+        u.system = self.system.or(u.system);
+        u.site = self.site.or(u.site);
+
+        u
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use trunking::tsbk::TsbkBuilder;
+
+    #[test]
+    fn test_registration() {
+        let mut r = UnitRegistry::new();
+
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::NetworkStatusBroadcast, [
+            0x07, 0xBE, 0xE0, 0x01, 0x23, 0x10, 0x05, 0x70,
+        ]).build());
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::RfssStatusBroadcast, [
+            0x07, 0x11, 0x23, 0x02, 0x03, 0x10, 0x05, 0x70,
+        ]).build());
+
+        assert!(r.is_empty());
+        r.advance(10.0);
+
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::UnitRegResponse, [
+            0x01, 0x23, 0x12, 0x34, 0x56, 0x00, 0x00, 0x2A,
+        ]).build());
+
+        let u = r.get(0x2A).unwrap();
+        assert_eq!(u.id, Some(0x123456));
+        assert_eq!(u.wacn, Some(0xBEE00));
+        assert_eq!(u.system, Some(0x123));
+        assert_eq!(u.site, Some((2, 3)));
+        assert!(u.registered());
+        assert_eq!(u.activity, UnitActivity::Registration);
+        assert_eq!(u.last_seen, 10.0);
+
+        r.advance(5.0);
+
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::GroupAffiliationResponse, [
+            0x80, 0x00, 0x00, 0x12, 0x34, 0x00, 0x00, 0x2A,
+        ]).build());
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::GroupAffiliationResponse, [
+            0x82, 0x00, 0x00, 0x56, 0x78, 0x00, 0x00, 0x2A,
+        ]).build());
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::LocRegResponse, [
+            0x00, 0x00, 0x01, 0x02, 0x04, 0x00, 0x00, 0x2B,
+        ]).build());
+
+        let u = r.get(0x2A).unwrap();
+        assert_eq!(u.talkgroup, Some(TalkGroup::Other(0x1234)));
+        assert_eq!(u.activity, UnitActivity::Affiliation);
+        assert_eq!(u.last_seen, 15.0);
+
+        let u = r.get(0x2B).unwrap();
+        assert_eq!(u.site, Some((2, 4)));
+        assert!(u.affiliated(TalkGroup::Default));
+
+        assert_eq!(r.affiliated(TalkGroup::Other(0x1234)).len(), 1);
+        assert_eq!(r.active(1.0).len(), 2);
+
+        // Affiliating with another talkgroup replaces the current one.
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::GroupAffiliationResponse, [
+            0x80, 0x00, 0x00, 0x56, 0x78, 0x00, 0x00, 0x2A,
+        ]).build());
+
+        assert_eq!(r.get(0x2A).unwrap().talkgroup, Some(TalkGroup::Other(0x5678)));
+        assert!(r.affiliated(TalkGroup::Other(0x1234)).is_empty());
+
+        r.advance(5.0);
+
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::UnitDeregAck, [
+            0x00, 0xBE, 0xE0, 0x01, 0x23, 0x00, 0x00, 0x2A,
+        ]).build());
+
+        let u = r.get(0x2A).unwrap();
+        assert!(!u.registered());
+        assert_eq!(u.talkgroup, None);
+        assert_eq!(r.active(1.0).len(), 1);
+
+        let e = r.export();
+        assert_eq!(e.len(), 2);
+        assert_eq!(e[0].unit, 0x2A);
+        assert_eq!(e[1].unit, 0x2B);
+    }
+
+    #[test]
+    fn test_calls() {
+        let mut r = UnitRegistry::new();
+
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::GroupVoiceGrant, [
+            0x00, 0x60, 0x09, 0x12, 0x34, 0x00, 0x00, 0x01,
+        ]).build());
+        r.feed_tsbk(&TsbkBuilder::standard(TsbkOpcode::UnitVoiceGrant, [
+            0x60, 0x09, 0x00, 0x00, 0x02, 0x00, 0x00, 0x03,
+        ]).build());
+
+        let units: Vec<u32> = r.units().map(|u| u.unit).collect();
+        assert_eq!(units, vec![1, 2, 3]);
+        assert!(r.units().all(|u| u.activity == UnitActivity::Call));
+        assert!(r.units().all(|u| u.reg.is_none() && u.site.is_none()));
+
+        // Vendor packets are ignored.
+        r.feed_tsbk(&TsbkBuilder::new(0x00, 0x90, [0; 8]).build());
+        assert_eq!(r.len(), 3);
+    }
+}
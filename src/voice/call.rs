@@ -0,0 +1,521 @@
+//! Group the voice events of a received message stream into calls.
+//!
+//! A call starts with a voice header or, if the header was missed, the first LDU1 frame
+//! group, and ends with a simple or link control terminator. If the terminator is missed,
+//! the call ends once no voice data units have been seen for a timeout period.
+
+use message::nid::NetworkAccessCode;
+use message::receiver::MessageEvent;
+use trunking::fields::{self, TalkGroup};
+use voice::control::{LinkControlFields, LinkControlOpcode, GroupVoiceTraffic,
+                     UnitVoiceTraffic};
+use voice::crypto::CryptoAlgorithm;
+use voice::frame::VoiceFrame;
+
+/// Default time (seconds) without voice data units after which a call is ended.
+const DEFAULT_TIMEOUT: f32 = 1.0;
+
+/// Parameters needed to decrypt a call.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CryptoParams {
+    /// Initialization vector of the crypto algorithm.
+    pub init: [u8; 9],
+    /// Crypto algorithm in use.
+    pub alg: CryptoAlgorithm,
+    /// Encryption key ID.
+    pub key: u16,
+}
+
+impl CryptoParams {
+    /// Create a new `CryptoParams` from the given initialization vector bytes, algorithm,
+    /// and key.
+    fn new(init: &[u8], alg: CryptoAlgorithm, key: u16) -> CryptoParams {
+        let mut buf = [0; 9];
+        buf.copy_from_slice(init);
+
+        CryptoParams {
+            init: buf,
+            alg: alg,
+            key: key,
+        }
+    }
+}
+
+/// How a call ended.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CallEnd {
+    /// A simple or link control terminator was received.
+    Terminator,
+    /// No voice data units were received for the timeout period.
+    Timeout,
+    /// A new call started before a terminator was received.
+    Superseded,
+}
+
+/// Information collected over a complete call.
+pub struct CallSummary {
+    /// NAC of the channel carrying the call.
+    pub nac: NetworkAccessCode,
+    /// Talkgroup of a group call, if known.
+    pub talkgroup: Option<TalkGroup>,
+    /// Source unit of the call, if known.
+    pub src_unit: Option<u32>,
+    /// Destination unit of a unit-to-unit call, if known.
+    pub dest_unit: Option<u32>,
+    /// Whether the call was marked as an emergency.
+    pub emergency: bool,
+    /// Most recent crypto parameters, if any were received.
+    pub crypto: Option<CryptoParams>,
+    /// Voice frames of the call, in received order.
+    pub frames: Vec<VoiceFrame>,
+    /// Time (seconds) from the start of the call to the last data unit received.
+    pub duration: f32,
+    /// How the call ended.
+    pub end: CallEnd,
+}
+
+impl CallSummary {
+    /// Total number of FEC errors corrected over all voice frames.
+    pub fn errors(&self) -> usize {
+        self.frames.iter().fold(0, |s, f| s + f.errors.iter().sum::<usize>())
+    }
+
+    /// Fraction of voice frames received without any FEC errors, from 0 to 1, or `None`
+    /// if no voice frames were received.
+    pub fn quality(&self) -> Option<f32> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        let clean = self.frames.iter()
+            .filter(|f| f.errors.iter().all(|&e| e == 0))
+            .count();
+
+        Some(clean as f32 / self.frames.len() as f32)
+    }
+
+    /// Whether the call was encrypted.
+    pub fn encrypted(&self) -> bool {
+        match self.crypto {
+            Some(c) => c.alg != CryptoAlgorithm::Unencrypted,
+            None => false,
+        }
+    }
+}
+
+/// Call in progress.
+struct Call {
+    /// Collected information.
+    summary: CallSummary,
+    /// Time (seconds) when the call started.
+    start: f64,
+    /// Time (seconds) of the last voice data unit.
+    last: f64,
+}
+
+/// Groups voice events into calls, producing a summary of each call when it ends.
+///
+/// The tracker has its own clock, which must be advanced by the time covered by the
+/// samples fed into the receiver, independent of how many events they produced.
+pub struct CallTracker {
+    /// Time (seconds) without voice data units after which a call is ended.
+    timeout: f32,
+    /// Current time (seconds.)
+    now: f64,
+    /// NAC of the most recent data unit.
+    nac: Option<NetworkAccessCode>,
+    /// Current call, if any.
+    call: Option<Call>,
+}
+
+impl CallTracker {
+    /// Create a new `CallTracker` with no call in progress.
+    pub fn new() -> CallTracker {
+        CallTracker {
+            timeout: DEFAULT_TIMEOUT,
+            now: 0.0,
+            nac: None,
+            call: None,
+        }
+    }
+
+    /// Set the time (seconds) without voice data units after which a call is ended.
+    pub fn set_timeout(&mut self, secs: f32) { self.timeout = secs; }
+
+    /// Whether a call is currently in progress.
+    pub fn in_call(&self) -> bool { self.call.is_some() }
+
+    /// Advance the clock by the given time (seconds.) Return a summary of the current
+    /// call if it timed out.
+    pub fn advance(&mut self, secs: f64) -> Option<CallSummary> {
+        self.now += secs;
+        self.check_timeout()
+    }
+
+    /// Handle the given event. Return a summary of the current call if it ended.
+    pub fn feed(&mut self, event: &MessageEvent) -> Option<CallSummary> {
+        use message::nid::DataUnit::*;
+
+        match *event {
+            MessageEvent::PacketNID(nid) => {
+                self.nac = Some(nid.access_code);
+
+                match nid.data_unit {
+                    VoiceHeader => self.start(),
+                    VoiceLCFrameGroup if self.call.is_none() => self.start(),
+                    VoiceLCFrameGroup | VoiceCCFrameGroup | VoiceLCTerminator => {
+                        self.touch();
+                        None
+                    },
+                    VoiceSimpleTerminator => self.finish(CallEnd::Terminator),
+                    DataPacket | TrunkingSignaling => self.check_timeout(),
+                }
+            },
+            MessageEvent::VoiceHeader(ref h) => {
+                if let Some(ref mut c) = self.call {
+                    c.summary.talkgroup = Some(h.talk_group());
+                    c.summary.crypto = Some(CryptoParams::new(
+                        h.crypto_init(), h.crypto_alg(), h.crypto_key()));
+                }
+
+                None
+            },
+            MessageEvent::VoiceFrame(ref vf) => {
+                if let Some(ref mut c) = self.call {
+                    c.summary.frames.push(*vf);
+                }
+
+                None
+            },
+            MessageEvent::LinkControl(ref lc) => {
+                if let Some(ref mut c) = self.call {
+                    update_lc(&mut c.summary, lc);
+                }
+
+                None
+            },
+            MessageEvent::CryptoControl(ref cc) => {
+                if let Some(ref mut c) = self.call {
+                    c.summary.crypto = Some(CryptoParams::new(cc.init(), cc.alg(),
+                                                              cc.key()));
+                }
+
+                None
+            },
+            MessageEvent::VoiceTerm(ref lc) => {
+                if let Some(ref mut c) = self.call {
+                    update_lc(&mut c.summary, lc);
+                }
+
+                self.finish(CallEnd::Terminator)
+            },
+            _ => self.check_timeout(),
+        }
+    }
+
+    /// Start a new call, returning a summary of any call it replaced.
+    fn start(&mut self) -> Option<CallSummary> {
+        let prev = self.finish(CallEnd::Superseded);
+
+        self.call = Some(Call {
+            summary: CallSummary {
+                nac: self.nac.unwrap_or(NetworkAccessCode::Default),
+                talkgroup: None,
+                src_unit: None,
+                dest_unit: None,
+                emergency: false,
+                crypto: None,
+                frames: vec![],
+                duration: 0.0,
+                end: CallEnd::Terminator,
+            },
+            start: self.now,
+            last: self.now,
+        });
+
+        prev
+    }
+
+    /// Record that a voice data unit was seen in the current call.
+    fn touch(&mut self) {
+        if let Some(ref mut c) = self.call {
+            c.last = self.now;
+        }
+    }
+
+    /// End the current call if it has timed out.
+    fn check_timeout(&mut self) -> Option<CallSummary> {
+        let expired = match self.call {
+            Some(ref c) => self.now - c.last > self.timeout as f64,
+            None => false,
+        };
+
+        if expired {
+            self.finish(CallEnd::Timeout)
+        } else {
+            None
+        }
+    }
+
+    /// End the current call, if any, for the given reason.
+    fn finish(&mut self, end: CallEnd) -> Option<CallSummary> {
+        if end != CallEnd::Timeout {
+            self.touch();
+        }
+
+        self.call.take().map(|c| {
+            let mut summary = c.summary;
+
+            summary.duration = (c.last - c.start) as f32;
+            summary.end = end;
+
+            summary
+        })
+    }
+}
+
+/// Update call information from the given link control word.
+fn update_lc(summary: &mut CallSummary, lc: &LinkControlFields) {
+    if !fields::is_standard_mfg(lc.mfg()) {
+        return;
+    }
+
+    match lc.opcode() {
+        Some(LinkControlOpcode::GroupVoiceTraffic) => {
+            let gv = GroupVoiceTraffic::new(*lc);
+
+            summary.talkgroup = Some(gv.talkgroup());
+            summary.src_unit = Some(gv.src_unit());
+            summary.emergency |= gv.opts().emergency();
+        },
+        Some(LinkControlOpcode::UnitVoiceTraffic) => {
+            let uv = UnitVoiceTraffic::new(*lc);
+
+            summary.src_unit = Some(uv.src_unit());
+            summary.dest_unit = Some(uv.dest_unit());
+            summary.emergency |= uv.opts().emergency();
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std;
+    use baseband::iq::Complex;
+    use bits::Dibit;
+    use message::nid::{DataUnit, NetworkId};
+    use message::receiver::ComplexMessageReceiver;
+    use message::status::StatusCode;
+    use message::transmit;
+    use voice::crypto::CryptoControlFields;
+    use voice::frame_group::{FrameGroupEncoder, LinkControlExtra};
+    use voice::header::VoiceHeaderBuilder;
+    use voice::term;
+
+    fn nid(du: DataUnit) -> MessageEvent {
+        MessageEvent::PacketNID(NetworkId::new(NetworkAccessCode::Other(0x293), du))
+    }
+
+    fn frame(errs: usize) -> MessageEvent {
+        let mut vf = VoiceFrame::from_chunks([0; 8]);
+        vf.errors[0] = errs;
+        MessageEvent::VoiceFrame(vf)
+    }
+
+    fn group_lc() -> MessageEvent {
+        MessageEvent::LinkControl(LinkControlFields::new([
+            0x00, 0x00, 0x80, 0x00, 0x12, 0x34, 0xAB, 0xCD, 0xEF,
+        ]))
+    }
+
+    #[test]
+    fn test_call() {
+        let mut t = CallTracker::new();
+
+        assert!(t.feed(&nid(DataUnit::VoiceHeader)).is_none());
+        assert!(t.feed(&MessageEvent::VoiceHeader(VoiceHeaderBuilder::new(
+            [1, 2, 3, 4, 5, 6, 7, 8, 9], 0, CryptoAlgorithm::Unencrypted, 0,
+            TalkGroup::Other(0x1234)).build())).is_none());
+
+        assert!(t.in_call());
+
+        assert!(t.feed(&nid(DataUnit::VoiceLCFrameGroup)).is_none());
+        assert!(t.feed(&frame(0)).is_none());
+        assert!(t.feed(&group_lc()).is_none());
+        assert!(t.feed(&frame(1)).is_none());
+
+        for _ in 0..200 {
+            assert!(t.advance(0.001).is_none());
+        }
+
+        assert!(t.feed(&nid(DataUnit::VoiceCCFrameGroup)).is_none());
+        assert!(t.feed(&frame(0)).is_none());
+        assert!(t.feed(&MessageEvent::CryptoControl(CryptoControlFields::new([
+            9, 8, 7, 6, 5, 4, 3, 2, 1, 0x84, 0x12, 0x34,
+        ]))).is_none());
+        assert!(t.feed(&frame(0)).is_none());
+
+        for _ in 0..99 {
+            assert!(t.advance(0.001).is_none());
+        }
+
+        let s = t.feed(&nid(DataUnit::VoiceSimpleTerminator)).unwrap();
+
+        assert!(!t.in_call());
+        assert_eq!(s.nac, NetworkAccessCode::Other(0x293));
+        assert_eq!(s.talkgroup, Some(TalkGroup::Other(0x1234)));
+        assert_eq!(s.src_unit, Some(0xABCDEF));
+        assert_eq!(s.dest_unit, None);
+        assert!(s.emergency);
+        assert_eq!(s.crypto, Some(CryptoParams {
+            init: [9, 8, 7, 6, 5, 4, 3, 2, 1],
+            alg: CryptoAlgorithm::Aes,
+            key: 0x1234,
+        }));
+        assert!(s.encrypted());
+        assert_eq!(s.frames.len(), 4);
+        assert_eq!(s.errors(), 1);
+        assert_eq!(s.quality(), Some(0.75));
+        assert!((s.duration - 0.299).abs() < 1e-6);
+        assert_eq!(s.end, CallEnd::Terminator);
+    }
+
+    #[test]
+    fn test_late_entry() {
+        let mut t = CallTracker::new();
+
+        // Frames outside of a call are ignored.
+        assert!(t.feed(&frame(0)).is_none());
+        assert!(!t.in_call());
+
+        assert!(t.feed(&nid(DataUnit::VoiceCCFrameGroup)).is_none());
+        assert!(!t.in_call());
+
+        assert!(t.feed(&nid(DataUnit::VoiceLCFrameGroup)).is_none());
+        assert!(t.in_call());
+        assert!(t.feed(&frame(0)).is_none());
+
+        let s = t.feed(&MessageEvent::VoiceTerm(LinkControlFields::new([
+            0x03, 0x00, 0x00, 0x00, 0x00, 0x42, 0x00, 0x00, 0x17,
+        ]))).unwrap();
+
+        assert_eq!(s.talkgroup, None);
+        assert_eq!(s.src_unit, Some(0x17));
+        assert_eq!(s.dest_unit, Some(0x42));
+        assert!(!s.emergency);
+        assert_eq!(s.crypto, None);
+        assert!(!s.encrypted());
+        assert_eq!(s.frames.len(), 1);
+        assert_eq!(s.quality(), Some(1.0));
+        assert_eq!(s.end, CallEnd::Terminator);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut t = CallTracker::new();
+        t.set_timeout(0.5);
+
+        assert!(t.feed(&nid(DataUnit::VoiceLCFrameGroup)).is_none());
+        assert!(t.advance(0.25).is_none());
+        assert!(t.advance(0.25).is_none());
+
+        let s = t.advance(0.001).unwrap();
+        assert_eq!(s.end, CallEnd::Timeout);
+        assert_eq!(s.duration, 0.0);
+        assert_eq!(s.quality(), None);
+        assert!(!t.in_call());
+        assert!(t.advance(0.001).is_none());
+    }
+
+    #[test]
+    fn test_superseded() {
+        let mut t = CallTracker::new();
+
+        assert!(t.feed(&nid(DataUnit::VoiceHeader)).is_none());
+        assert!(t.feed(&nid(DataUnit::VoiceLCFrameGroup)).is_none());
+
+        let s = t.feed(&nid(DataUnit::VoiceHeader)).unwrap();
+        assert_eq!(s.end, CallEnd::Superseded);
+        assert!(t.in_call());
+    }
+
+    #[test]
+    fn test_complex_input() {
+        let nac = NetworkAccessCode::Other(0x123);
+        let lc = LinkControlFields::new([
+            0x00, 0x00, 0x80, 0x00, 0x12, 0x34, 0xAB, 0xCD, 0xEF,
+        ]);
+
+        // Lead in with random symbols so the receiver can settle before the call.
+        let mut seed = 0x1234u32;
+
+        let mut dibits: Vec<Dibit> = (0..2400).map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            Dibit::new((seed >> 16) as u8 & 0b11)
+        }).collect();
+
+        for _ in 0..4 {
+            dibits.extend(transmit::data_unit(nac, DataUnit::VoiceLCFrameGroup,
+                FrameGroupEncoder::<LinkControlExtra>::encode(&[[0; 8]; 9], &lc, 0)
+                    .iter().cloned(),
+                StatusCode::SubscriberRepeater));
+        }
+
+        dibits.extend(term::lc_terminator(nac, &lc, StatusCode::SubscriberRepeater));
+
+        // The front end resamples the second rate, so the receiver sees a different
+        // number of samples and events per input sample.
+        let durations: Vec<f32> = [48000, 100000].iter().map(|&rate| {
+            let mut recv = ComplexMessageReceiver::c4fm(rate);
+            let mut t = CallTracker::new();
+            let mut calls = vec![];
+            let mut phase = 0.0;
+
+            // Frequency modulate each symbol at its C4FM deviation, followed by 1.5
+            // seconds of carrier.
+            let symbols = dibits.iter().map(|d| match d.bits() {
+                0b01 => 1800.0,
+                0b00 => 600.0,
+                0b10 => -600.0,
+                _ => -1800.0,
+            }).chain(std::iter::repeat(0.0).take(7200));
+
+            for (n, dev) in symbols.enumerate() {
+                let samples = (n + 1) * rate / 4800 - n * rate / 4800;
+
+                for _ in 0..samples {
+                    phase += 2.0 * std::f32::consts::PI * dev / rate as f32;
+
+                    recv.feed(Complex::from_phase(phase), |e| {
+                        calls.extend(t.feed(&e));
+                    });
+
+                    calls.extend(t.advance(1.0 / rate as f64));
+                }
+            }
+
+            assert_eq!(calls.len(), 1);
+
+            let s = &calls[0];
+
+            assert_eq!(s.nac, nac);
+            assert_eq!(s.talkgroup, Some(TalkGroup::Other(0x1234)));
+            assert_eq!(s.src_unit, Some(0xABCDEF));
+            assert_eq!(s.frames.len(), 36);
+            assert_eq!(s.end, CallEnd::Terminator);
+
+            s.duration
+        }).collect();
+
+        // The call runs from the first NID to the end of the terminator.
+        let expected = (4.0 * 864.0 + 216.0 - 57.0) / 4800.0;
+
+        for &d in durations.iter() {
+            assert!((d - expected).abs() < 0.01);
+        }
+
+        assert!((durations[0] - durations[1]).abs() < 0.002);
+    }
+}
@@ -3,7 +3,7 @@
 use consts::LINK_CONTROL_BYTES;
 use util::{slice_u16, slice_u24};
 
-use trunking::fields::{TalkGroup, ServiceOptions};
+use trunking::fields::{self, Channel, TalkGroup, ServiceOptions, SystemServices};
 
 /// Buffer of bytes that represents a link control packet.
 pub type Buf = [u8; LINK_CONTROL_BYTES];
@@ -90,9 +90,23 @@ impl LinkControlFields {
     /// Interpret the given bytes as a link control packet.
     pub fn new(buf: Buf) -> Self { LinkControlFields(buf) }
 
+    /// Bytes that make up the packet.
+    pub fn bytes(&self) -> &Buf { &self.0 }
+
     /// Whether the packet is encrypted.
     pub fn protected(&self) -> bool { self.0[0] >> 7 == 1 }
 
+    /// Manufacturer ID, which determines if the packet is standardized. Packets with the
+    /// implicit format flag set are always standard, and the following byte is part of
+    /// the payload rather than the manufacturer ID.
+    pub fn mfg(&self) -> u8 {
+        if self.0[0] >> 6 & 1 == 1 {
+            fields::MFG_STANDARD
+        } else {
+            self.0[1]
+        }
+    }
+
     /// Type of data contained in the payload.
     pub fn opcode(&self) -> Option<LinkControlOpcode> {
         LinkControlOpcode::from_bits(self.0[0] & 0x3F)
@@ -102,6 +116,63 @@ impl LinkControlFields {
     pub fn payload(&self) -> &[u8] { &self.0[1..=8] }
 }
 
+impl fields::VendorPacket for LinkControlFields {
+    type Opcode = LinkControlOpcode;
+
+    fn mfg(&self) -> u8 { LinkControlFields::mfg(self) }
+    fn opcode_bits(&self) -> u8 { self.0[0] & 0x3F }
+    fn standard_opcode(bits: u8) -> Option<LinkControlOpcode> {
+        LinkControlOpcode::from_bits(bits)
+    }
+}
+
+/// Interpretation of a link control packet based on its manufacturer ID.
+pub type LinkControlKind<P> = fields::PacketKind<LinkControlOpcode, P>;
+
+/// Registry of decoders for manufacturer-specific link control packets.
+pub type LinkControlRegistry<P> = fields::VendorRegistry<LinkControlFields, P>;
+
+/// Manufacturer-specific link control payloads supported by the built-in decoders.
+#[derive(Debug)]
+pub enum VendorLinkControl {
+    /// Fragment of the talker alias of the transmitting Harris unit.
+    HarrisTalkerAlias(HarrisFragment),
+    /// Fragment of the GPS position of the transmitting Harris unit.
+    HarrisGps(HarrisFragment),
+}
+
+impl fields::VendorRegistry<LinkControlFields, VendorLinkControl> {
+    /// Create a new `LinkControlRegistry` with all built-in manufacturer decoders
+    /// registered.
+    pub fn builtin() -> Self {
+        let mut reg = Self::new();
+        reg.register(fields::MFG_HARRIS, decode_harris);
+        reg
+    }
+}
+
+/// Decode a Harris manufacturer-specific packet.
+pub fn decode_harris(lc: &LinkControlFields) -> Option<VendorLinkControl> {
+    match lc.0[0] & 0x3F {
+        op @ 0x32..=0x35 =>
+            Some(VendorLinkControl::HarrisTalkerAlias(HarrisFragment(lc.0, op - 0x32))),
+        op @ 0x2A..=0x2B =>
+            Some(VendorLinkControl::HarrisGps(HarrisFragment(lc.0, op - 0x2A))),
+        _ => None,
+    }
+}
+
+/// One block of a Harris value that's split over several consecutive packets.
+#[derive(Debug)]
+pub struct HarrisFragment(Buf, u8);
+
+impl HarrisFragment {
+    /// Index of the block within the full value, starting at 0.
+    pub fn block(&self) -> usize { self.1 as usize }
+    /// Bytes of the value carried in this block.
+    pub fn data(&self) -> &[u8] { &self.0[2..=8] }
+}
+
 /// Identity of unit transmitting on the current talkgroup traffic channel.
 pub struct GroupVoiceTraffic(Buf);
 
@@ -152,10 +223,139 @@ impl PhoneTraffic {
     pub fn unit(&self) -> u32 { slice_u24(&self.0[6..=8]) }
 }
 
+/// Termination or cancellation of a call.
+pub struct CallTermination(Buf);
+
+impl CallTermination {
+    /// Create a new `CallTermination` decoder from the base LC decoder.
+    pub fn new(lc: LinkControlFields) -> Self { CallTermination(lc.0) }
+
+    /// Unit whose call was ended.
+    pub fn unit(&self) -> u32 { slice_u24(&self.0[6..=8]) }
+}
+
+/// Updates subscribers about an ongoing talkgroup conversation, with explicit transmit
+/// and receive channels.
+pub struct GroupVoiceUpdateExplicit(Buf);
+
+impl GroupVoiceUpdateExplicit {
+    /// Create a new `GroupVoiceUpdateExplicit` decoder from the base LC decoder.
+    pub fn new(lc: LinkControlFields) -> Self { GroupVoiceUpdateExplicit(lc.0) }
+
+    /// Options granted for the traffic channel.
+    pub fn opts(&self) -> ServiceOptions { ServiceOptions::new(self.0[2]) }
+    /// Talkgroup of the conversation.
+    pub fn talkgroup(&self) -> TalkGroup { TalkGroup::new(&self.0[3..=4]) }
+    /// Channel the subscriber receives on, i.e. the outbound CHANNEL(T).
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0[5..=6]) }
+    /// Channel the subscriber transmits on, i.e. the inbound CHANNEL(R).
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0[7..=8]) }
+}
+
+/// Command for a target unit to register with the given system.
+///
+/// Note that this can be used for both `UnitRegistrationRequest` and
+/// `UnitAuthenticationRequst`.
+pub struct UnitRegistrationRequest(Buf);
+
+impl UnitRegistrationRequest {
+    /// Create a new `UnitRegistrationRequest` decoder from the base LC decoder.
+    pub fn new(lc: LinkControlFields) -> Self { UnitRegistrationRequest(lc.0) }
+
+    /// WACN ID of the system.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0[2..=4]) >> 4 }
+    /// System ID within the WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[4..=5]) & 0xFFF }
+    /// Target unit.
+    pub fn dest_unit(&self) -> u32 { slice_u24(&self.0[6..=8]) }
+}
+
+/// Advertisement of an alternative control channel for the current site, with explicit
+/// transmit and receive channels.
+pub struct AltControlChannelExplicit(Buf);
+
+impl AltControlChannelExplicit {
+    /// Create a new `AltControlChannelExplicit` decoder from the base LC decoder.
+    pub fn new(lc: LinkControlFields) -> Self { AltControlChannelExplicit(lc.0) }
+
+    /// RF Subsystem ID of current site within System.
+    pub fn rfss(&self) -> u8 { self.0[1] }
+    /// Site ID of current site within RFSS.
+    pub fn site(&self) -> u8 { self.0[2] }
+    /// Channel the control channel transmits on.
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0[3..=4]) }
+    /// Channel the control channel receives on.
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0[5..=6]) }
+    /// Services supported by the control channel.
+    pub fn services(&self) -> SystemServices { SystemServices::new(self.0[7]) }
+}
+
+/// Advertisement of an adjacent site, with explicit transmit and receive channels.
+pub struct AdjacentSiteExplicit(Buf);
+
+impl AdjacentSiteExplicit {
+    /// Create a new `AdjacentSiteExplicit` decoder from the base LC decoder.
+    pub fn new(lc: LinkControlFields) -> Self { AdjacentSiteExplicit(lc.0) }
+
+    /// Location registration area of adjacent site.
+    pub fn area(&self) -> u8 { self.0[1] }
+    /// Channel the adjacent site transmits on.
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0[2..=3]) }
+    /// RF Subsystem ID of adjacent site within the System.
+    pub fn rfss(&self) -> u8 { self.0[4] }
+    /// Site ID of adjacent site within the RFSS.
+    pub fn site(&self) -> u8 { self.0[5] }
+    /// Channel the adjacent site receives on.
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0[6..=7]) }
+    /// Services supported by the adjacent site.
+    pub fn services(&self) -> SystemServices { SystemServices::new(self.0[8]) }
+}
+
+/// Site and RFSS information of current control channel, with explicit transmit and
+/// receive channels.
+pub struct RfssStatusExplicit(Buf);
+
+impl RfssStatusExplicit {
+    /// Create a new `RfssStatusExplicit` decoder from the base LC decoder.
+    pub fn new(lc: LinkControlFields) -> Self { RfssStatusExplicit(lc.0) }
+
+    /// Location registration area of current site.
+    pub fn area(&self) -> u8 { self.0[1] }
+    /// RF Subsystem ID of current site within System.
+    pub fn rfss(&self) -> u8 { self.0[2] }
+    /// Site ID of current site within RFSS.
+    pub fn site(&self) -> u8 { self.0[3] }
+    /// Channel the control channel transmits on.
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0[4..=5]) }
+    /// Channel the control channel receives on.
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0[6..=7]) }
+    /// Services supported by the current site.
+    pub fn services(&self) -> SystemServices { SystemServices::new(self.0[8]) }
+}
+
+/// WACN and System ID information of current control channel, with explicit transmit
+/// and receive channels.
+pub struct NetworkStatusExplicit(Buf);
+
+impl NetworkStatusExplicit {
+    /// Create a new `NetworkStatusExplicit` decoder from the base LC decoder.
+    pub fn new(lc: LinkControlFields) -> Self { NetworkStatusExplicit(lc.0) }
+
+    /// WACN ID within the communications network.
+    pub fn wacn(&self) -> u32 { slice_u24(&self.0[1..=3]) >> 4 }
+    /// System ID of site within WACN.
+    pub fn system(&self) -> u16 { slice_u16(&self.0[3..=4]) & 0xFFF }
+    /// Channel the control channel transmits on.
+    pub fn tx_channel(&self) -> Channel { Channel::new(&self.0[5..=6]) }
+    /// Channel the control channel receives on.
+    pub fn rx_channel(&self) -> Channel { Channel::new(&self.0[7..=8]) }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use trunking::fields::*;
+    use voice::crypto::CryptoAlgorithm;
 
     #[test]
     fn test_lc() {
@@ -474,4 +674,183 @@ mod test {
         assert_eq!(p.call_timer(), 0b1000000000000010);
         assert_eq!(p.unit(), 0b111100000011001111100010);
     }
+
+    #[test]
+    fn test_call_termination() {
+        let l = LinkControlFields::new([
+            0b00001111, 0, 0, 0, 0, 0, 0x12, 0x34, 0x56,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::CallTermination));
+        assert_eq!(CallTermination::new(l).unit(), 0x123456);
+    }
+
+    #[test]
+    fn test_group_voice_update_explicit() {
+        let l = LinkControlFields::new([
+            0b00000100,
+            0b00000000,
+            0b10000101,
+            0x12, 0x34,
+            0b00010000, 0b00000011,
+            0b00100000, 0b00000100,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::GroupVoiceUpdateExplicit));
+        let u = GroupVoiceUpdateExplicit::new(l);
+        assert!(u.opts().emergency());
+        assert_eq!(u.opts().prio(), 5);
+        assert_eq!(u.talkgroup(), TalkGroup::Other(0x1234));
+        assert_eq!(u.rx_channel().id(), 1);
+        assert_eq!(u.rx_channel().number(), 3);
+        assert_eq!(u.tx_channel().id(), 2);
+        assert_eq!(u.tx_channel().number(), 4);
+    }
+
+    #[test]
+    fn test_protection_params() {
+        let l = LinkControlFields::new([
+            0b00100101, 0x00, 0x00, 0x84, 0x12, 0x34, 0x00, 0x00, 0x2A,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::ProtectionParamBroadcast));
+        let p = ProtectionParams::new(l.payload());
+        assert_eq!(p.alg(), CryptoAlgorithm::Aes);
+        assert_eq!(p.key(), 0x1234);
+        assert_eq!(p.dest_unit(), 0x2A);
+    }
+
+    #[test]
+    fn test_unit_registration_request() {
+        let l = LinkControlFields::new([
+            0b00010010, 0x00, 0xBE, 0xE0, 0x01, 0x23, 0xAB, 0xCD, 0xEF,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::UnitAuthenticationRequst));
+        let r = UnitRegistrationRequest::new(l);
+        assert_eq!(r.wacn(), 0xBEE00);
+        assert_eq!(r.system(), 0x123);
+        assert_eq!(r.dest_unit(), 0xABCDEF);
+    }
+
+    #[test]
+    fn test_unit_status() {
+        let l = LinkControlFields::new([
+            0b00010100, 0x12, 0x34, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::UnitStatusUpdate));
+        let u = UnitStatusUpdate::new(l.payload());
+        assert_eq!(u.user_status(), 0x12);
+        assert_eq!(u.unit_status(), 0x34);
+        assert_eq!(u.dest_unit(), 0xABCDEF);
+        assert_eq!(u.src_unit(), 0x012345);
+
+        let l = LinkControlFields::new([
+            0b00010011, 0, 0, 0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::UnitStatusRequest));
+        let q = UnitStatusQuery::new(l.payload());
+        assert_eq!(q.dest_unit(), 0xABCDEF);
+        assert_eq!(q.src_unit(), 0x012345);
+    }
+
+    #[test]
+    fn test_explicit_broadcasts() {
+        let l = LinkControlFields::new([
+            0b00100110, 0x01, 0x02, 0x10, 0x05, 0x10, 0x06, 0b00010001, 0,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::AltControlChannelExplicit));
+        let a = AltControlChannelExplicit::new(l);
+        assert_eq!(a.rfss(), 1);
+        assert_eq!(a.site(), 2);
+        assert_eq!(a.tx_channel().number(), 5);
+        assert_eq!(a.rx_channel().number(), 6);
+        assert!(a.services().is_composite());
+        assert!(a.services().has_data());
+
+        let l = LinkControlFields::new([
+            0b00100111, 0x07, 0x10, 0x05, 0x01, 0x02, 0x10, 0x06, 0b00100000,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::AdjacentSiteExplicit));
+        let a = AdjacentSiteExplicit::new(l);
+        assert_eq!(a.area(), 7);
+        assert_eq!(a.tx_channel().number(), 5);
+        assert_eq!(a.rfss(), 1);
+        assert_eq!(a.site(), 2);
+        assert_eq!(a.rx_channel().number(), 6);
+        assert!(a.services().has_voice());
+
+        let l = LinkControlFields::new([
+            0b00101000, 0x07, 0x01, 0x02, 0x10, 0x05, 0x10, 0x06, 0b00100000,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::RfssStatusExplicit));
+        let r = RfssStatusExplicit::new(l);
+        assert_eq!(r.area(), 7);
+        assert_eq!(r.rfss(), 1);
+        assert_eq!(r.site(), 2);
+        assert_eq!(r.tx_channel().number(), 5);
+        assert_eq!(r.rx_channel().number(), 6);
+        assert!(r.services().has_voice());
+
+        let l = LinkControlFields::new([
+            0b00101001, 0xBE, 0xE0, 0x01, 0x23, 0x10, 0x05, 0x10, 0x06,
+        ]);
+        assert_eq!(l.opcode(), Some(LinkControlOpcode::NetworkStatusExplicit));
+        let n = NetworkStatusExplicit::new(l);
+        assert_eq!(n.wacn(), 0xBEE00);
+        assert_eq!(n.system(), 0x123);
+        assert_eq!(n.tx_channel().id(), 1);
+        assert_eq!(n.tx_channel().number(), 5);
+        assert_eq!(n.rx_channel().number(), 6);
+    }
+
+    #[test]
+    fn test_registry() {
+        let reg = LinkControlRegistry::builtin();
+
+        let lc = LinkControlFields::new([0x00, 0x00, 0, 0, 0, 1, 0, 0, 1]);
+        assert_eq!(lc.mfg(), fields::MFG_STANDARD);
+        match reg.decode(&lc) {
+            LinkControlKind::Standard(LinkControlOpcode::GroupVoiceTraffic) => {},
+            _ => panic!(),
+        }
+
+        // Implicit format, where the second byte isn't a manufacturer ID.
+        let lc = LinkControlFields::new([0x62, 0xA4, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(lc.mfg(), fields::MFG_STANDARD);
+        match reg.decode(&lc) {
+            LinkControlKind::Standard(LinkControlOpcode::AdjacentSite) => {},
+            _ => panic!(),
+        }
+
+        let lc = LinkControlFields::new([
+            0x33, 0xA4, b'E', b'N', b'G', b'I', b'N', b'E', b' ',
+        ]);
+        assert_eq!(lc.mfg(), fields::MFG_HARRIS);
+        match reg.decode(&lc) {
+            LinkControlKind::Vendor(VendorLinkControl::HarrisTalkerAlias(f)) => {
+                assert_eq!(f.block(), 1);
+                assert_eq!(f.data(), b"ENGINE ");
+            },
+            _ => panic!(),
+        }
+
+        let lc = LinkControlFields::new([0x2B, 0xA4, 1, 2, 3, 4, 5, 6, 7]);
+        match reg.decode(&lc) {
+            LinkControlKind::Vendor(VendorLinkControl::HarrisGps(f)) => {
+                assert_eq!(f.block(), 1);
+                assert_eq!(f.data(), &[1, 2, 3, 4, 5, 6, 7]);
+            },
+            _ => panic!(),
+        }
+
+        // Standard opcodes aren't misinterpreted for other manufacturers.
+        let lc = LinkControlFields::new([0x00, 0x90, 0, 0, 0, 1, 0, 0, 1]);
+        match reg.decode(&lc) {
+            LinkControlKind::Unknown { mfg: fields::MFG_MOTOROLA, opcode: 0x00 } => {},
+            _ => panic!(),
+        }
+
+        let lc = LinkControlFields::new([0x00, 0xA4, 0, 0, 0, 1, 0, 0, 1]);
+        match reg.decode(&lc) {
+            LinkControlKind::Unknown { mfg: fields::MFG_HARRIS, opcode: 0x00 } => {},
+            _ => panic!(),
+        }
+    }
 }
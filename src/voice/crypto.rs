@@ -13,6 +13,9 @@ impl CryptoControlFields {
     /// Create a new `CryptoControlFields` decoder from the given bytes.
     pub fn new(buf: Buf) -> Self { CryptoControlFields(buf) }
 
+    /// Bytes that make up the packet.
+    pub fn bytes(&self) -> &Buf { &self.0 }
+
     /// Initialization vector used internally by associated crypto algorithm.
     pub fn init(&self) -> &[u8] { &self.0[..9] }
     /// Type of crypto algorithm in use, if any.
@@ -57,6 +60,25 @@ impl CryptoAlgorithm {
             b => Other(b),
         }
     }
+
+    /// Convert the crypto algorithm to its 8-bit representation.
+    pub fn to_bits(self) -> u8 {
+        use self::CryptoAlgorithm::*;
+
+        match self {
+            Accordion => 0x00,
+            BatonEven => 0x01,
+            Firefly => 0x02,
+            Mayfly => 0x03,
+            Saville => 0x04,
+            BatonOdd => 0x41,
+            Unencrypted => 0x80,
+            Des => 0x81,
+            TripleDes => 0x83,
+            Aes => 0x84,
+            Other(b) => b,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -76,4 +98,11 @@ mod test {
         assert_eq!(c.alg(), Aes);
         assert_eq!(c.key(), 0xDEAD);
     }
+
+    #[test]
+    fn test_alg_bits() {
+        for b in 0..=255 {
+            assert_eq!(CryptoAlgorithm::from_bits(b).to_bits(), b);
+        }
+    }
 }
@@ -1,4 +1,5 @@
-//! Descramble/Deinterleave the dibits in a received voice frame.
+//! Descramble/Deinterleave the dibits in a received voice frame, and scramble/interleave
+//! the dibits of a transmitted one.
 
 use bits::Dibit;
 use consts;
@@ -9,6 +10,12 @@ pub fn descramble(dibits: &[Dibit; consts::FRAME_DIBITS], idx: usize) -> u32 {
     DESCRAMBLERS[idx].descramble(dibits)
 }
 
+/// Scramble the given PN-scrambled, coded chunk `u_{idx}` into its portion of the given
+/// voice frame dibits.
+pub fn scramble(dibits: &mut [Dibit; consts::FRAME_DIBITS], idx: usize, chunk: u32) {
+    DESCRAMBLERS[idx].scramble(dibits, chunk)
+}
+
 /// Set of descramblers for each associated chunk `u_0`, ..., `u_7`.
 const DESCRAMBLERS: [VoiceFrameDescrambler; 8] = [
     VoiceFrameDescrambler(&[
@@ -59,6 +66,22 @@ impl VoiceFrameDescrambler {
             })
         })
     }
+
+    /// Scramble the given chunk into the dibits, the inverse of `descramble`.
+    pub fn scramble(&self, dibits: &mut [Dibit; consts::FRAME_DIBITS], chunk: u32) {
+        let len = self.0.iter().fold(0, |len, zz| len + zz.remain);
+
+        for (i, (idx, hi)) in self.0.iter().flat_map(|&zz| zz).enumerate() {
+            let bit = (chunk >> (len - i - 1)) as u8 & 1;
+            let bits = dibits[idx].bits();
+
+            dibits[idx] = Dibit::new(if hi {
+                bits & 0b01 | bit << 1
+            } else {
+                bits & 0b10 | bit
+            });
+        }
+    }
 }
 
 /// Walks the zigzagging interleave schedule used for voice frames.
@@ -117,9 +140,28 @@ impl Iterator for ZigZag {
 
 #[cfg(test)]
 mod test {
+    use super::*;
     use super::DESCRAMBLERS;
     use consts;
 
+    #[test]
+    fn test_scramble() {
+        let chunks = [
+            0x5A5A5A, 0x123456, 0x7FFFFF, 0x000001,
+            0x5555, 0x2AAA, 0x1234, 0x55,
+        ];
+
+        let mut dibits = [Dibit::default(); consts::FRAME_DIBITS];
+
+        for (idx, &chunk) in chunks.iter().enumerate() {
+            scramble(&mut dibits, idx, chunk);
+        }
+
+        for (idx, &chunk) in chunks.iter().enumerate() {
+            assert_eq!(descramble(&dibits, idx), chunk);
+        }
+    }
+
     #[test]
     fn test_steps_exhaustive() {
         let mut visited = [0u32; consts::FRAME_DIBITS];
@@ -1,16 +1,18 @@
-//! Decode a voice frame into chunks suitable for IMBE.
+//! Decode a voice frame into chunks suitable for IMBE, and encode chunks into a voice
+//! frame.
 
 use bits::Dibit;
 use coding::{golay, hamming};
 use consts;
 use error::Result;
 
-use voice::descramble::descramble;
+use voice::descramble::{descramble, scramble};
 use voice::rand;
 
 use error::P25Error::*;
 
 /// IMBE-encoded voice frame.
+#[derive(Copy, Clone)]
 pub struct VoiceFrame {
     /// Chunks of IMBE-prioritized data, Known as `u_0`, ..., `u_7` in the standard.
     pub chunks: [u32; 8],
@@ -70,4 +72,64 @@ impl VoiceFrame {
             errors: errors,
         })
     }
+
+    /// Create a `VoiceFrame` with the given chunks `u_0`, ..., `u_7` for transmission.
+    pub fn from_chunks(chunks: [u32; 8]) -> VoiceFrame {
+        VoiceFrame {
+            chunks: chunks,
+            errors: [0; 7],
+        }
+    }
+
+    /// Code, PN-scramble, and interleave the chunks into the dibits carried over the air.
+    pub fn encode(&self) -> [Dibit; consts::FRAME_DIBITS] {
+        let c = &self.chunks;
+
+        assert!(c[..4].iter().all(|&x| x >> 12 == 0));
+        assert!(c[4..7].iter().all(|&x| x >> 11 == 0));
+        assert!(c[7] >> 7 == 0);
+
+        let mut dibits = [Dibit::default(); consts::FRAME_DIBITS];
+
+        // The PN sequence is seeded with u_0, which is itself left unscrambled.
+        let mut prand = rand::PseudoRand::new(c[0] as u16);
+
+        scramble(&mut dibits, 0, golay::standard::encode(c[0] as u16));
+
+        for idx in 1..=3 {
+            scramble(&mut dibits, idx,
+                     golay::standard::encode(c[idx] as u16) ^ prand.next_23());
+        }
+
+        for idx in 4..=6 {
+            scramble(&mut dibits, idx,
+                     hamming::standard::encode(c[idx] as u16) as u32 ^ prand.next_15());
+        }
+
+        scramble(&mut dibits, 7, c[7]);
+
+        dibits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let chunks = [0xABC, 0x123, 0xFFF, 0x000, 0x7FF, 0x2AA, 0x555, 0x5A];
+        let mut dibits = VoiceFrame::from_chunks(chunks).encode();
+
+        let vf = VoiceFrame::new(&dibits).unwrap();
+        assert_eq!(vf.chunks, chunks);
+        assert_eq!(vf.errors, [0; 7]);
+
+        // Flip a bit in u_0, which must be corrected before the rest can be descrambled.
+        dibits[0] = Dibit::new(dibits[0].bits() ^ 0b10);
+
+        let vf = VoiceFrame::new(&dibits).unwrap();
+        assert_eq!(vf.chunks, chunks);
+        assert_eq!(vf.errors, [1, 0, 0, 0, 0, 0, 0]);
+    }
 }
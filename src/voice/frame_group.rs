@@ -1,4 +1,4 @@
-//! Receive voice frame groups, known as LDU1 and LDU2 in the standard.
+//! Receive and encode voice frame groups, known as LDU1 and LDU2 in the standard.
 //!
 //! Each frame group contains 9 voice frames, a low-speed data word, and an "extra"
 //! packet: either link control (LC) or crypto control (CC).
@@ -7,7 +7,7 @@ use std;
 
 use collect_slice::CollectSlice;
 
-use bits::{Hexbit, HexbitBytes, Dibit};
+use bits::{Hexbit, HexbitBytes, Hexbits, Dibit, WordDibits};
 use coding::{cyclic, hamming, reed_solomon};
 use error::{P25Error, Result};
 use stats::{Stats, HasStats};
@@ -24,8 +24,11 @@ use buffer::{
 
 use consts::{
     CRYPTO_CONTROL_BYTES,
+    DATA_FRAG_DIBITS,
     EXTRA_HEXBITS,
     EXTRA_PIECE_DIBITS,
+    EXTRA_WORD_DIBITS,
+    FRAME_GROUP_DIBITS,
     LINK_CONTROL_BYTES,
 };
 
@@ -38,6 +41,11 @@ pub type VoiceLCFrameGroupReceiver = FrameGroupReceiver<LinkControlExtra>;
 /// Receiver for Crypto Control (CC) frame group.
 pub type VoiceCCFrameGroupReceiver = FrameGroupReceiver<CryptoControlExtra>;
 
+/// Encoder for Link Control (LC) frame group.
+pub type VoiceLCFrameGroupEncoder = FrameGroupEncoder<LinkControlExtra>;
+/// Encoder for Crypto Control (CC) frame group.
+pub type VoiceCCFrameGroupEncoder = FrameGroupEncoder<CryptoControlExtra>;
+
 /// Internal state of the frame group receiver.
 enum State {
     /// Decoding a voice frame.
@@ -192,6 +200,8 @@ pub trait Extra {
         -> Result<&'a [Hexbit]>;
     /// Transform the given hexbits into a base packet decoder.
     fn decode_extra(buf: &[Hexbit]) -> Self::Fields;
+    /// Transform the given packet into hexbits and apply the outer Reed Solomon code.
+    fn encode_extra(fields: &Self::Fields) -> [Hexbit; EXTRA_HEXBITS];
 }
 
 /// Link control frame group extra.
@@ -215,6 +225,16 @@ impl Extra for LinkControlExtra {
 
         control::LinkControlFields::new(bytes)
     }
+
+    fn encode_extra(fields: &Self::Fields) -> [Hexbit; EXTRA_HEXBITS] {
+        let mut buf = [Hexbit::default(); EXTRA_HEXBITS];
+        Hexbits::new(fields.bytes().iter().cloned())
+            .collect_slice_checked(&mut buf[..12]);
+
+        reed_solomon::short::encode(&mut buf);
+
+        buf
+    }
 }
 
 /// Crypto control frame group extra.
@@ -238,6 +258,56 @@ impl Extra for CryptoControlExtra {
 
         crypto::CryptoControlFields::new(bytes)
     }
+
+    fn encode_extra(fields: &Self::Fields) -> [Hexbit; EXTRA_HEXBITS] {
+        let mut buf = [Hexbit::default(); EXTRA_HEXBITS];
+        Hexbits::new(fields.bytes().iter().cloned())
+            .collect_slice_checked(&mut buf[..16]);
+
+        reed_solomon::medium::encode(&mut buf);
+
+        buf
+    }
+}
+
+/// Codes the pieces of a frame group into the dibits carried over the air.
+pub struct FrameGroupEncoder<E: Extra>(std::marker::PhantomData<E>);
+
+impl<E: Extra> FrameGroupEncoder<E> {
+    /// Encode a frame group from the given chunks `u_0`, ..., `u_7` of each of the 9
+    /// voice frames, the given extra packet, and the given 16-bit low-speed data
+    /// fragment.
+    pub fn encode(frames: &[[u32; 8]; 9], extra: &E::Fields, data: u16)
+        -> [Dibit; FRAME_GROUP_DIBITS]
+    {
+        let extra: Vec<Dibit> = E::encode_extra(extra).iter()
+            .flat_map(|h| WordDibits::new(hamming::shortened::encode(h.bits()) as u64,
+                                          EXTRA_WORD_DIBITS))
+            .collect();
+
+        let mut dibits = vec![];
+
+        for (idx, &chunks) in frames.iter().enumerate() {
+            dibits.extend_from_slice(&VoiceFrame::from_chunks(chunks).encode()[..]);
+
+            match idx {
+                1..=6 => {
+                    let start = (idx - 1) * EXTRA_PIECE_DIBITS;
+                    dibits.extend_from_slice(&extra[start..start + EXTRA_PIECE_DIBITS]);
+                },
+                7 => for &byte in &[(data >> 8) as u8, data as u8] {
+                    dibits.extend(WordDibits::new(cyclic::encode(byte) as u64,
+                                                  DATA_FRAG_DIBITS));
+                },
+                _ => {},
+            }
+        }
+
+        let mut buf = [Dibit::default(); FRAME_GROUP_DIBITS];
+        dibits.into_iter().collect_slice_checked(&mut buf[..]);
+
+        buf
+    }
 }
 
 /// Receives and decodes an IMBE voice frame.
@@ -399,3 +469,78 @@ impl DataFragmentReceiver {
 impl HasStats for DataFragmentReceiver {
     fn stats(&mut self) -> &mut Stats { &mut self.stats }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frames() -> [[u32; 8]; 9] {
+        let mut frames = [[0; 8]; 9];
+
+        for (i, f) in frames.iter_mut().enumerate() {
+            let i = i as u32;
+            *f = [0x100 + i, 0xABC, 0x800 | i, 0x123, 0x7FF - i, 0x2AA, 0x555, 0x40 | i];
+        }
+
+        frames
+    }
+
+    #[test]
+    fn test_lc_frame_group() {
+        let lc = control::LinkControlFields::new([0x00, 0x00, 0x04, 0x12, 0x34, 0x56,
+                                                  0x78, 0x9A, 0xBC]);
+        let mut dibits = VoiceLCFrameGroupEncoder::encode(&frames(), &lc, 0xBEEF);
+
+        // Corrupt some of the coded words.
+        for i in (0..dibits.len()).filter(|i| i % 100 == 25) {
+            dibits[i] = Dibit::new(dibits[i].bits() ^ 0b01);
+        }
+
+        let mut recv = VoiceLCFrameGroupReceiver::new();
+        let mut voice = vec![];
+        let mut extra = None;
+        let mut data = None;
+
+        for &d in dibits.iter() {
+            match recv.feed(d) {
+                Some(Ok(FrameGroupEvent::VoiceFrame(vf))) => voice.push(vf.chunks),
+                Some(Ok(FrameGroupEvent::Extra(lc))) => extra = Some(*lc.bytes()),
+                Some(Ok(FrameGroupEvent::DataFragment(d))) => data = Some(d),
+                Some(Err(e)) => panic!("{:?}", e),
+                None => {},
+            }
+        }
+
+        assert!(recv.done());
+        assert_eq!(&voice[..], &frames()[..]);
+        assert_eq!(extra, Some(*lc.bytes()));
+        assert_eq!(data, Some(0xBEEF));
+    }
+
+    #[test]
+    fn test_cc_frame_group() {
+        let cc = crypto::CryptoControlFields::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 0x84,
+                                                   0xDE, 0xAD]);
+        let dibits = VoiceCCFrameGroupEncoder::encode(&frames(), &cc, 0x1234);
+
+        let mut recv = VoiceCCFrameGroupReceiver::new();
+        let mut voice = 0;
+        let mut extra = None;
+        let mut data = None;
+
+        for &d in dibits.iter() {
+            match recv.feed(d) {
+                Some(Ok(FrameGroupEvent::VoiceFrame(_))) => voice += 1,
+                Some(Ok(FrameGroupEvent::Extra(cc))) => extra = Some(*cc.bytes()),
+                Some(Ok(FrameGroupEvent::DataFragment(d))) => data = Some(d),
+                Some(Err(e)) => panic!("{:?}", e),
+                None => {},
+            }
+        }
+
+        assert!(recv.done());
+        assert_eq!(voice, 9);
+        assert_eq!(extra, Some(*cc.bytes()));
+        assert_eq!(data, Some(0x1234));
+    }
+}
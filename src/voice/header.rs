@@ -1,11 +1,12 @@
-//! Receive and decode voice header packets.
+//! Receive and decode voice header packets, and build voice header packets for
+//! transmission.
 
 use collect_slice::CollectSlice;
 
-use bits::{Dibit, Hexbit, HexbitBytes};
+use bits::{Dibit, Hexbit, HexbitBytes, Hexbits, WordDibits};
 use buffer::{Buffer, VoiceHeaderWordStorage, VoiceHeaderStorage};
 use coding::{reed_solomon, golay};
-use consts::HEADER_BYTES;
+use consts::{HEADER_BYTES, HEADER_HEXBITS, HEADER_WORD_DIBITS};
 use error::Result;
 use stats::{Stats, HasStats};
 use trunking::fields::TalkGroup;
@@ -100,6 +101,51 @@ impl VoiceHeaderFields {
     pub fn talk_group(&self) -> TalkGroup {
         TalkGroup::from_bits(slice_u16(&self.0[13..]))
     }
+
+    /// Code the packet into the dibits carried over the air.
+    pub fn encode(&self) -> [Dibit; HEADER_HEXBITS * HEADER_WORD_DIBITS] {
+        let mut hexbits = [Hexbit::default(); HEADER_HEXBITS];
+        Hexbits::new(self.0.iter().cloned()).collect_slice_checked(&mut hexbits[..20]);
+
+        reed_solomon::long::encode(&mut hexbits);
+
+        let mut dibits = [Dibit::default(); HEADER_HEXBITS * HEADER_WORD_DIBITS];
+
+        hexbits.iter()
+            .flat_map(|h| WordDibits::new(golay::shortened::encode(h.bits()) as u64,
+                                          HEADER_WORD_DIBITS))
+            .collect_slice_checked(&mut dibits[..]);
+
+        dibits
+    }
+}
+
+/// Builds a voice header packet from its fields.
+pub struct VoiceHeaderBuilder(Buf);
+
+impl VoiceHeaderBuilder {
+    /// Create a new `VoiceHeaderBuilder` with the given crypto initialization vector
+    /// (message indicator), manufacturer ID, crypto algorithm, key ID, and talkgroup.
+    pub fn new(crypto_init: [u8; 9], mfg: u8, alg: CryptoAlgorithm, key: u16,
+               tg: TalkGroup)
+        -> VoiceHeaderBuilder
+    {
+        let mut buf = [0; HEADER_BYTES];
+        let tg = tg.to_bits();
+
+        buf[..9].copy_from_slice(&crypto_init[..]);
+        buf[9] = mfg;
+        buf[10] = alg.to_bits();
+        buf[11] = (key >> 8) as u8;
+        buf[12] = key as u8;
+        buf[13] = (tg >> 8) as u8;
+        buf[14] = tg as u8;
+
+        VoiceHeaderBuilder(buf)
+    }
+
+    /// Return the finished packet.
+    pub fn build(self) -> VoiceHeaderFields { VoiceHeaderFields(self.0) }
 }
 
 #[cfg(test)]
@@ -126,4 +172,37 @@ mod test {
         assert_eq!(h.crypto_key(), 0);
         assert_eq!(h.talk_group(), TalkGroup::Everbody);
     }
+
+    #[test]
+    fn test_encode() {
+        let h = VoiceHeaderBuilder::new([9, 8, 7, 6, 5, 4, 3, 2, 1], 0x90, Aes, 0xBEEF,
+                                        TalkGroup::Other(0x1234)).build();
+
+        assert_eq!(h.crypto_init(), &[9, 8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(h.mfg(), 0x90);
+        assert_eq!(h.crypto_alg(), Aes);
+        assert_eq!(h.crypto_key(), 0xBEEF);
+        assert_eq!(h.talk_group(), TalkGroup::Other(0x1234));
+
+        let mut dibits = h.encode();
+        assert_eq!(dibits.len(), 324);
+
+        // Corrupt some of the coded words.
+        for i in (0..dibits.len()).filter(|i| i % 20 == 3) {
+            dibits[i] = Dibit::new(dibits[i].bits() ^ 0b11);
+        }
+
+        let mut recv = VoiceHeaderReceiver::new();
+        let mut pkts: Vec<VoiceHeaderFields> = dibits.iter()
+            .filter_map(|&d| recv.feed(d))
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(pkts.len(), 1);
+
+        let pkt = pkts.pop().unwrap();
+        assert_eq!(pkt.0, h.0);
+        assert_eq!(pkt.crypto_alg(), Aes);
+        assert_eq!(pkt.talk_group(), TalkGroup::Other(0x1234));
+    }
 }
@@ -0,0 +1,1299 @@
+//! IMBE voice model parameters, speech analysis, and speech synthesis.
+//!
+//! IMBE models each 20ms frame of speech as a fundamental frequency, a voiced/unvoiced
+//! decision for each harmonic of the fundamental, and a spectral amplitude for each
+//! harmonic. Voiced harmonics are synthesized as sinusoids with continuous phase across
+//! frames, and unvoiced harmonics as noise within the band around the harmonic. Analysis
+//! estimates these parameters from speech samples.
+//!
+//! Each frame's parameters are quantized into values `b_0`, ..., `b_{L+1}` and spread
+//! over the prioritized chunks `u_0`, ..., `u_7` of a `VoiceFrame`. The fundamental is
+//! quantized to 8 bits, the voiced/unvoiced decisions take one bit per band, and the gain
+//! is quantized uniformly in the log domain. Spectral amplitudes are coded as the residual
+//! from a prediction based on the previous frame: the residuals are divided into 6
+//! blocks, each block is transformed by a DCT, and the block means are transformed again
+//! into a prediction residual block average (PRBA) vector. Bits are allocated to the
+//! resulting coefficients by their expected variance, and the most significant bits of
+//! every value are carried in the most strongly protected chunks.
+//!
+//! The bit allocation and quantizer levels are computed by this module rather than
+//! taken from the annex tables of the vocoder description, so frames produced here are
+//! only meant to be decoded here.
+
+use std;
+
+use voice::frame::VoiceFrame;
+
+/// Speech samples per second.
+pub const SAMPLE_RATE: usize = 8000;
+/// Speech samples per 20ms frame.
+pub const FRAME_SAMPLES: usize = 160;
+/// Maximum number of harmonics in a frame.
+pub const MAX_HARMONICS: usize = 56;
+
+/// Number of noise sinusoids used to synthesize each unvoiced harmonic band.
+const NOISE_COMPONENTS: usize = 3;
+
+/// Consecutive repeated frames after which output is muted.
+const MAX_REPEATS: usize = 3;
+
+/// Speech samples in the analysis window, covering the previous and current frames.
+const WINDOW_SAMPLES: usize = FRAME_SAMPLES * 2;
+
+/// Shortest and longest pitch periods, in samples, allowed by the fundamental range.
+const MIN_PERIOD: usize = 20;
+const MAX_PERIOD: usize = 123;
+
+/// Minimum normalized autocorrelation for a frame to be considered periodic.
+const MIN_PERIODICITY: f32 = 0.5;
+
+/// Bits in each prioritized chunk `u_0`, ..., `u_7`.
+const CHUNK_BITS: [usize; 8] = [12, 12, 12, 12, 11, 11, 11, 7];
+
+/// Bits in the quantized fundamental, `b_0`, and gain, `b_2`.
+const FUNDAMENTAL_BITS: usize = 8;
+const GAIN_BITS: usize = 6;
+
+/// Bits shared between the voiced/unvoiced decisions and spectral amplitude
+/// coefficients.
+const SHARED_BITS: usize = 88 - FUNDAMENTAL_BITS - GAIN_BITS;
+
+/// Number of blocks the spectral amplitudes are divided into.
+const BLOCKS: usize = 6;
+
+/// Most bits allocated to a single spectral amplitude coefficient.
+const MAX_COEF_BITS: usize = 10;
+
+/// Weight of the previous frame in predicting log spectral amplitudes, `ρ` in the
+/// standard.
+const PREDICTION: f32 = 0.65;
+
+/// Step of the gain quantizer, in log2 amplitude.
+const GAIN_STEP: f32 = 0.25;
+
+/// Step of the uniform coefficient quantizer for each number of bits, relative to the
+/// standard deviation of the coefficient.
+const COEF_STEPS: [f32; MAX_COEF_BITS] = [
+    1.20, 0.85, 0.65, 0.40, 0.28, 0.15, 0.08, 0.04, 0.02, 0.01,
+];
+
+/// Standard deviation of the PRBA coefficients `G_2`, ..., `G_6`.
+const PRBA_DEVS: [f32; BLOCKS - 1] = [1.00, 0.60, 0.45, 0.35, 0.30];
+
+/// Standard deviation of the higher-order DCT coefficients `C_{i,2}`, ..., `C_{i,10}`
+/// of each block.
+const HOC_DEVS: [f32; 9] = [0.307, 0.241, 0.207, 0.190, 0.179, 0.173, 0.165, 0.170, 0.170];
+
+/// Minimum ratio of spectral energy at harmonics to energy between harmonics for a band
+/// to be considered voiced.
+const VOICING_RATIO: f32 = 4.0;
+
+/// Fundamental frequency of a frame and the resulting harmonic structure.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fundamental {
+    /// Fundamental frequency, in radians per sample.
+    freq: f32,
+    /// Number of harmonics, `L` in the standard.
+    harmonics: usize,
+    /// Number of voiced/unvoiced bands, `K` in the standard.
+    bands: usize,
+}
+
+impl Fundamental {
+    /// Decode the fundamental from its 8-bit quantized value, `b_0` in the standard.
+    /// Return `None` if the value is outside the valid range.
+    pub fn from_bits(bits: u8) -> Option<Fundamental> {
+        if bits > 207 {
+            return None;
+        }
+
+        let freq = 4.0 * std::f32::consts::PI / (bits as f32 + 39.5);
+        let harmonics = (0.9254 * (std::f32::consts::PI / freq + 0.25).floor()) as usize;
+
+        let bands = if harmonics <= 36 {
+            (harmonics + 2) / 3
+        } else {
+            12
+        };
+
+        Some(Fundamental {
+            freq: freq,
+            harmonics: harmonics,
+            bands: bands,
+        })
+    }
+
+    /// Quantize the given fundamental frequency, in radians per sample, to the nearest
+    /// representable fundamental. Frequencies outside the valid range are clamped.
+    pub fn from_freq(freq: f32) -> Fundamental {
+        let bits = (4.0 * std::f32::consts::PI / freq - 39.5).round();
+        Fundamental::from_bits(bits.max(0.0).min(207.0) as u8).unwrap()
+    }
+
+    /// Encode the fundamental into its 8-bit quantized value.
+    pub fn to_bits(&self) -> u8 {
+        (4.0 * std::f32::consts::PI / self.freq - 39.5).round() as u8
+    }
+
+    /// Fundamental frequency, in radians per sample.
+    pub fn freq(&self) -> f32 { self.freq }
+    /// Fundamental frequency (Hz.)
+    pub fn hz(&self) -> f32 { self.freq * SAMPLE_RATE as f32 / (2.0 * std::f32::consts::PI) }
+    /// Number of harmonics, from 9 to 56.
+    pub fn harmonics(&self) -> usize { self.harmonics }
+    /// Number of voiced/unvoiced bands, from 3 to 12.
+    pub fn bands(&self) -> usize { self.bands }
+
+    /// Voiced/unvoiced band (0-based) that contains the given harmonic (1-based.) Each
+    /// band covers 3 harmonics, except the last, which covers all remaining harmonics.
+    pub fn band(&self, harmonic: usize) -> usize {
+        assert!(harmonic >= 1 && harmonic <= self.harmonics);
+        std::cmp::min((harmonic - 1) / 3, self.bands - 1)
+    }
+}
+
+/// Model parameters of a frame of speech.
+#[derive(Copy, Clone)]
+pub struct ImbeParams {
+    /// Fundamental frequency and harmonic structure.
+    pub fundamental: Fundamental,
+    /// Whether each voiced/unvoiced band is voiced. Only the first `bands()` entries are
+    /// used.
+    pub voiced: [bool; 12],
+    /// Spectral amplitude of each harmonic, starting with the fundamental. Only the
+    /// first `harmonics()` entries are used.
+    pub amplitudes: [f32; MAX_HARMONICS],
+}
+
+impl ImbeParams {
+    /// Create a new `ImbeParams` with the given fundamental, with every band unvoiced
+    /// and every amplitude zero.
+    pub fn new(fundamental: Fundamental) -> ImbeParams {
+        ImbeParams {
+            fundamental: fundamental,
+            voiced: [false; 12],
+            amplitudes: [0.0; MAX_HARMONICS],
+        }
+    }
+
+    /// Whether the given harmonic (1-based) is voiced.
+    pub fn harmonic_voiced(&self, harmonic: usize) -> bool {
+        self.voiced[self.fundamental.band(harmonic)]
+    }
+
+    /// Amplitude of the given harmonic (1-based) if it's voiced, and zero otherwise.
+    fn voiced_amplitude(&self, harmonic: usize) -> f32 {
+        if harmonic <= self.fundamental.harmonics && self.harmonic_voiced(harmonic) {
+            self.amplitudes[harmonic - 1]
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Layout of the quantized values `b_0`, ..., `b_{L+1}` of a frame with a given
+/// fundamental.
+///
+/// After the fundamental `b_0`, voiced/unvoiced decisions `b_1`, and gain `b_2` come the
+/// PRBA coefficients `G_2`, ..., `G_6` in `b_3`, ..., `b_7`, then the higher-order DCT
+/// coefficients of each block in order.
+struct Layout {
+    /// Number of bits in each value.
+    bits: Vec<usize>,
+    /// Standard deviation of each coefficient, starting with `b_3`.
+    devs: Vec<f32>,
+    /// Number of spectral amplitudes in each block, `J_i` in the standard.
+    blocks: [usize; BLOCKS],
+}
+
+impl Layout {
+    /// Create a new `Layout` for frames with the given fundamental.
+    fn new(f: &Fundamental) -> Layout {
+        let l = f.harmonics();
+
+        // Harmonics are divided as evenly as possible, with any extra going to the
+        // higher blocks.
+        let mut blocks = [l / BLOCKS; BLOCKS];
+
+        for len in blocks[BLOCKS - l % BLOCKS..].iter_mut() {
+            *len += 1;
+        }
+
+        let mut devs = PRBA_DEVS.to_vec();
+
+        for &len in blocks.iter() {
+            devs.extend_from_slice(&HOC_DEVS[..len - 1]);
+        }
+
+        // Give each bit to the coefficient whose quantization error it reduces the most.
+        let mut coefs = vec![0; devs.len()];
+
+        for _ in 0..SHARED_BITS - f.bands() {
+            let err = |m: usize| devs[m] * 0.5f32.powi(coefs[m] as i32);
+
+            let best = (0..devs.len())
+                .filter(|&m| coefs[m] < MAX_COEF_BITS)
+                .fold(None, |b, m| match b {
+                    Some(b) if err(b) >= err(m) => Some(b),
+                    _ => Some(m),
+                })
+                .unwrap();
+
+            coefs[best] += 1;
+        }
+
+        let mut bits = vec![FUNDAMENTAL_BITS, f.bands(), GAIN_BITS];
+        bits.extend(coefs);
+
+        Layout {
+            bits: bits,
+            devs: devs,
+            blocks: blocks,
+        }
+    }
+
+    /// Bits of the quantized values as `(value, bit)` pairs, from most to least
+    /// important. The fundamental, gain, and voiced/unvoiced decisions come first,
+    /// followed by the coefficients from their most to least significant bits.
+    fn priority(&self) -> Vec<(usize, usize)> {
+        let mut order = vec![];
+
+        for &m in [0, 2, 1].iter() {
+            for bit in (0..self.bits[m]).rev() {
+                order.push((m, bit));
+            }
+        }
+
+        for rank in 0..MAX_COEF_BITS {
+            for m in 3..self.bits.len() {
+                if self.bits[m] > rank {
+                    order.push((m, self.bits[m] - 1 - rank));
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Spread the given quantized values over chunks `u_0`, ..., `u_7`.
+    fn pack(&self, vals: &[u16]) -> [u32; 8] {
+        let mut chunks = [0; 8];
+
+        for ((m, bit), (c, pos)) in self.priority().into_iter().zip(chunk_bits()) {
+            chunks[c] |= (vals[m] as u32 >> bit & 1) << pos;
+        }
+
+        chunks
+    }
+
+    /// Gather the quantized values from the given chunks `u_0`, ..., `u_7`.
+    fn unpack(&self, chunks: &[u32; 8]) -> Vec<u16> {
+        let mut vals = vec![0; self.bits.len()];
+
+        for ((m, bit), (c, pos)) in self.priority().into_iter().zip(chunk_bits()) {
+            vals[m] |= ((chunks[c] >> pos & 1) as u16) << bit;
+        }
+
+        vals
+    }
+
+    /// Quantize the given log2 spectral amplitude prediction residuals into the gain and
+    /// coefficients `b_2`, ..., `b_{L+1}`, leaving `b_0` and `b_1` zero.
+    fn quantize(&self, resid: &[f32]) -> Vec<u16> {
+        let mut vals = vec![0; self.bits.len()];
+        let mut means = [0.0; BLOCKS];
+        let mut hocs = vec![];
+        let mut rest = resid;
+
+        for (&len, mean) in self.blocks.iter().zip(means.iter_mut()) {
+            let mut block = vec![0.0; len];
+            dct(&rest[..len], &mut block);
+            rest = &rest[len..];
+
+            *mean = block[0];
+            hocs.extend_from_slice(&block[1..]);
+        }
+
+        let mut prba = [0.0; BLOCKS];
+        dct(&means, &mut prba);
+
+        let max = ((1 << GAIN_BITS) - 1) as f32;
+        vals[2] = (prba[0] / GAIN_STEP).round().max(0.0).min(max) as u16;
+
+        for (m, &c) in prba[1..].iter().chain(hocs.iter()).enumerate() {
+            let bits = self.bits[m + 3];
+
+            if bits == 0 {
+                continue;
+            }
+
+            let step = COEF_STEPS[bits - 1] * self.devs[m];
+            let max = ((1 << bits) - 1) as f32;
+
+            vals[m + 3] = (c / step + (1 << (bits - 1)) as f32).floor().max(0.0).min(max)
+                as u16;
+        }
+
+        vals
+    }
+
+    /// Reconstruct the log2 spectral amplitude prediction residuals, `T_l` in the
+    /// standard, from the given quantized values.
+    fn residuals(&self, vals: &[u16]) -> Vec<f32> {
+        let coefs: Vec<f32> = vals[3..].iter().enumerate().map(|(m, &b)| {
+            let bits = self.bits[m + 3];
+
+            if bits == 0 {
+                return 0.0;
+            }
+
+            let step = COEF_STEPS[bits - 1] * self.devs[m];
+            step * (b as f32 - (1 << (bits - 1)) as f32 + 0.5)
+        }).collect();
+
+        let mut prba = [0.0; BLOCKS];
+        prba[0] = vals[2] as f32 * GAIN_STEP;
+        prba[1..].copy_from_slice(&coefs[..BLOCKS - 1]);
+
+        let mut means = [0.0; BLOCKS];
+        idct(&prba, &mut means);
+
+        let mut out = vec![];
+        let mut hocs = &coefs[BLOCKS - 1..];
+
+        for (&len, &mean) in self.blocks.iter().zip(means.iter()) {
+            let mut block = vec![mean];
+            block.extend_from_slice(&hocs[..len - 1]);
+            hocs = &hocs[len - 1..];
+
+            let mut amps = vec![0.0; len];
+            idct(&block, &mut amps);
+            out.extend(amps);
+        }
+
+        out
+    }
+}
+
+/// Positions of the bits of chunks `u_0`, ..., `u_7` as `(chunk, bit)` pairs, from the
+/// most significant bit of `u_0` to the least significant bit of `u_7`.
+fn chunk_bits() -> Vec<(usize, usize)> {
+    CHUNK_BITS.iter().enumerate().flat_map(|(c, &bits)| {
+        (0..bits).rev().map(move |pos| (c, pos))
+    }).collect()
+}
+
+/// Compute the DCT of the given values, where the first coefficient is their mean.
+fn dct(vals: &[f32], out: &mut [f32]) {
+    let len = vals.len() as f32;
+
+    for (k, c) in out.iter_mut().enumerate() {
+        *c = vals.iter().enumerate().fold(0.0, |s, (j, &x)| {
+            s + x * (std::f32::consts::PI * k as f32 * (j as f32 + 0.5) / len).cos()
+        }) / len;
+    }
+}
+
+/// Compute the inverse DCT of the given coefficients, where the first coefficient is the
+/// mean of the outputs.
+fn idct(coefs: &[f32], out: &mut [f32]) {
+    let len = out.len() as f32;
+
+    for (j, x) in out.iter_mut().enumerate() {
+        *x = coefs.iter().enumerate().skip(1).fold(coefs[0], |s, (k, &c)| {
+            s + 2.0 * c * (std::f32::consts::PI * k as f32 * (j as f32 + 0.5) / len).cos()
+        });
+    }
+}
+
+/// Reconstructs model parameters from the prioritized chunks `u_0`, ..., `u_7` of voice
+/// frames.
+///
+/// Spectral amplitudes are coded relative to those of the previous frame, so frames must
+/// be decoded in order, and frames that are discarded shouldn't be decoded at all.
+pub struct ChunkDecoder {
+    /// Log2 spectral amplitudes of the previous frame.
+    prev: [f32; MAX_HARMONICS],
+    /// Number of harmonics in the previous frame.
+    prev_harmonics: usize,
+}
+
+impl ChunkDecoder {
+    /// Create a new `ChunkDecoder` with unit amplitudes as the previous frame.
+    pub fn new() -> ChunkDecoder {
+        ChunkDecoder {
+            prev: [0.0; MAX_HARMONICS],
+            prev_harmonics: 30,
+        }
+    }
+
+    /// Decode model parameters from the given chunks. Return `Some(params)` if the
+    /// chunks hold a valid fundamental, and `None` otherwise.
+    pub fn decode(&mut self, chunks: &[u32; 8]) -> Option<ImbeParams> {
+        let bits = chunks[0] >> (CHUNK_BITS[0] - FUNDAMENTAL_BITS);
+
+        let f = match Fundamental::from_bits(bits as u8) {
+            Some(f) => f,
+            None => return None,
+        };
+
+        let layout = Layout::new(&f);
+        let vals = layout.unpack(chunks);
+        let mut params = ImbeParams::new(f);
+
+        for k in 0..f.bands() {
+            params.voiced[k] = vals[1] >> (f.bands() - 1 - k) & 1 == 1;
+        }
+
+        let pred = self.prediction(&f);
+
+        for (l, t) in layout.residuals(&vals).into_iter().enumerate() {
+            self.prev[l] = t + pred[l];
+            params.amplitudes[l] = self.prev[l].exp2();
+        }
+
+        self.prev_harmonics = f.harmonics();
+
+        Some(params)
+    }
+
+    /// Predict the log2 spectral amplitudes of a frame with the given fundamental by
+    /// resampling those of the previous frame, with their mean removed.
+    fn prediction(&self, f: &Fundamental) -> Vec<f32> {
+        let l = f.harmonics();
+        let ratio = self.prev_harmonics as f32 / l as f32;
+
+        let prev = |k: usize| {
+            self.prev[std::cmp::min(std::cmp::max(k, 1), self.prev_harmonics) - 1]
+        };
+
+        let pred: Vec<f32> = (1..=l).map(|n| {
+            let pos = ratio * n as f32;
+            let (k, frac) = (pos.floor() as usize, pos.fract());
+
+            PREDICTION * ((1.0 - frac) * prev(k) + frac * prev(k + 1))
+        }).collect();
+
+        let mean = pred.iter().fold(0.0, |s, &p| s + p) / l as f32;
+
+        pred.into_iter().map(|p| p - mean).collect()
+    }
+}
+
+/// Quantizes model parameters into the prioritized chunks `u_0`, ..., `u_7` of voice
+/// frames.
+///
+/// Spectral amplitudes are coded relative to those a `ChunkDecoder` reconstructs for the
+/// previous frame, so quantization errors don't accumulate at the receiver.
+pub struct ChunkEncoder {
+    /// Tracks the parameters reconstructed at the receiver.
+    decoder: ChunkDecoder,
+}
+
+impl ChunkEncoder {
+    /// Create a new `ChunkEncoder` in the initial state.
+    pub fn new() -> ChunkEncoder {
+        ChunkEncoder {
+            decoder: ChunkDecoder::new(),
+        }
+    }
+
+    /// Quantize the given parameters into chunks.
+    pub fn encode(&mut self, params: &ImbeParams) -> [u32; 8] {
+        let f = params.fundamental;
+        let layout = Layout::new(&f);
+        let pred = self.decoder.prediction(&f);
+
+        let resid: Vec<f32> = (0..f.harmonics()).map(|l| {
+            params.amplitudes[l].max(1.0).log2() - pred[l]
+        }).collect();
+
+        let mut vals = layout.quantize(&resid[..]);
+        vals[0] = f.to_bits() as u16;
+        vals[1] = (0..f.bands()).fold(0, |s, k| s << 1 | params.voiced[k] as u16);
+
+        let chunks = layout.pack(&vals[..]);
+        self.decoder.decode(&chunks);
+
+        chunks
+    }
+}
+
+/// How a received frame should be handled, based on its FEC errors.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameDisposition {
+    /// Synthesize speech from the frame.
+    Use,
+    /// Discard the frame and repeat the previous one.
+    Repeat,
+    /// Discard the frame and mute the output.
+    Mute,
+}
+
+/// Tracks the rate of FEC errors in received voice frames to determine when frames
+/// should be repeated or muted.
+///
+/// A frame is repeated if its `u_0` chunk had at least 2 errors and its total errors are
+/// high relative to the running error rate, since the remaining chunks are PN-scrambled
+/// with a seed taken from `u_0`. Output is muted when the running error rate gets too
+/// high, or after several consecutive repeats.
+pub struct ErrorTracker {
+    /// Running error rate, `ε_R` in the standard.
+    rate: f32,
+    /// Number of consecutive repeated frames.
+    repeats: usize,
+}
+
+impl ErrorTracker {
+    /// Create a new `ErrorTracker` with zero error rate.
+    pub fn new() -> ErrorTracker {
+        ErrorTracker {
+            rate: 0.0,
+            repeats: 0,
+        }
+    }
+
+    /// Update with the FEC errors of the next frame, in the layout of
+    /// `VoiceFrame::errors`, and determine how the frame should be handled.
+    pub fn feed(&mut self, errors: &[usize; 7]) -> FrameDisposition {
+        let total = errors.iter().fold(0, |s, &e| s + e) as f32;
+
+        self.rate = 0.95 * self.rate + 0.000365 * total;
+
+        if self.rate > 0.0875 {
+            return FrameDisposition::Mute;
+        }
+
+        if errors[0] >= 2 && total >= 10.0 + 40.0 * self.rate {
+            self.repeats += 1;
+
+            return if self.repeats > MAX_REPEATS {
+                FrameDisposition::Mute
+            } else {
+                FrameDisposition::Repeat
+            };
+        }
+
+        self.repeats = 0;
+
+        FrameDisposition::Use
+    }
+
+    /// Current running error rate.
+    pub fn rate(&self) -> f32 { self.rate }
+}
+
+/// Sinusoid used to synthesize noise within an unvoiced band.
+#[derive(Copy, Clone)]
+struct NoiseComponent {
+    /// Frequency, in radians per sample.
+    freq: f32,
+    /// Current phase.
+    phase: f32,
+    /// Amplitude.
+    amp: f32,
+}
+
+/// Synthesizes speech samples from frames of model parameters.
+///
+/// Each voiced harmonic is synthesized with its amplitude and frequency interpolated
+/// from the previous frame, keeping its phase continuous. Each unvoiced band is
+/// synthesized with sinusoids of random frequency within the band, crossfaded with those
+/// of the previous frame.
+pub struct Synthesizer {
+    /// Parameters of the previous frame.
+    prev: Option<ImbeParams>,
+    /// Current phase of each voiced harmonic.
+    phases: [f32; MAX_HARMONICS],
+    /// Noise sinusoids of the previous frame.
+    noise: Vec<NoiseComponent>,
+    /// State of the noise generator.
+    seed: u32,
+}
+
+impl Synthesizer {
+    /// Create a new `Synthesizer` with silence as the previous frame.
+    pub fn new() -> Synthesizer {
+        Synthesizer {
+            prev: None,
+            phases: [0.0; MAX_HARMONICS],
+            noise: vec![],
+            seed: 0x1234_5678,
+        }
+    }
+
+    /// Synthesize a frame of speech samples from the given parameters.
+    pub fn feed(&mut self, params: &ImbeParams) -> [f32; FRAME_SAMPLES] {
+        let mut out = [0.0; FRAME_SAMPLES];
+
+        self.voiced(params, &mut out);
+        self.unvoiced(params, &mut out);
+
+        self.prev = Some(*params);
+
+        out
+    }
+
+    /// Synthesize a frame of speech samples by repeating the previous parameters.
+    pub fn repeat(&mut self) -> [f32; FRAME_SAMPLES] {
+        match self.prev {
+            Some(p) => self.feed(&p),
+            None => [0.0; FRAME_SAMPLES],
+        }
+    }
+
+    /// Synthesize a frame of speech samples that fades the previous frame to silence.
+    pub fn mute(&mut self) -> [f32; FRAME_SAMPLES] {
+        match self.prev {
+            Some(p) => self.feed(&ImbeParams::new(p.fundamental)),
+            None => [0.0; FRAME_SAMPLES],
+        }
+    }
+
+    /// Add in the voiced harmonics.
+    fn voiced(&mut self, cur: &ImbeParams, out: &mut [f32; FRAME_SAMPLES]) {
+        let prev = self.prev.unwrap_or(ImbeParams::new(cur.fundamental));
+        let count = std::cmp::max(prev.fundamental.harmonics, cur.fundamental.harmonics);
+
+        for l in 1..=count {
+            let (a0, a1) = (prev.voiced_amplitude(l), cur.voiced_amplitude(l));
+
+            if a0 == 0.0 && a1 == 0.0 {
+                continue;
+            }
+
+            // Sweep the frequency only if the harmonic continues across frames.
+            let (w0, w1) = match (a0 == 0.0, a1 == 0.0) {
+                (false, false) => (prev.fundamental.freq, cur.fundamental.freq),
+                (false, true) => (prev.fundamental.freq, prev.fundamental.freq),
+                _ => (cur.fundamental.freq, cur.fundamental.freq),
+            };
+
+            let phase = &mut self.phases[l - 1];
+
+            for (n, s) in out.iter_mut().enumerate() {
+                let t = n as f32 / FRAME_SAMPLES as f32;
+
+                *s += (a0 + (a1 - a0) * t) * phase.cos();
+                *phase += l as f32 * (w0 + (w1 - w0) * t);
+            }
+
+            *phase %= 2.0 * std::f32::consts::PI;
+        }
+    }
+
+    /// Add in the unvoiced bands.
+    fn unvoiced(&mut self, cur: &ImbeParams, out: &mut [f32; FRAME_SAMPLES]) {
+        let mut noise = vec![];
+        let w = cur.fundamental.freq;
+
+        for l in 1..=cur.fundamental.harmonics {
+            if cur.harmonic_voiced(l) || cur.amplitudes[l - 1] == 0.0 {
+                continue;
+            }
+
+            let amp = cur.amplitudes[l - 1] / (NOISE_COMPONENTS as f32).sqrt();
+
+            for _ in 0..NOISE_COMPONENTS {
+                let freq = w * (l as f32 - 0.5 + self.random());
+                let phase = 2.0 * std::f32::consts::PI * self.random();
+
+                noise.push(NoiseComponent {
+                    freq: freq,
+                    phase: phase,
+                    amp: amp,
+                });
+            }
+        }
+
+        let prev = std::mem::replace(&mut self.noise, vec![]);
+
+        // Fade out the previous components and fade in the current ones.
+        for (mut c, fade_in) in prev.into_iter().map(|c| (c, false))
+            .chain(noise.iter().map(|&c| (c, true)))
+        {
+            for (n, s) in out.iter_mut().enumerate() {
+                let t = n as f32 / FRAME_SAMPLES as f32;
+                let gain = if fade_in { t } else { 1.0 - t };
+
+                *s += gain * c.amp * c.phase.cos();
+                c.phase += c.freq;
+            }
+
+            if fade_in {
+                c.phase %= 2.0 * std::f32::consts::PI;
+                self.noise.push(c);
+            }
+        }
+    }
+
+    /// Generate a uniform random number in [0, 1).
+    fn random(&mut self) -> f32 {
+        self.seed = self.seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Synthesizes speech from received voice frames, repeating or muting frames received
+/// with too many errors.
+pub struct ImbeDecoder {
+    /// Tracks frame errors.
+    errors: ErrorTracker,
+    /// Decodes model parameters.
+    chunks: ChunkDecoder,
+    /// Synthesizes speech.
+    synth: Synthesizer,
+}
+
+impl ImbeDecoder {
+    /// Create a new `ImbeDecoder` in the initial state.
+    pub fn new() -> ImbeDecoder {
+        ImbeDecoder {
+            errors: ErrorTracker::new(),
+            chunks: ChunkDecoder::new(),
+            synth: Synthesizer::new(),
+        }
+    }
+
+    /// Synthesize the next frame of speech samples from the given voice frame.
+    pub fn feed(&mut self, frame: &VoiceFrame) -> [f32; FRAME_SAMPLES] {
+        let disp = self.errors.feed(&frame.errors);
+
+        let params = match disp {
+            FrameDisposition::Use => self.chunks.decode(&frame.chunks),
+            _ => None,
+        };
+
+        self.synthesize(disp, params.as_ref())
+    }
+
+    /// Synthesize the next frame of speech samples from the given parameters, which were
+    /// decoded from a voice frame with the given FEC errors, in the layout of
+    /// `VoiceFrame::errors`. Return the samples along with how the frame was handled.
+    pub fn feed_params(&mut self, params: &ImbeParams, errors: &[usize; 7])
+        -> ([f32; FRAME_SAMPLES], FrameDisposition)
+    {
+        let disp = self.errors.feed(errors);
+
+        (self.synthesize(disp, Some(params)), disp)
+    }
+
+    /// Synthesize the next frame of speech samples in place of a frame that couldn't be
+    /// decoded at all.
+    pub fn feed_lost(&mut self) -> [f32; FRAME_SAMPLES] {
+        self.synth.mute()
+    }
+
+    /// Synthesize speech samples according to the given frame disposition, repeating
+    /// the previous frame if the parameters couldn't be decoded.
+    fn synthesize(&mut self, disp: FrameDisposition, params: Option<&ImbeParams>)
+        -> [f32; FRAME_SAMPLES]
+    {
+        match (disp, params) {
+            (FrameDisposition::Use, Some(p)) => self.synth.feed(p),
+            (FrameDisposition::Mute, _) => self.synth.mute(),
+            _ => self.synth.repeat(),
+        }
+    }
+}
+
+/// Estimates model parameters from frames of speech samples.
+///
+/// Each frame is analyzed over a window covering it and the previous frame. The pitch
+/// period is found by autocorrelation, and each harmonic amplitude is measured from the
+/// windowed spectrum at the harmonic frequency. A band is voiced if its spectral energy is
+/// concentrated at the harmonics rather than spread between them.
+pub struct ImbeEncoder {
+    /// Samples of the previous frame.
+    prev: [f32; FRAME_SAMPLES],
+    /// Analysis window coefficients.
+    window: [f32; WINDOW_SAMPLES],
+    /// Quantizes estimated parameters.
+    chunks: ChunkEncoder,
+}
+
+impl ImbeEncoder {
+    /// Create a new `ImbeEncoder` with silence as the previous frame.
+    pub fn new() -> ImbeEncoder {
+        let mut window = [0.0; WINDOW_SAMPLES];
+
+        for (n, w) in window.iter_mut().enumerate() {
+            *w = 0.54 - 0.46 * (2.0 * std::f32::consts::PI * n as f32 /
+                                (WINDOW_SAMPLES - 1) as f32).cos();
+        }
+
+        ImbeEncoder {
+            prev: [0.0; FRAME_SAMPLES],
+            window: window,
+            chunks: ChunkEncoder::new(),
+        }
+    }
+
+    /// Encode the given frame of speech samples into chunks `u_0`, ..., `u_7`, suitable
+    /// for `VoiceFrame::from_chunks`.
+    pub fn feed(&mut self, samples: &[f32; FRAME_SAMPLES]) -> [u32; 8] {
+        let params = self.analyze(samples);
+        self.chunks.encode(&params)
+    }
+
+    /// Estimate the model parameters of the given frame of speech samples.
+    pub fn analyze(&mut self, samples: &[f32; FRAME_SAMPLES]) -> ImbeParams {
+        let mut buf = [0.0; WINDOW_SAMPLES];
+        buf[..FRAME_SAMPLES].copy_from_slice(&self.prev[..]);
+        buf[FRAME_SAMPLES..].copy_from_slice(&samples[..]);
+
+        self.prev = *samples;
+
+        let (period, periodic) = pitch(&buf);
+        let mut params = ImbeParams::new(Fundamental::from_freq(
+            2.0 * std::f32::consts::PI / period));
+
+        for (n, s) in buf.iter_mut().enumerate() {
+            *s *= self.window[n];
+        }
+
+        let gain = self.window.iter().fold(0.0, |s, &w| s + w);
+        let f = params.fundamental;
+
+        // Spectral magnitude at the given frequency, scaled so that a sinusoid of
+        // amplitude A measures A at its own frequency.
+        let mag = |freq: f32| {
+            let (re, im) = buf.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &s)| {
+                let phase = freq * n as f32;
+                (re + s * phase.cos(), im - s * phase.sin())
+            });
+
+            2.0 * (re * re + im * im).sqrt() / gain
+        };
+
+        let mut peak = [0.0; 12];
+        let mut between = [0.0; 12];
+
+        for l in 1..=f.harmonics() {
+            let center = mag(l as f32 * f.freq());
+            let lower = mag((l as f32 - 0.5) * f.freq());
+            let upper = mag((l as f32 + 0.5) * f.freq());
+
+            let band = f.band(l);
+            peak[band] += center * center;
+            between[band] += (lower * lower + upper * upper) / 2.0;
+
+            params.amplitudes[l - 1] = center;
+        }
+
+        for k in 0..f.bands() {
+            params.voiced[k] = periodic && peak[k] > 0.0 &&
+                peak[k] >= VOICING_RATIO * between[k];
+        }
+
+        // The spectrum of noise is spread across the band, so measure unvoiced amplitudes
+        // by their average energy around each harmonic.
+        for l in 1..=f.harmonics() {
+            if params.harmonic_voiced(l) {
+                continue;
+            }
+
+            let energy = [-1.0 / 3.0, 0.0, 1.0 / 3.0].iter()
+                .map(|&d| mag((l as f32 + d) * f.freq()).powi(2))
+                .fold(0.0, |s, e| s + e);
+
+            params.amplitudes[l - 1] = (energy / 3.0).sqrt();
+        }
+
+        params
+    }
+}
+
+/// Estimate the pitch period, in samples, of the given speech samples. Return the period
+/// along with whether the samples are periodic at all.
+fn pitch(buf: &[f32; WINDOW_SAMPLES]) -> (f32, bool) {
+    let corr = |lag: usize| {
+        let (xy, xx, yy) = buf[..WINDOW_SAMPLES - lag].iter().zip(buf[lag..].iter())
+            .fold((0.0, 0.0, 0.0), |(xy, xx, yy), (&x, &y)| {
+                (xy + x * y, xx + x * x, yy + y * y)
+            });
+
+        if xx == 0.0 || yy == 0.0 {
+            0.0
+        } else {
+            xy / (xx * yy).sqrt()
+        }
+    };
+
+    let mut r = [0.0; MAX_PERIOD + 2];
+
+    for lag in MIN_PERIOD - 1..=MAX_PERIOD + 1 {
+        r[lag] = corr(lag);
+    }
+
+    let best = (MIN_PERIOD..=MAX_PERIOD).fold(MIN_PERIOD, |b, lag| {
+        if r[lag] > r[b] { lag } else { b }
+    });
+
+    // Multiples of the true period correlate as well as the period itself, so prefer the
+    // shortest submultiple that correlates nearly as well.
+    let lag = (2..=4).rev()
+        .map(|k| (best as f32 / k as f32).round() as usize)
+        .filter(|&lag| lag >= MIN_PERIOD)
+        .map(|lag| (lag - 1..=lag + 1).fold(lag, |b, l| if r[l] > r[b] { l } else { b }))
+        .find(|&lag| lag >= MIN_PERIOD && r[lag] >= 0.85 * r[best])
+        .unwrap_or(best);
+
+    // Refine the period between samples with a parabola through the neighboring lags.
+    let (a, b, c) = (r[lag - 1], r[lag], r[lag + 1]);
+    let denom = a - 2.0 * b + c;
+
+    let offset = if denom < 0.0 {
+        (0.5 * (a - c) / denom).max(-0.5).min(0.5)
+    } else {
+        0.0
+    };
+
+    (lag as f32 + offset, r[lag] >= MIN_PERIODICITY)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fundamental() {
+        let f = Fundamental::from_bits(0).unwrap();
+        assert!((f.freq() - 4.0 * std::f32::consts::PI / 39.5).abs() < 1e-6);
+        assert_eq!(f.harmonics(), 9);
+        assert_eq!(f.bands(), 3);
+        assert_eq!(f.band(1), 0);
+        assert_eq!(f.band(3), 0);
+        assert_eq!(f.band(4), 1);
+        assert_eq!(f.band(9), 2);
+
+        let f = Fundamental::from_bits(207).unwrap();
+        assert_eq!(f.harmonics(), 56);
+        assert_eq!(f.bands(), 12);
+        assert_eq!(f.band(34), 11);
+        assert_eq!(f.band(56), 11);
+
+        let f = Fundamental::from_bits(100).unwrap();
+        assert!((f.hz() - 8000.0 * 2.0 / 139.5).abs() < 1e-3);
+
+        assert!(Fundamental::from_bits(208).is_none());
+        assert!(Fundamental::from_bits(255).is_none());
+
+        for b in 0..=207 {
+            assert_eq!(Fundamental::from_bits(b).unwrap().to_bits(), b);
+        }
+
+        let f = Fundamental::from_freq(2.0 * std::f32::consts::PI / 40.0);
+        assert_eq!(f.to_bits(), 41);
+        assert_eq!(Fundamental::from_freq(1.0).to_bits(), 0);
+        assert_eq!(Fundamental::from_freq(0.01).to_bits(), 207);
+    }
+
+    #[test]
+    fn test_error_tracker() {
+        let mut e = ErrorTracker::new();
+
+        assert_eq!(e.feed(&[0; 7]), FrameDisposition::Use);
+        assert_eq!(e.feed(&[1, 1, 1, 1, 1, 1, 1]), FrameDisposition::Use);
+        assert_eq!(e.feed(&[2, 3, 3, 3, 1, 0, 0]), FrameDisposition::Repeat);
+        assert_eq!(e.feed(&[0; 7]), FrameDisposition::Use);
+
+        for _ in 0..MAX_REPEATS {
+            assert_eq!(e.feed(&[3, 3, 3, 3, 0, 0, 0]), FrameDisposition::Repeat);
+        }
+
+        assert_eq!(e.feed(&[3, 3, 3, 3, 0, 0, 0]), FrameDisposition::Mute);
+
+        let mut e = ErrorTracker::new();
+
+        // Sustained errors eventually mute even without repeats.
+        let disp: Vec<_> = (0..100).map(|_| e.feed(&[1, 3, 3, 3, 1, 1, 1])).collect();
+        assert_eq!(disp[0], FrameDisposition::Use);
+        assert_eq!(disp[99], FrameDisposition::Mute);
+        assert!(e.rate() > 0.0875);
+    }
+
+    #[test]
+    fn test_voiced() {
+        let f = Fundamental::from_bits(60).unwrap();
+        let mut p = ImbeParams::new(f);
+        p.voiced[0] = true;
+        p.amplitudes[0] = 1000.0;
+
+        let mut s = Synthesizer::new();
+        s.feed(&p);
+
+        let out = s.feed(&p);
+
+        let peak = out.iter().fold(0.0f32, |m, &x| m.max(x.abs()));
+        assert!((peak - 1000.0).abs() < 10.0);
+
+        // Count zero crossings to measure the synthesized frequency.
+        let crossings = out.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count();
+        let expected = f.hz() * 2.0 * FRAME_SAMPLES as f32 / SAMPLE_RATE as f32;
+        assert!((crossings as f32 - expected).abs() <= 1.0);
+
+        // Phase continues smoothly into the next frame.
+        let next = s.feed(&p);
+        assert!((next[0] - out[FRAME_SAMPLES - 1]).abs() < 1000.0 * f.freq() * 1.1);
+    }
+
+    #[test]
+    fn test_unvoiced() {
+        let f = Fundamental::from_bits(60).unwrap();
+        let mut p = ImbeParams::new(f);
+
+        for a in p.amplitudes[..f.harmonics()].iter_mut() {
+            *a = 100.0;
+        }
+
+        let mut s = Synthesizer::new();
+        s.feed(&p);
+
+        let out = s.feed(&p);
+        let power = out.iter().fold(0.0, |s, &x| s + x * x) / FRAME_SAMPLES as f32;
+
+        assert!(power > 0.0);
+        assert!(out.iter().all(|x| x.abs() < 100.0 * f.harmonics() as f32));
+    }
+
+    #[test]
+    fn test_decoder() {
+        let f = Fundamental::from_bits(60).unwrap();
+        let mut p = ImbeParams::new(f);
+        p.voiced = [true; 12];
+        p.amplitudes[2] = 500.0;
+
+        let mut d = ImbeDecoder::new();
+
+        let (_, disp) = d.feed_params(&p, &[0; 7]);
+        assert_eq!(disp, FrameDisposition::Use);
+
+        let (good, _) = d.feed_params(&p, &[0; 7]);
+
+        // A bad frame with different parameters repeats the previous ones instead.
+        let mut bad = p;
+        bad.amplitudes[2] = 0.0;
+
+        let (out, disp) = d.feed_params(&bad, &[3, 3, 3, 3, 0, 0, 0]);
+        assert_eq!(disp, FrameDisposition::Repeat);
+
+        let peak = |x: &[f32]| x.iter().fold(0.0f32, |m, &s| m.max(s.abs()));
+        assert!((peak(&out[..]) - peak(&good[..])).abs() < 5.0);
+
+        // Lost frames fade out to silence.
+        d.feed_lost();
+        assert!(d.feed_lost().iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_layout() {
+        let f = Fundamental::from_bits(0).unwrap();
+        let layout = Layout::new(&f);
+
+        assert_eq!(layout.blocks, [1, 1, 1, 2, 2, 2]);
+        assert_eq!(layout.bits.len(), f.harmonics() + 2);
+        assert_eq!(layout.bits.iter().fold(0, |s, &b| s + b), 88);
+
+        let f = Fundamental::from_bits(207).unwrap();
+        let layout = Layout::new(&f);
+
+        assert_eq!(layout.blocks, [9, 9, 9, 9, 10, 10]);
+        assert_eq!(layout.bits.len(), f.harmonics() + 2);
+        assert_eq!(layout.bits.iter().fold(0, |s, &b| s + b), 88);
+        assert!(layout.bits[3..].iter().all(|&b| b <= MAX_COEF_BITS));
+
+        // The fundamental fills the top of u_0 so the layout can be recovered.
+        let mut order = layout.priority();
+        assert_eq!(&order[..8], &[(0, 7), (0, 6), (0, 5), (0, 4),
+                                  (0, 3), (0, 2), (0, 1), (0, 0)]);
+
+        order.sort();
+        order.dedup();
+        assert_eq!(order.len(), 88);
+
+        let vals: Vec<u16> = layout.bits.iter().enumerate()
+            .map(|(m, &b)| (m as u16).wrapping_mul(0x9E37) & ((1u32 << b) - 1) as u16)
+            .collect();
+
+        assert_eq!(layout.unpack(&layout.pack(&vals[..])), vals);
+    }
+
+    #[test]
+    fn test_decode_frame() {
+        let f = Fundamental::from_bits(60).unwrap();
+        let layout = Layout::new(&f);
+
+        // Every band voiced, a gain of 2^8, and every coefficient at its midpoint.
+        let mut vals: Vec<u16> = layout.bits.iter()
+            .map(|&b| if b == 0 { 0 } else { 1 << (b - 1) })
+            .collect();
+
+        vals[0] = 60;
+        vals[1] = (1 << f.bands()) - 1;
+        vals[2] = 32;
+
+        let chunks = layout.pack(&vals[..]);
+        let frame = VoiceFrame::new(&VoiceFrame::from_chunks(chunks).encode()).unwrap();
+        assert_eq!(frame.chunks, chunks);
+
+        let p = ChunkDecoder::new().decode(&frame.chunks).unwrap();
+        let amps = &p.amplitudes[..f.harmonics()];
+
+        assert_eq!(p.fundamental.to_bits(), 60);
+        assert!(p.voiced[..f.bands()].iter().all(|&v| v));
+        assert!(amps.iter().all(|&a| (a.log2() - 8.0).abs() < 1.25));
+
+        let mean = amps.iter().fold(0.0, |s, &a| s + a.log2()) / amps.len() as f32;
+        assert!((mean - 8.0).abs() < 0.25);
+
+        let mut d = ImbeDecoder::new();
+        let peak = |x: &[f32]| x.iter().fold(0.0f32, |m, &s| m.max(s.abs()));
+
+        d.feed(&frame);
+        let good = d.feed(&frame);
+        assert!(peak(&good[..]) > 256.0);
+
+        // A frame with too many errors repeats the previous one, whatever it holds.
+        let mut bad = VoiceFrame::from_chunks([0xFFF, 0, 0, 0, 0, 0, 0, 0]);
+        bad.errors = [3, 3, 3, 3, 0, 0, 0];
+
+        let out = d.feed(&bad);
+        assert!((peak(&out[..]) - peak(&good[..])).abs() < 0.1 * peak(&good[..]));
+
+        // An invalid fundamental also repeats the previous frame.
+        let out = d.feed(&VoiceFrame::from_chunks([0xFFF, 0, 0, 0, 0, 0, 0, 0]));
+        assert!(peak(&out[..]) > 0.5 * peak(&good[..]));
+    }
+
+    fn tone(hz: &[(f32, f32)], frame: usize) -> [f32; FRAME_SAMPLES] {
+        let mut out = [0.0; FRAME_SAMPLES];
+
+        for (n, s) in out.iter_mut().enumerate() {
+            let t = (frame * FRAME_SAMPLES + n) as f32 / SAMPLE_RATE as f32;
+
+            *s = hz.iter().fold(0.0, |s, &(f, a)| {
+                s + a * (2.0 * std::f32::consts::PI * f * t).sin()
+            });
+        }
+
+        out
+    }
+
+    #[test]
+    fn test_encode_tone() {
+        let mut e = ImbeEncoder::new();
+        e.analyze(&tone(&[(200.0, 1000.0), (400.0, 500.0)], 0));
+
+        let p = e.analyze(&tone(&[(200.0, 1000.0), (400.0, 500.0)], 1));
+        let f = p.fundamental;
+
+        assert!((f.hz() - 200.0).abs() < 2.0);
+        assert!(p.voiced[0]);
+        assert!((p.amplitudes[0] - 1000.0).abs() < 50.0);
+        assert!((p.amplitudes[1] - 500.0).abs() < 50.0);
+        assert!(p.amplitudes[2..f.harmonics()].iter().all(|&a| a < 50.0));
+    }
+
+    #[test]
+    fn test_encode_silence() {
+        let mut e = ImbeEncoder::new();
+        let p = e.analyze(&[0.0; FRAME_SAMPLES]);
+
+        assert!(p.voiced.iter().all(|&v| !v));
+        assert!(p.amplitudes.iter().all(|&a| a == 0.0));
+    }
+
+    #[test]
+    fn test_encode_noise() {
+        let mut e = ImbeEncoder::new();
+        let mut seed = 0x1234u32;
+        let mut p = None;
+
+        for _ in 0..4 {
+            let mut frame = [0.0; FRAME_SAMPLES];
+
+            for s in frame.iter_mut() {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                *s = ((seed >> 16) & 0x7FFF) as f32 - 16384.0;
+            }
+
+            p = Some(e.analyze(&frame));
+        }
+
+        let p = p.unwrap();
+        let f = p.fundamental;
+
+        assert!(p.voiced[..f.bands()].iter().all(|&v| !v));
+        assert!(p.amplitudes[..f.harmonics()].iter().all(|&a| a > 0.0));
+    }
+
+    #[test]
+    fn test_encode_frame() {
+        let harmonics: Vec<_> = (1..=10).map(|n| (200.0 * n as f32, 1000.0 / n as f32))
+            .collect();
+
+        let mut e = ImbeEncoder::new();
+        let mut d = ImbeDecoder::new();
+        let mut params = ChunkDecoder::new();
+
+        let (mut p, mut out) = (None, [0.0; FRAME_SAMPLES]);
+
+        for frame in 0..6 {
+            let chunks = e.feed(&tone(&harmonics[..], frame));
+            let vf = VoiceFrame::new(&VoiceFrame::from_chunks(chunks).encode()).unwrap();
+
+            p = params.decode(&vf.chunks);
+            out = d.feed(&vf);
+        }
+
+        let p = p.unwrap();
+
+        assert!((p.fundamental.hz() - 200.0).abs() < 2.0);
+        assert!(p.voiced[0]);
+        assert!((p.amplitudes[0] - 1000.0).abs() < 100.0);
+        assert!((p.amplitudes[1] - 500.0).abs() < 50.0);
+
+        // The decoded speech keeps the input's period and roughly its power.
+        let corr = |lag: usize| {
+            let (xy, xx, yy) = out[lag..].iter().zip(out.iter())
+                .fold((0.0, 0.0, 0.0), |(xy, xx, yy), (&x, &y)| {
+                    (xy + x * y, xx + x * x, yy + y * y)
+                });
+
+            xy / (xx * yy).sqrt()
+        };
+
+        assert!(corr(40) > 0.9);
+        assert!(corr(20) < 0.7);
+
+        let power = out.iter().fold(0.0, |s, &x| s + x * x) / FRAME_SAMPLES as f32;
+        let expected = harmonics.iter().fold(0.0, |s, &(_, a)| s + a * a / 2.0);
+        assert!(power > 0.5 * expected && power < 2.0 * expected);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let f = Fundamental::from_bits(60).unwrap();
+        let mut p = ImbeParams::new(f);
+        p.voiced = [true; 12];
+        p.amplitudes[0] = 800.0;
+        p.amplitudes[1] = 400.0;
+
+        let mut d = Synthesizer::new();
+        let mut e = ImbeEncoder::new();
+
+        let mut out = None;
+
+        for _ in 0..4 {
+            out = Some(e.analyze(&d.feed(&p)));
+        }
+
+        let out = out.unwrap();
+
+        assert_eq!(out.fundamental.to_bits(), 60);
+        assert!(out.voiced[0]);
+        assert!((out.amplitudes[0] - 800.0).abs() < 40.0);
+        assert!((out.amplitudes[1] - 400.0).abs() < 40.0);
+    }
+}
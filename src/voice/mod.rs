@@ -1,10 +1,12 @@
 //! Receive and decode voice-related data units.
 
+pub mod call;
 pub mod control;
 pub mod crypto;
 pub mod descramble;
 pub mod frame;
 pub mod frame_group;
 pub mod header;
+pub mod imbe;
 pub mod rand;
 pub mod term;
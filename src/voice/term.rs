@@ -1,14 +1,19 @@
-//! Decode voice Link Control (LC) terminator packets.
+//! Decode voice Link Control (LC) terminator packets, and encode simple and LC
+//! terminators for transmission.
 
 use collect_slice::CollectSlice;
 
-use bits::{Dibit, Hexbit, HexbitBytes};
+use bits::{Dibit, Hexbit, HexbitBytes, WordDibits};
 use buffer::{Buffer, VoiceLCTermWordStorage, VoiceExtraStorage};
 use coding::{reed_solomon, golay};
-use consts::LINK_CONTROL_BYTES;
+use consts::{EXTRA_HEXBITS, LC_TERM_WORD_DIBITS, LINK_CONTROL_BYTES};
 use error::Result;
+use message::nid::{DataUnit, NetworkAccessCode};
+use message::status::StatusCode;
+use message::transmit;
 use stats::{Stats, HasStats};
 use voice::control::LinkControlFields;
+use voice::frame_group::{Extra, LinkControlExtra};
 
 use error::P25Error::*;
 
@@ -76,3 +81,93 @@ impl VoiceLCTerminatorReceiver {
 impl HasStats for VoiceLCTerminatorReceiver {
     fn stats(&mut self) -> &mut Stats { &mut self.stats }
 }
+
+/// Code the given link control packet into the dibits carried by an LC terminator.
+pub fn encode_lc(lc: &LinkControlFields)
+    -> [Dibit; EXTRA_HEXBITS / 2 * LC_TERM_WORD_DIBITS]
+{
+    // The terminator carries the same RS-coded LC word as a frame group extra.
+    let hexbits = LinkControlExtra::encode_extra(lc);
+
+    let mut dibits = [Dibit::default(); EXTRA_HEXBITS / 2 * LC_TERM_WORD_DIBITS];
+
+    // Each pair of hexbits is coded as a 12-bit word.
+    hexbits.chunks(2)
+        .map(|h| (h[0].bits() as u16) << 6 | h[1].bits() as u16)
+        .flat_map(|w| WordDibits::new(golay::extended::encode(w) as u64,
+                                      LC_TERM_WORD_DIBITS))
+        .collect_slice_checked(&mut dibits[..]);
+
+    dibits
+}
+
+/// Construct a complete LC terminator data unit carrying the given link control packet,
+/// with the given NAC and status code.
+pub fn lc_terminator(nac: NetworkAccessCode, lc: &LinkControlFields, status: StatusCode)
+    -> Vec<Dibit>
+{
+    transmit::data_unit(nac, DataUnit::VoiceLCTerminator, encode_lc(lc).iter().cloned(),
+                        status)
+}
+
+/// Construct a complete simple terminator data unit with the given NAC and status code.
+pub fn simple_terminator(nac: NetworkAccessCode, status: StatusCode) -> Vec<Dibit> {
+    transmit::data_unit(nac, DataUnit::VoiceSimpleTerminator, None, status)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use consts::{NID_DIBITS, SYNC_SYMBOLS};
+    use message::status::{StatusDeinterleaver, StreamSymbol};
+
+    #[test]
+    fn test_encode_lc() {
+        let lc = LinkControlFields::new([0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0xAB, 0xCD,
+                                         0xEF]);
+
+        let mut dibits = encode_lc(&lc);
+        assert_eq!(dibits.len(), 144);
+
+        // Corrupt some of the coded words.
+        for i in (0..dibits.len()).filter(|i| i % 30 == 7) {
+            dibits[i] = Dibit::new(dibits[i].bits() ^ 0b11);
+        }
+
+        let mut recv = VoiceLCTerminatorReceiver::new();
+        let mut pkts: Vec<LinkControlFields> = dibits.iter()
+            .filter_map(|&d| recv.feed(d))
+            .map(|r| r.unwrap())
+            .collect();
+
+        assert_eq!(pkts.len(), 1);
+        assert_eq!(pkts.pop().unwrap().bytes(), lc.bytes());
+    }
+
+    #[test]
+    fn test_terminators() {
+        let lc = LinkControlFields::new([0x0F, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let dibits = lc_terminator(NetworkAccessCode::Default, &lc,
+                                   StatusCode::InboundIdle);
+        assert_eq!(dibits.len(), 216);
+
+        let mut stream = StatusDeinterleaver::new();
+        let mut recv = VoiceLCTerminatorReceiver::new();
+
+        let pkt = dibits[SYNC_SYMBOLS..].iter()
+            .filter_map(|&d| match stream.feed(d) {
+                StreamSymbol::Data(d) => Some(d),
+                StreamSymbol::Status(_) => None,
+            })
+            .skip(NID_DIBITS)
+            .filter_map(|d| recv.feed(d))
+            .next().unwrap().unwrap();
+
+        assert_eq!(pkt.bytes(), lc.bytes());
+
+        let dibits = simple_terminator(NetworkAccessCode::Default,
+                                       StatusCode::InboundIdle);
+        assert_eq!(dibits.len(), 72);
+    }
+}